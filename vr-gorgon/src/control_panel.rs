@@ -1,21 +1,40 @@
-use crate::gorgon1::MultiGorgonSettings;
+use crate::fonts;
+use crate::glyph_atlas::GlyphAtlas;
+use crate::gorgon1::{
+    GorgonFragmentShaderBuilder, GorgonPreview, GorgonSettings, MultiGorgonSettings,
+};
+use crate::i18n;
+use crate::keypad::{self, KeypadEntry, KeypadOutcome, KeypadTarget, KEYS};
+use crate::markup;
+use crate::menu::{
+    Menu, MenuItem, NumericField, PanelButton, PanelRect, RowLayout, BACKDROP_MARGIN, BACKDROP_TOP,
+    BUTTON_HALF_HEIGHT, FIELD_LABEL_HALF_SIZE, FIELD_LABEL_MAX_HALF_WIDTH, MENU_TOP,
+    PANEL_HALF_WIDTH,
+};
+use crate::shader_reload::ShaderFile;
 use crate::shaders::{BoxOutline, ConcentricRings, Latitude, Latitwod, SpriteRect};
-use crate::sprites::{SpriteLocation, SpriteSheet};
-use crate::text_painting;
+use crate::sprite_atlas::{SpriteAtlas, SpriteAtlasBuilder};
+use crate::sprites::SpriteLocation;
+use crate::surprise::{self, MUTATION_AMOUNTS};
+use crate::text_layout::{Align, TextStyle};
+use crate::text_painting::TextSpan;
+use crate::theme::{self, Theme};
 use crate::thumbstick_smoother::ThumbstickSmoother;
-use gl::types::{GLfloat, GLsizei};
+use crate::widgets::{self, Widgets};
+use gl::types::GLfloat;
 use gl_thin::gl_fancy::{GPUState, VertexBufferBundle, VertexBufferLite};
 use gl_thin::gl_helper::{GLErrorWrapper, Texture};
 use gl_thin::linear::{
     xr_matrix4x4f_create_scale, xr_matrix4x4f_create_translation, xr_matrix4x4f_uniform_scale,
     XrMatrix4x4f,
 };
-use once_cell::sync::Lazy;
 use openxr_sys::Vector2f;
-use rusttype::Font;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+/// empty pixels around each icon in [ControlPanel::icons]
+const ICON_PADDING: u32 = 2;
 
 pub fn fab_uv_square_lesser(
     gpu_state: &mut GPUState,
@@ -32,30 +51,74 @@ pub fn fab_uv_square_lesser(
 
 //
 
+/// a [SpriteRect] and the square it draws into
+type SpriteRectBundle = (SpriteRect, VertexBufferBundle<'static, GLfloat, u8>);
+
 pub struct SpriteRectG {
     shader: SpriteRect,
     square: VertexBufferBundle<'static, GLfloat, u8>,
-    bg: [f32; 4],
+    /// the same geometry and uniforms, but for glyphs from a [GlyphAtlas]
+    text_shader: SpriteRect,
+    text_square: VertexBufferBundle<'static, GLfloat, u8>,
+    /// for [SpriteAtlas]es
+    rgba_shader: SpriteRect,
+    rgba_square: VertexBufferBundle<'static, GLfloat, u8>,
+    theme: Theme,
 }
 
 impl SpriteRectG {
-    const FG: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
-    const HIGHLIGHT: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+    const TRANSPARENT: [f32; 4] = [0.0; 4];
 
     pub fn new(
         buffers: &VertexBufferLite<'static, GLfloat, u8>,
         gpu_state: &mut GPUState,
     ) -> Result<Self, GLErrorWrapper> {
-        let shader = SpriteRect::new()?;
-        let square =
-            VertexBufferBundle::from_buffers(gpu_state, buffers, 4, &shader.attributes_tuples(2))?;
+        let (shader, square) = Self::bundle(SpriteRect::new()?, buffers, gpu_state)?;
+        let (text_shader, text_square) =
+            Self::compile(SpriteRect::sdf_text_fragment_shader(), buffers, gpu_state)?;
+        let (rgba_shader, rgba_square) =
+            Self::compile(SpriteRect::rgba_fragment_shader(), buffers, gpu_state)?;
         Ok(Self {
             shader,
             square,
-            bg: [1.0, 1.0, 1.0, 0.5],
+            text_shader,
+            text_square,
+            rgba_shader,
+            rgba_square,
+            theme: Theme::default(),
         })
     }
 
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    fn compile(
+        fragment_shader: &str,
+        buffers: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<SpriteRectBundle, GLErrorWrapper> {
+        Self::bundle(
+            SpriteRect::with_fragment_shader(fragment_shader)?,
+            buffers,
+            gpu_state,
+        )
+    }
+
+    fn bundle(
+        shader: SpriteRect,
+        buffers: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<SpriteRectBundle, GLErrorWrapper> {
+        let square =
+            VertexBufferBundle::from_buffers(gpu_state, buffers, 4, &shader.attributes_tuples(2))?;
+        Ok((shader, square))
+    }
+
     pub fn draw(
         &self,
         matrix: &XrMatrix4x4f,
@@ -71,12 +134,120 @@ impl SpriteRectG {
             offset,
             texture,
             fg,
-            &self.bg,
+            &self.theme.bg,
             &self.square,
             gpu_state,
         )
     }
 
+    /// replace the fragment shader, keeping the old one if the new source fails to compile
+    pub fn reload(
+        &mut self,
+        fragment_shader: &str,
+        buffers: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        (self.shader, self.square) = Self::compile(fragment_shader, buffers, gpu_state)?;
+        Ok(())
+    }
+
+    /// like [Self::reload], for the shader [Self::draw_text] uses
+    pub fn reload_text(
+        &mut self,
+        fragment_shader: &str,
+        buffers: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        (self.text_shader, self.text_square) = Self::compile(fragment_shader, buffers, gpu_state)?;
+        Ok(())
+    }
+
+    /// like [Self::reload], for the shader [Self::draw_sprite] uses
+    pub fn reload_rgba(
+        &mut self,
+        fragment_shader: &str,
+        buffers: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        (self.rgba_shader, self.rgba_square) = Self::compile(fragment_shader, buffers, gpu_state)?;
+        Ok(())
+    }
+
+    /// Image `name` from `atlas` centered in `rect`, as big as fits, tinted by `tint`, with nothing behind it.
+    /// Draws nothing if `atlas` has no such image.
+    pub fn draw_sprite(
+        &self,
+        matrix: &XrMatrix4x4f,
+        atlas: &SpriteAtlas,
+        name: &str,
+        tint: &[f32; 4],
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        match (atlas.sprite(name), atlas.size(name)) {
+            (Some(sprite), Some([w, h])) => self.draw_rgba_fitted(
+                matrix,
+                &sprite,
+                [w as f32, h as f32],
+                tint,
+                &Self::TRANSPARENT,
+                rect,
+                gpu_state,
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// `sprite`, which is `size` pixels, centered in `rect` and as big as fits
+    #[allow(clippy::too_many_arguments)]
+    fn draw_rgba_fitted(
+        &self,
+        matrix: &XrMatrix4x4f,
+        sprite: &SpriteLocation,
+        size: [f32; 2],
+        tint: &[f32; 4],
+        bg: &[f32; 4],
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let [w, h] = size;
+        let k = (rect.half_size[1] * 2.0 / h).min(rect.half_size[0] * 2.0 / w);
+        let fitted = PanelRect {
+            center: rect.center,
+            half_size: [w * k / 2.0, h * k / 2.0],
+        };
+        self.draw_rgba_stretched(matrix, sprite, tint, bg, &fitted, gpu_state)
+    }
+
+    /// `sprite` (part of an RGBA texture, like one from a [SpriteAtlas]) stretched to fill `rect`,
+    /// tinted by `tint`, with nothing behind it
+    pub fn draw_sprite_stretched(
+        &self,
+        matrix: &XrMatrix4x4f,
+        sprite: &SpriteLocation,
+        tint: &[f32; 4],
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        self.draw_rgba_stretched(matrix, sprite, tint, &Self::TRANSPARENT, rect, gpu_state)
+    }
+
+    fn draw_rgba_stretched(
+        &self,
+        matrix: &XrMatrix4x4f,
+        sprite: &SpriteLocation,
+        tint: &[f32; 4],
+        bg: &[f32; 4],
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let m2 = matrix
+            * xr_matrix4x4f_create_translation(rect.center[0], rect.center[1], 0.0)
+            * xr_matrix4x4f_create_scale(rect.half_size[0], rect.half_size[1], 1.0);
+        self.rgba_shader
+            .draw2(&m2, sprite, tint, bg, &self.rgba_square, gpu_state)
+    }
+
     pub fn draw2(
         &self,
         matrix: &XrMatrix4x4f,
@@ -84,17 +255,139 @@ impl SpriteRectG {
         gpu_state: &mut GPUState,
         fg: &[f32; 4],
     ) -> Result<(), GLErrorWrapper> {
-        let bg = &self.bg;
+        let bg = &self.theme.bg;
         self.shader
             .draw2(matrix, sprite, fg, bg, &self.square, gpu_state)
     }
 
-    pub fn fg_for(highlight: bool) -> &'static [f32; 4] {
-        if highlight {
-            &Self::HIGHLIGHT
-        } else {
-            &Self::FG
+    /// `text` centered in `rect`, as big as fits, composed from glyphs in `atlas`
+    pub fn draw_text(
+        &self,
+        atlas: &GlyphAtlas,
+        text: &str,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        highlight: bool,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let style = TextStyle::centered();
+        self.draw_text_block(atlas, text, &style, matrix, rect, highlight, gpu_state)
+    }
+
+    /// like [Self::draw_text], but with nothing behind the text, for labels on widgets that have their own background
+    pub fn draw_caption(
+        &self,
+        atlas: &GlyphAtlas,
+        text: &str,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        highlight: bool,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let style = TextStyle::centered();
+        let spans = [TextSpan::new(text, *self.theme.fg_for(highlight))];
+        self.draw_glyphs(atlas, &spans, &style, matrix, rect, None, gpu_state)
+    }
+
+    /// `spans` (from [crate::markup::parse]) centered in `rect` over the usual background,
+    /// each in its own color.  Like [Self::draw_text], it is as big as fits.
+    pub fn draw_spans(
+        &self,
+        atlas: &GlyphAtlas,
+        spans: &[TextSpan],
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let style = TextStyle::centered();
+        let bg = Some(&self.theme.bg);
+        self.draw_glyphs(atlas, spans, &style, matrix, rect, bg, gpu_state)
+    }
+
+    /// `text` laid out according to `style` and made as big as fits in `rect` (scaled by [Theme::font_scale]).
+    /// The block sits against the side of `rect` that [TextStyle::align] says, and is centered vertically.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_block(
+        &self,
+        atlas: &GlyphAtlas,
+        text: &str,
+        style: &TextStyle,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        highlight: bool,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let spans = [TextSpan::new(text, *self.theme.fg_for(highlight))];
+        let bg = Some(&self.theme.bg);
+        self.draw_glyphs(atlas, &spans, style, matrix, rect, bg, gpu_state)
+    }
+
+    /// [Self::draw_text_block] for `spans`, each glyph in the color of its span, over a box of `bg` if there is one
+    #[allow(clippy::too_many_arguments)]
+    fn draw_glyphs(
+        &self,
+        atlas: &GlyphAtlas,
+        spans: &[TextSpan],
+        style: &TextStyle,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        bg: Option<&[f32; 4]>,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let layout = atlas.layout_spans(spans, style, gpu_state)?;
+        let [w, h] = layout.size;
+        if w <= 0.0 || h <= 0.0 {
+            return Ok(());
         }
+        // panel units per pixel
+        let k =
+            (rect.half_size[1] * 2.0 / h).min(rect.half_size[0] * 2.0 / w) * self.theme.font_scale;
+        let left = match style.align {
+            Align::Left => rect.center[0] - rect.half_size[0],
+            Align::Center => rect.center[0] - w * k / 2.0,
+            Align::Right => rect.center[0] + rect.half_size[0] - w * k,
+        };
+        let top = rect.center[1] - h * k / 2.0;
+
+        if let Some(bg) = bg {
+            let m_bg = matrix
+                * xr_matrix4x4f_create_translation(left + w * k / 2.0, rect.center[1], 0.0)
+                * xr_matrix4x4f_create_scale(w * k / 2.0, h * k / 2.0, 1.0);
+            let blank = SpriteLocation::new([0.0; 2], [0.0; 2], atlas.texture());
+            self.draw2(&m_bg, &blank, gpu_state, bg)?;
+        }
+
+        for (idx, glyph) in layout.glyphs.iter().enumerate() {
+            let span = &spans[glyph.span];
+            let [x0, y0, x1, y1] = glyph.rect;
+            // without a bold font, bold is faked by drawing each glyph again a little to the right
+            let smear = if span.bold && span.icon.is_none() && atlas.fakes_bold() {
+                atlas.pixel_size() * span.size / 20.0
+            } else {
+                0.0
+            };
+            // each glyph slightly in front of the last, so overlapping quads do not clip each other
+            let z = -0.005 - idx as f32 * 0.00005;
+            let copies = if smear > 0.0 { 2 } else { 1 };
+            for dx in (0..copies).map(|copy| copy as f32 * smear) {
+                let m2 = matrix
+                    * xr_matrix4x4f_create_translation(
+                        left + ((x0 + x1) / 2.0 + dx) * k,
+                        top + (y0 + y1) / 2.0 * k,
+                        z,
+                    )
+                    * xr_matrix4x4f_create_scale((x1 - x0) / 2.0 * k, (y1 - y0) / 2.0 * k, 1.0);
+                self.text_shader.draw2(
+                    &m2,
+                    &glyph.sprite(atlas.texture()),
+                    &span.color,
+                    &Self::TRANSPARENT,
+                    &self.text_square,
+                    gpu_state,
+                )?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -102,6 +395,20 @@ impl SpriteRectG {
 
 macro_rules! shader_plus_geometry {
     ($st:ident, $stg:ident) => {
+        shader_plus_geometry! {@without_draw $st, $stg}
+
+        impl $stg {
+            pub fn draw(
+                &self,
+                matrix: &XrMatrix4x4f,
+                gpu_state: &mut GPUState,
+            ) -> Result<(), GLErrorWrapper> {
+                self.shader.draw(matrix, &self.square, gpu_state)
+            }
+        }
+    };
+    // for shaders whose `draw` needs more than a matrix
+    (@without_draw $st:ident, $stg:ident) => {
         pub struct $stg {
             shader: $st,
             square: VertexBufferBundle<'static, GLfloat, u8>,
@@ -122,37 +429,80 @@ macro_rules! shader_plus_geometry {
                 Ok(Self { shader, square })
             }
 
-            pub fn draw(
-                &self,
-                matrix: &XrMatrix4x4f,
+            /// replace the fragment shader, keeping the old one if the new source fails to compile
+            pub fn reload(
+                &mut self,
+                fragment_shader: &str,
+                buffers: &VertexBufferLite<'static, GLfloat, u8>,
                 gpu_state: &mut GPUState,
             ) -> Result<(), GLErrorWrapper> {
-                self.shader.draw(matrix, &self.square, gpu_state)
+                let shader = $st::with_fragment_shader(fragment_shader)?;
+                self.square = VertexBufferBundle::from_buffers(
+                    gpu_state,
+                    buffers,
+                    4,
+                    &shader.attributes_tuples(2),
+                )?;
+                self.shader = shader;
+                Ok(())
             }
         }
     };
 }
 
 shader_plus_geometry! {ConcentricRings, ConcentricRingsG}
-shader_plus_geometry! {BoxOutline, BoxOutlineG}
+shader_plus_geometry! {@without_draw BoxOutline, BoxOutlineG}
 shader_plus_geometry! {Latitude, LatitudeG}
 shader_plus_geometry! {Latitwod, LatitwodG}
 
+impl BoxOutlineG {
+    pub fn draw(
+        &self,
+        matrix: &XrMatrix4x4f,
+        color: &[f32; 4],
+        thickness: f32,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        self.shader
+            .draw(matrix, color, thickness, &self.square, gpu_state)
+    }
+}
+
 //
 
 pub struct ControlPanel {
+    square: VertexBufferLite<'static, GLfloat, u8>,
     c_rings: ConcentricRingsG,
     sprite: SpriteRectG,
     latitude: LatitudeG,
     latitwod: LatitwodG,
-    sprites: SpriteSheet,
+    /// every string on the panel is drawn from this
+    atlas: GlyphAtlas,
     ring: BoxOutlineG,
+    /// the live render on each X/Y/Z toggle
+    layer_icons: LayerIcons,
+    /// what the [Widgets] are drawn from (see [Self::load_icons])
+    icons: SpriteAtlas,
+    menu: Menu,
     cursor: CPCursor,
+    /// the widget under the laser pointer
+    hover: Option<CPCursor>,
+    /// the button under the laser pointer
+    button_hover: Option<PanelButton>,
+    /// a numeric field being dragged with the trigger held
+    scrub: Option<Scrub>,
+    /// typing a value for a numeric field; while open it takes all the input
+    keypad: Option<KeypadEntry>,
+    /// the key under the laser pointer, an index into [KEYS]
+    keypad_hover: Option<usize>,
+    /// the seed behind the last surprise or mutation, so a good one can be reproduced,
+    /// and the message ID of which one it was
+    last_seed: Option<(&'static str, u64)>,
+    /// an index into [MUTATION_AMOUNTS]
+    mutation_amount: usize,
 
     thumbstick_x_smoother: ThumbstickSmoother,
     thumbstick_y_smoother: ThumbstickSmoother,
-
-    gorgon_val: RefCell<Option<ValueEditor>>,
 }
 
 impl ControlPanel {
@@ -168,364 +518,419 @@ impl ControlPanel {
             c_rings,
             latitude,
             latitwod: LatitwodG::new(&square, gpu_state)?,
-            // square,
-            sprites: SpriteSheet::new(gpu_state)?,
+            atlas: GlyphAtlas::new(fonts::registry(), 40.0)?,
             sprite,
             ring: BoxOutlineG::new(&square, gpu_state)?,
+            square,
+            layer_icons: LayerIcons::default(),
+            icons: Self::builtin_icons().build(gpu_state)?,
+            menu: Menu::gorgon_layers(),
             cursor: CPCursor::default(),
+            hover: None,
+            button_hover: None,
+            scrub: None,
+            keypad: None,
+            keypad_hover: None,
+            last_seed: None,
+            mutation_amount: 1,
             thumbstick_x_smoother: Default::default(),
             thumbstick_y_smoother: Default::default(),
-            gorgon_val: RefCell::new(None),
         })
     }
 
-    pub fn draw(
-        &self,
-        matrix: &XrMatrix4x4f,
-        gpu_state: &mut GPUState,
-        settings: &MultiGorgonSettings,
-    ) -> Result<(), GLErrorWrapper> {
-        let mut ring_sprite = None;
-        let mut cursor_y = -1.0;
-        cursor_y = self.header_1(matrix, gpu_state, cursor_y, &mut ring_sprite, settings)?;
-
-        if self.cursor.row == GorgonShape::Spiral {
-            cursor_y = self.spiral_menu(matrix, gpu_state, cursor_y, &mut ring_sprite, settings)?;
-        }
+    pub fn theme(&self) -> &Theme {
+        self.sprite.theme()
+    }
 
-        // if self.cursor.row == GorgonShape::Latitude {
-        //     ring_y = cursor_y + 0.25;
-        // }
-        cursor_y = self.header_2(matrix, gpu_state, cursor_y, &mut ring_sprite, settings)?;
+    /// the glyphs every string on the panel is drawn from
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
 
-        if self.cursor.row == GorgonShape::Latitude {
-            cursor_y = self.latitude_menu(matrix, gpu_state, cursor_y, &mut ring_sprite)?;
-        }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.sprite.set_theme(theme);
+    }
 
-        // if self.cursor.row == GorgonShape::Cartesian {
-        //     ring_y = cursor_y + 0.25;
-        // }
-        cursor_y = self.header_3(matrix, gpu_state, cursor_y, &mut ring_sprite, settings)?;
+    /// the images the [Widgets] and the keypad's symbol keys are drawn from
+    fn builtin_icons() -> SpriteAtlasBuilder {
+        let mut builder = SpriteAtlasBuilder::new(ICON_PADDING);
+        widgets::add_builtin_images(&mut builder);
+        keypad::add_key_images(&mut builder);
+        builder
+    }
 
-        #[allow(unused_assignments)]
-        if self.cursor.row == GorgonShape::Cartesian {
-            cursor_y = self.cartesian_menu(matrix, gpu_state, cursor_y, &mut ring_sprite)?;
+    fn widgets(&self) -> Widgets<'_> {
+        Widgets {
+            sprite: &self.sprite,
+            icons: &self.icons,
+            atlas: &self.atlas,
         }
+    }
 
-        if let Some(sprite_loc) = ring_sprite {
-            let dx = sprite_loc.offset[0];
-            let dy = sprite_loc.offset[1];
-            let thick = 0.06;
-            let sx = sprite_loc.scale[0] / (1.0 - 2.0 * thick);
-            let sy = sprite_loc.scale[1] / (1.0 - 2.0 * thick);
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(dx, dy, -0.02)
-                * xr_matrix4x4f_create_scale(sx, sy, 1.0);
-            self.ring.draw(&m2, gpu_state)?;
+    /// Rebuild [Self::icons] with the PNGs in `dir` replacing (or adding to) the built-in icons of the same name,
+    /// and save the packed atlas to `dump` so it can be checked for bleeding between icons.
+    pub fn load_icons(
+        &mut self,
+        dir: &Path,
+        dump: &Path,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let mut builder = Self::builtin_icons();
+        builder.add_png_directory(dir);
+        self.icons = builder.build(gpu_state)?;
+        match self.icons.dump_png(dump) {
+            Ok(()) => log::info!("wrote the icon atlas to {:?}", dump),
+            Err(e) => log::warn!("failed to write {:?}: {}", dump, e),
         }
         Ok(())
     }
 
-    fn header_1<'a>(
-        &'a self,
-        matrix: &XrMatrix4x4f,
+    /// recompile the panel shader that corresponds to `file`.
+    /// Returns `Ok(false)` if `file` is not one of ours.
+    pub fn reload_shader(
+        &mut self,
+        file: ShaderFile,
+        source: &str,
         gpu_state: &mut GPUState,
-        y0: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
-        settings: &MultiGorgonSettings,
-    ) -> Result<f32, GLErrorWrapper> {
-        let y = y0 + 0.25;
-        {
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(-0.75, y, 0.0)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            self.c_rings.draw(&m2, gpu_state)?;
-        }
-
-        let spiral_enable =
-            self.cursor.row == GorgonShape::Spiral && self.cursor.subrow == GorgonParam::Enable;
-        if spiral_enable {
-            let x = self.cursor.axis.x1();
-            *ring_loc = Some(SpriteLocation::new(
-                [0.25; 2],
-                [x, y],
-                &self.sprites.texture,
-            ));
-        }
-
-        for (i, (sprite, axis)) in [
-            (self.sprites.x(), GorgonAxis::X),
-            (self.sprites.y(), GorgonAxis::Y),
-            (self.sprites.z(), GorgonAxis::Z),
-        ]
-        .iter()
-        .enumerate()
-        {
-            let x = -0.25 + i as f32 * 0.5;
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y0 + sprite.scale[1], -0.01)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            let fg = if settings.lookup(GorgonShape::Spiral, *axis).enabled {
-                &SpriteRectG::HIGHLIGHT
-            } else {
-                &SpriteRectG::FG
-            };
-            self.sprite.draw2(&m2, &sprite, gpu_state, fg)?;
+    ) -> Result<bool, GLErrorWrapper> {
+        let square = &self.square;
+        match file {
+            ShaderFile::ConcentricRings => self.c_rings.reload(source, square, gpu_state)?,
+            ShaderFile::Latitude => self.latitude.reload(source, square, gpu_state)?,
+            ShaderFile::Latitwod => self.latitwod.reload(source, square, gpu_state)?,
+            ShaderFile::BoxOutline => self.ring.reload(source, square, gpu_state)?,
+            ShaderFile::SpriteRect => self.sprite.reload(source, square, gpu_state)?,
+            ShaderFile::SdfText => self.sprite.reload_text(source, square, gpu_state)?,
+            ShaderFile::SpriteRgba => self.sprite.reload_rgba(source, square, gpu_state)?,
+            _ => return Ok(false),
         }
-
-        Ok(y0 + 0.5)
+        Ok(true)
     }
 
-    fn spiral_menu<'a>(
-        &'a self,
+    /// `phase` animates the layer icons, like [crate::gorgon1::Gorgon1::paint]
+    pub fn draw(
+        &self,
         matrix: &XrMatrix4x4f,
+        phase: GLfloat,
         gpu_state: &mut GPUState,
-        y_top: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
         settings: &MultiGorgonSettings,
-    ) -> Result<f32, GLErrorWrapper> {
-        let mut y = y_top;
-
-        for (sprite, subrow) in [
-            (self.sprites.freq(), GorgonParam::Frequency),
-            (self.sprites.speed(), GorgonParam::Speed),
-            (self.sprites.amplitude(), GorgonParam::Amplitude),
-            (self.sprites.curl(), GorgonParam::Curl),
-        ] {
-            let x = -0.5;
-            let freq_sprite = sprite;
-            let y1 = y + freq_sprite.scale[1];
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y1, 0.0)
-                * xr_matrix4x4f_create_scale(freq_sprite.scale[0], freq_sprite.scale[1], 1.0);
-            self.sprite
-                .draw2(&m2, &freq_sprite, gpu_state, &SpriteRectG::FG)?;
-
-            if self.cursor.row == GorgonShape::Spiral && self.cursor.subrow == subrow {
-                let one = settings.lookup(self.cursor.row, self.cursor.axis);
-                let val = match subrow {
-                    GorgonParam::Frequency => Some(FormattableValue::U8(one.frequency)),
-                    GorgonParam::Speed => Some(FormattableValue::F32(one.speed)),
-                    GorgonParam::Amplitude => Some(FormattableValue::F32(one.amplitude)),
-                    GorgonParam::Curl => Some(FormattableValue::F32(one.curl)),
-                    _ => None,
-                };
-
-                if let Some(val) = val {
-                    self.paint_editor(matrix, &freq_sprite, x, y1, val, gpu_state)?;
+    ) -> Result<(), GLErrorWrapper> {
+        let layout = self
+            .menu
+            .layout(&self.cursor, self.field_label_half_width());
+        self.draw_frame(matrix, gpu_state, &layout)?;
+        for row in &layout {
+            match row.item {
+                MenuItem::Toggle => self.draw_header(matrix, phase, gpu_state, row, settings)?,
+                MenuItem::Numeric(field) => {
+                    self.draw_field(matrix, gpu_state, row, field, settings)?
                 }
+            }
+        }
 
-                *ring_loc = Some(SpriteLocation::new(
-                    freq_sprite.scale,
-                    [x, y1],
-                    &self.sprites.texture,
-                ))
+        // the thumbstick cursor, and the widget under the laser pointer
+        let hover = self.hover.filter(|hover| *hover != self.cursor);
+        for cursor in [Some(self.cursor), hover].into_iter().flatten() {
+            if let Some(row) = layout
+                .iter()
+                .find(|row| row.shape == cursor.row && row.item.param() == cursor.subrow)
+            {
+                self.draw_ring(matrix, &row.widget_rect(cursor.axis), gpu_state)?;
             }
+        }
+
+        let widgets = self.widgets();
+        for (button, rect) in PanelButton::layout(&layout) {
+            let hovered = self.button_hover == Some(button);
+            widgets.button(matrix, &rect, &button.label(), hovered, gpu_state)?;
+        }
+
+        self.draw_history(matrix, gpu_state, settings)?;
+        self.draw_seed(matrix, gpu_state)?;
+        self.draw_status(matrix, gpu_state, settings)?;
 
-            y += freq_sprite.scale[1] * 2.0;
+        if let Some(entry) = &self.keypad {
+            keypad::draw(entry, matrix, &self.widgets(), gpu_state)?;
+            let hover = self.keypad_hover.filter(|hover| *hover != entry.focus);
+            for idx in [Some(entry.focus), hover].into_iter().flatten() {
+                self.draw_ring(matrix, &keypad::key_rect(idx), gpu_state)?;
+            }
         }
-        Ok(y)
+        Ok(())
     }
 
-    fn paint_editor(
+    /// The backdrop behind the whole panel, from the status line to the buttons below the menu,
+    /// and a separator above each section but the first.
+    fn draw_frame(
         &self,
         matrix: &XrMatrix4x4f,
-        sprite: &SpriteLocation,
-        x: f32,
-        y1: f32,
-        val: FormattableValue,
         gpu_state: &mut GPUState,
+        layout: &[RowLayout],
     ) -> Result<(), GLErrorWrapper> {
-        self.update_gorgon_val_f(val, gpu_state);
+        let widgets = self.widgets();
+        let top = BACKDROP_TOP;
+        let bottom = PanelButton::layout(layout)
+            .map(|(_, rect)| rect.center[1] + BUTTON_HALF_HEIGHT)
+            .fold(Menu::bottom(layout), f32::max)
+            + BACKDROP_MARGIN;
+        let backdrop = PanelRect {
+            center: [0.0, (top + bottom) / 2.0],
+            half_size: [PANEL_HALF_WIDTH + BACKDROP_MARGIN, (bottom - top) / 2.0],
+        };
+        widgets.panel(matrix, &backdrop, gpu_state)?;
 
-        if let Some(editor) = self.gorgon_val.borrow().as_ref() {
-            let h = 0.25;
-            let w = editor.pix_dimensions.0 as f32 * h / editor.pix_dimensions.1 as f32;
-            let x2 = x + sprite.scale[0] + 0.1 + w;
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x2, y1, 0.0)
-                * xr_matrix4x4f_create_scale(w, h, 1.0);
-            self.sprite.draw2(
-                &m2,
-                &SpriteLocation::new([1.0; 2], [0.0; 2], &editor.texture),
-                gpu_state,
-                &SpriteRectG::FG,
-            )?;
+        for row in layout.iter().skip(1) {
+            if row.item == MenuItem::Toggle {
+                let line = PanelRect {
+                    center: [0.0, row.center_y - row.half_height],
+                    half_size: [PANEL_HALF_WIDTH, 0.008],
+                };
+                widgets.separator(matrix, &line, gpu_state)?;
+            }
         }
         Ok(())
     }
 
-    fn header_2<'a>(
-        &'a self,
+    /// how many edits can be undone, above the top right corner of the menu
+    fn draw_history(
+        &self,
         matrix: &XrMatrix4x4f,
         gpu_state: &mut GPUState,
-        y0: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
         settings: &MultiGorgonSettings,
-    ) -> Result<f32, GLErrorWrapper> {
-        let y = y0 + 0.25;
-        {
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(-0.75, y, 0.0)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            self.latitude.draw(&m2, gpu_state)?;
-        }
-
-        let latitude_enable =
-            self.cursor.row == GorgonShape::Latitude && self.cursor.subrow == GorgonParam::Enable;
-        if latitude_enable {
-            let x = self.cursor.axis.x1();
-            *ring_loc = Some(SpriteLocation::new(
-                [0.25; 2],
-                [x, y],
-                &self.sprites.texture,
-            ));
-        }
-
-        for (i, (sprite, axis)) in [
-            (self.sprites.x(), GorgonAxis::X),
-            (self.sprites.y(), GorgonAxis::Y),
-            (self.sprites.z(), GorgonAxis::Z),
-        ]
-        .iter()
-        .enumerate()
-        {
-            let x = -0.25 + i as f32 * 0.5;
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y0 + sprite.scale[1], -0.01)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            let fg = if settings.lookup(GorgonShape::Latitude, *axis).enabled {
-                &SpriteRectG::HIGHLIGHT
-            } else {
-                &SpriteRectG::FG
-            };
-            self.sprite.draw2(&m2, &sprite, gpu_state, fg)?;
+    ) -> Result<(), GLErrorWrapper> {
+        let (position, len) = settings.history().position();
+        if len == 0 {
+            return Ok(());
         }
+        let text = i18n::tr_args("undo-position", &[("position", &position), ("len", &len)]);
+        let rect = PanelRect {
+            center: [PANEL_HALF_WIDTH - 0.4, MENU_TOP - 0.12],
+            half_size: [0.4, 0.08],
+        };
+        let style = TextStyle {
+            align: Align::Right,
+            ..TextStyle::default()
+        };
+        self.sprite
+            .draw_text_block(&self.atlas, &text, &style, matrix, &rect, false, gpu_state)
+    }
 
-        Ok(y0 + 0.5)
+    /// the last seed, and how much the next mutation will change, above the top left corner of the menu
+    fn draw_seed(
+        &self,
+        matrix: &XrMatrix4x4f,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let percent = format!("{:.0}", MUTATION_AMOUNTS[self.mutation_amount] * 100.0);
+        let mutate = markup::escape(&i18n::tr_args("mutate-amount", &[("percent", &percent)]));
+        let text = match self.last_seed {
+            Some((action, seed)) => format!(
+                "{} [color={}]{}[/color]  {}",
+                markup::escape(&i18n::tr(action)),
+                theme::markup_color(&self.sprite.theme().accent),
+                seed,
+                mutate
+            ),
+            None => mutate,
+        };
+        let rect = PanelRect {
+            center: [-PANEL_HALF_WIDTH + 0.55, MENU_TOP - 0.12],
+            half_size: [0.55, 0.08],
+        };
+        let spans = markup::parse(&text, self.sprite.theme().fg);
+        self.sprite
+            .draw_spans(&self.atlas, &spans, matrix, &rect, gpu_state)
     }
 
-    fn latitude_menu<'a>(
-        &'a self,
+    /// a summary of the layer under the cursor, above the seed
+    fn draw_status(
+        &self,
         matrix: &XrMatrix4x4f,
         gpu_state: &mut GPUState,
-        y_top: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
-    ) -> Result<f32, GLErrorWrapper> {
-        let mut y = y_top;
-
-        for (sprite, subrow) in [
-            (self.sprites.freq(), GorgonParam::Frequency),
-            (self.sprites.speed(), GorgonParam::Speed),
-            (self.sprites.amplitude(), GorgonParam::Amplitude),
-            // (self.sprites.curl(), GorgonParam::Curl),
-        ] {
-            let x = -0.5;
-            let freq_sprite = sprite;
-            let y1 = y + freq_sprite.scale[1];
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y1, 0.0)
-                * xr_matrix4x4f_create_scale(freq_sprite.scale[0], freq_sprite.scale[1], 1.0);
-            self.sprite
-                .draw2(&m2, &freq_sprite, gpu_state, &SpriteRectG::FG)?;
-
-            if self.cursor.row == GorgonShape::Latitude && self.cursor.subrow == subrow {
-                *ring_loc = Some(SpriteLocation::new(
-                    freq_sprite.scale,
-                    [x, y1],
-                    &self.sprites.texture,
-                ))
-            }
+        settings: &MultiGorgonSettings,
+    ) -> Result<(), GLErrorWrapper> {
+        let (shape, axis) = (self.cursor.row, self.cursor.axis);
+        let layer = settings.lookup(shape, axis);
+        let theme = self.sprite.theme();
+        let enabled = if layer.enabled {
+            format!(
+                "[icon=dot][color={}]{}[/color]",
+                theme::markup_color(&theme.on),
+                markup::escape(&i18n::tr("layer-on"))
+            )
+        } else {
+            format!(
+                "[icon=ring][color={}]{}[/color]",
+                theme::markup_color(&theme.off),
+                markup::escape(&i18n::tr("layer-off"))
+            )
+        };
+        let frequency = GorgonParam::Frequency
+            .meta()
+            .format(layer.get(GorgonParam::Frequency));
+        let text = format!(
+            "[b]{} {}[/b]: {}, {}",
+            markup::escape(&shape.name()),
+            axis.letter().to_uppercase(),
+            enabled,
+            markup::escape(&frequency)
+        );
+        let rect = PanelRect {
+            center: [0.0, MENU_TOP - 0.3],
+            half_size: [PANEL_HALF_WIDTH, 0.08],
+        };
+        let spans = markup::parse(&text, theme.fg);
+        self.sprite
+            .draw_spans(&self.atlas, &spans, matrix, &rect, gpu_state)
+    }
 
-            y += freq_sprite.scale[1] * 2.0;
-        }
+    /// Half the width the field labels need to be shown at full height, so a language with
+    /// longer words gets a wider column instead of smaller text (up to a point).
+    fn field_label_half_width(&self) -> f32 {
+        let [min_half_width, half_height] = FIELD_LABEL_HALF_SIZE;
+        let style = TextStyle::centered();
+        self.menu
+            .fields()
+            .map(|field| {
+                let [w, h] = self.atlas.measure(&field.param.meta().label(), &style);
+                if h > 0.0 {
+                    w / h * half_height
+                } else {
+                    0.0
+                }
+            })
+            .fold(min_half_width, f32::max)
+            .min(FIELD_LABEL_MAX_HALF_WIDTH)
+    }
 
-        Ok(y)
+    /// outline `rect` with the [BoxOutline] shader
+    fn draw_ring(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let [dx, dy] = rect.center;
+        let theme = self.sprite.theme();
+        let thick = theme.outline_thickness;
+        let sx = rect.half_size[0] / (1.0 - 2.0 * thick);
+        let sy = rect.half_size[1] / (1.0 - 2.0 * thick);
+        let m2 = matrix
+            * xr_matrix4x4f_create_translation(dx, dy, -0.02)
+            * xr_matrix4x4f_create_scale(sx, sy, 1.0);
+        self.ring.draw(&m2, &theme.outline, thick, gpu_state)
     }
 
-    fn header_3<'a>(
-        &'a self,
+    /// The layer icon and the X/Y/Z toggles.
+    /// Each toggle shows a live render of its layer, whether or not it is enabled,
+    /// with its letter in one corner highlighted when it is, and a checkbox in the other.
+    fn draw_header(
+        &self,
         matrix: &XrMatrix4x4f,
+        phase: GLfloat,
         gpu_state: &mut GPUState,
-        y0: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
+        row: &RowLayout,
         settings: &MultiGorgonSettings,
-    ) -> Result<f32, GLErrorWrapper> {
-        let y = y0 + 0.25;
+    ) -> Result<(), GLErrorWrapper> {
+        let y = row.center_y;
         {
             let m2 = matrix
                 * xr_matrix4x4f_create_translation(-0.75, y, 0.0)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            self.latitwod.draw(&m2, gpu_state)?;
-        }
-
-        if self.cursor.row == GorgonShape::Cartesian && self.cursor.subrow == GorgonParam::Enable {
-            let x = self.cursor.axis.x1();
-            *ring_loc = Some(SpriteLocation::new(
-                [0.25; 2],
-                [x, y],
-                &self.sprites.texture,
-            ));
-        }
-
-        for (i, (sprite, axis)) in [
-            (self.sprites.x(), GorgonAxis::X),
-            (self.sprites.y(), GorgonAxis::Y),
-            (self.sprites.z(), GorgonAxis::Z),
-        ]
-        .iter()
-        .enumerate()
-        {
-            let x = -0.25 + i as f32 * 0.5;
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y0 + sprite.scale[1], -0.01)
-                * xr_matrix4x4f_uniform_scale(0.25);
-            let fg = if settings.lookup(GorgonShape::Cartesian, *axis).enabled {
-                &SpriteRectG::HIGHLIGHT
-            } else {
-                &SpriteRectG::FG
+                * xr_matrix4x4f_uniform_scale(row.half_height);
+            match row.shape {
+                GorgonShape::Spiral => self.c_rings.draw(&m2, gpu_state)?,
+                GorgonShape::Latitude => self.latitude.draw(&m2, gpu_state)?,
+                GorgonShape::Cartesian => self.latitwod.draw(&m2, gpu_state)?,
+            }
+        }
+
+        for axis in GorgonAxis::ALL {
+            let layer = settings.lookup(row.shape, axis);
+            let m_icon = matrix
+                * xr_matrix4x4f_create_translation(axis.x1(), y, 0.0)
+                * xr_matrix4x4f_uniform_scale(row.half_height);
+            self.layer_icons.paint(
+                row.shape,
+                axis,
+                layer,
+                &m_icon,
+                phase,
+                &self.square,
+                gpu_state,
+            )?;
+
+            let corner = row.half_height * 0.65;
+            // in front of the icon
+            let m_letter = matrix * xr_matrix4x4f_create_translation(0.0, 0.0, -0.01);
+            let letter = PanelRect {
+                center: [axis.x1() + corner, y + corner],
+                half_size: [row.half_height * 0.35; 2],
+            };
+            self.sprite.draw_text(
+                &self.atlas,
+                axis.letter(),
+                &m_letter,
+                &letter,
+                layer.enabled,
+                gpu_state,
+            )?;
+
+            let checkbox = PanelRect {
+                center: [axis.x1() - corner, y + corner],
+                half_size: [row.half_height * 0.25; 2],
             };
-            self.sprite.draw2(&m2, &sprite, gpu_state, fg)?;
+            self.widgets()
+                .checkbox(&m_letter, &checkbox, layer.enabled, gpu_state)?;
         }
 
-        Ok(y0 + 0.5)
+        Ok(())
     }
 
-    fn cartesian_menu<'a>(
-        &'a self,
+    /// the label of a numeric field, plus its value if the cursor is on it
+    fn draw_field(
+        &self,
         matrix: &XrMatrix4x4f,
         gpu_state: &mut GPUState,
-        y_top: f32,
-        ring_loc: &mut Option<SpriteLocation<'a>>,
-    ) -> Result<f32, GLErrorWrapper> {
-        let mut y = y_top;
-        for (sprite, subrow) in [
-            (self.sprites.freq(), GorgonParam::Frequency),
-            (self.sprites.speed(), GorgonParam::Speed),
-            (self.sprites.amplitude(), GorgonParam::Amplitude),
-            // (self.sprites.curl(), GorgonParam::Curl),
-        ] {
-            let x = -0.5;
-            let freq_sprite = sprite;
-            let y1 = y + freq_sprite.scale[1];
-            let m2 = matrix
-                * xr_matrix4x4f_create_translation(x, y1, 0.0)
-                * xr_matrix4x4f_create_scale(freq_sprite.scale[0], freq_sprite.scale[1], 1.0);
-            self.sprite
-                .draw2(&m2, &freq_sprite, gpu_state, &SpriteRectG::FG)?;
-
-            if self.cursor.row == GorgonShape::Cartesian && self.cursor.subrow == subrow {
-                *ring_loc = Some(SpriteLocation::new(
-                    freq_sprite.scale,
-                    [x, y1],
-                    &self.sprites.texture,
-                ))
-            }
-
-            y += freq_sprite.scale[1] * 2.0;
+        row: &RowLayout,
+        field: NumericField,
+        settings: &MultiGorgonSettings,
+    ) -> Result<(), GLErrorWrapper> {
+        let label = row.widget_rect(self.cursor.axis);
+        let meta = field.param.meta();
+        self.sprite
+            .draw_text(&self.atlas, &meta.label(), matrix, &label, false, gpu_state)?;
+
+        if self.cursor.row == row.shape && self.cursor.subrow == field.param {
+            let one = settings.lookup(self.cursor.row, self.cursor.axis);
+            let value = field.value(one);
+            // the value fills the rest of the row, right of the label, with a slider showing where it is in its range
+            let left = label.center[0] + label.half_size[0] + 0.1;
+            let half_width = (PANEL_HALF_WIDTH - left) / 2.0;
+            let half_height = label.half_size[1];
+            let text = PanelRect {
+                center: [left + half_width, row.center_y - half_height * 0.25],
+                half_size: [half_width, half_height * 0.75],
+            };
+            self.sprite.draw_text(
+                &self.atlas,
+                &meta.format(value),
+                matrix,
+                &text,
+                false,
+                gpu_state,
+            )?;
+            let slider = PanelRect {
+                center: [left + half_width, row.center_y + half_height * 0.75],
+                half_size: [half_width, half_height * 0.2],
+            };
+            let fraction = (value - meta.min) / (meta.max - meta.min);
+            self.widgets()
+                .slider(matrix, &slider, fraction, gpu_state)?;
         }
-
-        Ok(y)
+        Ok(())
     }
 
     pub(crate) fn handle_thumbstick(
@@ -537,58 +942,220 @@ impl ControlPanel {
         // log::debug!("thumbstick {}", dx);
 
         let smoothed_x = self.thumbstick_x_smoother.smooth_input(dx);
-        match self.cursor.subrow {
-            GorgonParam::Enable => match smoothed_x {
+        if dx.abs() < THUMBSTICK_REST {
+            settings.end_adjustment();
+        }
+        if let Some(entry) = &mut self.keypad {
+            let smoothed_y = self.thumbstick_y_smoother.smooth_input(delta.y);
+            entry.move_focus(smoothed_x as isize, -(smoothed_y as isize));
+            return;
+        }
+        match self.menu.item_at(&self.cursor) {
+            Some(MenuItem::Toggle) => match smoothed_x {
                 Ordering::Less => self.cursor.decr_x(),
                 Ordering::Equal => {}
                 Ordering::Greater => self.cursor.incr_x(),
             },
-            GorgonParam::Frequency => match smoothed_x {
-                Ordering::Less => settings.adjust_frequency(-1, self.cursor),
-                Ordering::Equal => {}
-                Ordering::Greater => settings.adjust_frequency(1, self.cursor),
-            },
-            GorgonParam::Speed => settings.adjust_speed(dx, self.cursor),
-            GorgonParam::Amplitude => settings.adjust_amplitude(dx, self.cursor),
-            GorgonParam::Curl => settings.adjust_curl(dx, self.cursor),
+            Some(MenuItem::Numeric(field)) if field.stepped => {
+                let step = field.param.meta().step;
+                match smoothed_x {
+                    Ordering::Less => field.adjust(settings, self.cursor, -step),
+                    Ordering::Equal => {}
+                    Ordering::Greater => field.adjust(settings, self.cursor, step),
+                }
+            }
+            Some(MenuItem::Numeric(field)) => {
+                field.adjust(settings, self.cursor, dx * field.param.meta().step)
+            }
+            None => {}
         }
 
         match self.thumbstick_y_smoother.smooth_input(delta.y) {
             // yeah, this is a little backwards
-            Ordering::Less => self.cursor.incr_y(),
+            Ordering::Less => self.menu.next_row(&mut self.cursor),
             Ordering::Equal => {}
-            Ordering::Greater => self.cursor.decr_y(),
+            Ordering::Greater => self.menu.prev_row(&mut self.cursor),
         }
     }
 
-    fn update_gorgon_val_f(&self, val2: FormattableValue, gpu_state: &mut GPUState) {
-        let stale = match self.gorgon_val.borrow().as_ref() {
-            Some(editor) => editor.val != val2,
-            _ => true,
+    /// Flips a toggle, or pops up the keypad for a numeric field.
+    /// While the keypad is open, presses the key the thumbstick has focused.
+    pub fn handle_a_click(&mut self, settings: &mut MultiGorgonSettings) {
+        if let Some(entry) = &self.keypad {
+            self.press_key(entry.focus, settings);
+            return;
+        }
+        match self.menu.item_at(&self.cursor) {
+            Some(MenuItem::Toggle) => settings.toggle_enabled(self.cursor),
+            Some(MenuItem::Numeric(field)) => {
+                self.keypad = Some(KeypadEntry::new(KeypadTarget::Field(field, self.cursor)))
+            }
+            None => {}
+        }
+    }
+
+    /// replace all the settings with random ones from a new seed
+    fn surprise_me(&mut self, settings: &mut MultiGorgonSettings) {
+        self.replay_surprise(settings, surprise::fresh_seed());
+    }
+
+    /// replace all the settings with the random ones `seed` makes, which are the same every time
+    fn replay_surprise(&mut self, settings: &mut MultiGorgonSettings, seed: u64) {
+        log::info!("surprise seed {}", seed);
+        surprise::surprise_me(settings, seed);
+        self.last_seed = Some(("seed-surprise", seed));
+    }
+
+    /// perturb the current settings by the chosen mutation amount
+    pub fn mutate(&mut self, settings: &mut MultiGorgonSettings) {
+        let seed = surprise::fresh_seed();
+        let amount = MUTATION_AMOUNTS[self.mutation_amount];
+        log::info!("mutation seed {} amount {}", seed, amount);
+        surprise::mutate(settings, seed, amount);
+        self.last_seed = Some(("seed-mutation", seed));
+    }
+
+    /// the fraction passed to [surprise::mutate]
+    pub fn mutation_amount(&self) -> f32 {
+        MUTATION_AMOUNTS[self.mutation_amount]
+    }
+
+    pub fn cycle_mutation_amount(&mut self) {
+        self.mutation_amount = (self.mutation_amount + 1) % MUTATION_AMOUNTS.len();
+    }
+
+    /// press `KEYS[idx]` on the open keypad, closing it if the entry was committed or cancelled
+    fn press_key(&mut self, idx: usize, settings: &mut MultiGorgonSettings) {
+        let entry = match &mut self.keypad {
+            Some(entry) => entry,
+            None => return,
         };
+        match (entry.press(KEYS[idx]), entry.target) {
+            (KeypadOutcome::Editing, _) => {}
+            (KeypadOutcome::Commit(value), KeypadTarget::Field(field, cursor)) => {
+                field.set(settings, cursor, value);
+                self.keypad = None;
+            }
+            (KeypadOutcome::Seed(seed), _) => {
+                self.keypad = None;
+                self.replay_surprise(settings, seed);
+            }
+            (KeypadOutcome::Commit(_), KeypadTarget::Seed) | (KeypadOutcome::Cancel, _) => {
+                self.keypad = None
+            }
+        }
+    }
 
-        if stale {
-            let font = default_font().unwrap();
-            let msg = format!("{}", val2);
-            if let Ok((texture, w, h)) =
-                text_painting::text_to_greyscale_texture(40.0, &msg, font, gpu_state)
-            {
-                *(self.gorgon_val.borrow_mut()) =
-                    Some(ValueEditor::new(val2, texture, w as _, h as _));
+    /// `hit` is where the laser pointer crosses the plane of the panel (in panel coordinates).
+    /// Pressing the trigger on a widget moves the cursor there and flips a toggle or
+    /// starts scrubbing a numeric field, which continues until the trigger is released.
+    /// Pressing it on a [PanelButton] does what the button says,
+    /// except for [PanelButton::Locale], which is returned for the caller to act on.
+    pub fn handle_pointer(
+        &mut self,
+        hit: Option<[f32; 2]>,
+        trigger_pressed: bool,
+        trigger_held: bool,
+        settings: &mut MultiGorgonSettings,
+    ) -> Option<PanelButton> {
+        if self.keypad.is_some() {
+            self.hover = None;
+            self.button_hover = None;
+            self.scrub = None;
+            self.keypad_hover = hit.and_then(keypad::key_at);
+            if let (true, Some(idx)) = (trigger_pressed, self.keypad_hover) {
+                self.press_key(idx, settings);
             }
+            return None;
         }
+        self.keypad_hover = None;
+
+        let layout = self
+            .menu
+            .layout(&self.cursor, self.field_label_half_width());
+        self.hover = hit.and_then(|point| Menu::hit_test(&layout, point, self.cursor.axis));
+        self.button_hover = hit.and_then(|point| PanelButton::hit_test(&layout, point));
+
+        if !trigger_held && self.scrub.take().is_some() {
+            settings.end_adjustment();
+        }
+
+        if let Some(scrub) = &mut self.scrub {
+            if let Some([x, _]) = hit {
+                scrub.drag(x, settings);
+            }
+        } else if trigger_pressed {
+            match self.button_hover {
+                Some(PanelButton::Surprise) => self.surprise_me(settings),
+                Some(PanelButton::Seed) => self.keypad = Some(KeypadEntry::new(KeypadTarget::Seed)),
+                Some(PanelButton::Locale) => return Some(PanelButton::Locale),
+                None => {}
+            }
+            if let (Some(target), Some([x, _])) = (self.hover, hit) {
+                self.cursor = target;
+                match self.menu.item_at(&target) {
+                    Some(MenuItem::Toggle) => settings.toggle_enabled(target),
+                    Some(MenuItem::Numeric(field)) => {
+                        self.scrub = Some(Scrub::new(field, target, x))
+                    }
+                    None => {}
+                }
+            }
+        }
+        None
     }
+}
 
-    pub fn handle_a_click(&mut self, settings: &mut MultiGorgonSettings) {
-        if let GorgonParam::Enable = self.cursor.subrow {
-            settings.toggle_enabled(self.cursor)
+//
+
+/// a thumbstick deflection smaller than this counts as released,
+/// which ends the undo step for the adjustment it was making
+const THUMBSTICK_REST: f32 = 0.1;
+
+/// how far (in panel units) the pointer moves to change a continuous value by its coarse step
+const SCRUB_DISTANCE_PER_COARSE_STEP: f32 = 1.0;
+/// how far (in panel units) the pointer moves to step an integer value by 1
+const SCRUB_DISTANCE_PER_STEP: f32 = 0.1;
+
+/// dragging the laser pointer sideways across a numeric field
+struct Scrub {
+    field: NumericField,
+    cursor: CPCursor,
+    last_x: f32,
+    /// movement of a stepped field which has not added up to a whole step yet
+    carry: f32,
+}
+
+impl Scrub {
+    fn new(field: NumericField, cursor: CPCursor, x: f32) -> Self {
+        Self {
+            field,
+            cursor,
+            last_x: x,
+            carry: 0.0,
+        }
+    }
+
+    fn drag(&mut self, x: f32, settings: &mut MultiGorgonSettings) {
+        let dx = x - self.last_x;
+        self.last_x = x;
+        if self.field.stepped {
+            self.carry += dx;
+            let steps = (self.carry / SCRUB_DISTANCE_PER_STEP).trunc();
+            self.carry -= steps * SCRUB_DISTANCE_PER_STEP;
+            let meta = self.field.param.meta();
+            self.field.adjust(settings, self.cursor, steps * meta.step);
+        } else {
+            let meta = self.field.param.meta();
+            let delta = meta.coarse_step * dx / SCRUB_DISTANCE_PER_COARSE_STEP;
+            self.field.adjust(settings, self.cursor, delta);
         }
     }
 }
 
 //
 
-#[derive(Default, PartialEq, Copy, Clone)]
+#[derive(Default, PartialEq, Copy, Clone, Debug)]
 pub enum GorgonShape {
     #[default]
     Spiral,
@@ -596,7 +1163,33 @@ pub enum GorgonShape {
     Cartesian,
 }
 
-#[derive(Default, PartialEq, Copy, Clone)]
+impl GorgonShape {
+    pub const ALL: [GorgonShape; 3] = [
+        GorgonShape::Spiral,
+        GorgonShape::Latitude,
+        GorgonShape::Cartesian,
+    ];
+
+    /// for files, unlike [Self::name]
+    pub fn key(&self) -> &'static str {
+        match self {
+            GorgonShape::Spiral => "spiral",
+            GorgonShape::Latitude => "latitude",
+            GorgonShape::Cartesian => "cartesian",
+        }
+    }
+
+    /// in the current locale
+    pub fn name(&self) -> String {
+        i18n::tr(match self {
+            GorgonShape::Spiral => "shape-spiral",
+            GorgonShape::Latitude => "shape-latitude",
+            GorgonShape::Cartesian => "shape-cartesian",
+        })
+    }
+}
+
+#[derive(Default, PartialEq, Copy, Clone, Debug)]
 pub enum GorgonAxis {
     #[default]
     X,
@@ -605,6 +1198,8 @@ pub enum GorgonAxis {
 }
 
 impl GorgonAxis {
+    pub const ALL: [GorgonAxis; 3] = [GorgonAxis::X, GorgonAxis::Y, GorgonAxis::Z];
+
     pub fn index(&self) -> u8 {
         match self {
             GorgonAxis::X => 0,
@@ -616,9 +1211,17 @@ impl GorgonAxis {
     pub fn x1(&self) -> f32 {
         self.index() as f32 * 0.5 - 0.25
     }
+
+    pub fn letter(&self) -> &'static str {
+        match self {
+            GorgonAxis::X => "x",
+            GorgonAxis::Y => "y",
+            GorgonAxis::Z => "z",
+        }
+    }
 }
 
-#[derive(Default, PartialEq, Copy, Clone)]
+#[derive(Default, PartialEq, Copy, Clone, Debug)]
 pub enum GorgonParam {
     #[default]
     Enable,
@@ -628,7 +1231,7 @@ pub enum GorgonParam {
     Curl,
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, PartialEq, Copy, Clone, Debug)]
 pub struct CPCursor {
     pub row: GorgonShape,
     pub axis: GorgonAxis,
@@ -651,101 +1254,60 @@ impl CPCursor {
             GorgonAxis::Z => GorgonAxis::Y,
         };
     }
-
-    pub fn incr_y(&mut self) {
-        (self.row, self.subrow) = match self.row {
-            GorgonShape::Spiral => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Spiral, GorgonParam::Frequency),
-                GorgonParam::Frequency => (GorgonShape::Spiral, GorgonParam::Speed),
-                GorgonParam::Speed => (GorgonShape::Spiral, GorgonParam::Amplitude),
-                GorgonParam::Amplitude => (GorgonShape::Spiral, GorgonParam::Curl),
-                GorgonParam::Curl => (GorgonShape::Latitude, GorgonParam::Enable),
-            },
-            GorgonShape::Latitude => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Latitude, GorgonParam::Frequency),
-                GorgonParam::Frequency => (GorgonShape::Latitude, GorgonParam::Speed),
-                GorgonParam::Speed => (GorgonShape::Latitude, GorgonParam::Amplitude),
-                GorgonParam::Amplitude | GorgonParam::Curl => {
-                    (GorgonShape::Cartesian, GorgonParam::Enable)
-                }
-            },
-
-            GorgonShape::Cartesian => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Cartesian, GorgonParam::Frequency),
-                GorgonParam::Frequency => (GorgonShape::Cartesian, GorgonParam::Speed),
-                GorgonParam::Speed => (GorgonShape::Cartesian, GorgonParam::Amplitude),
-                GorgonParam::Amplitude | GorgonParam::Curl => {
-                    (GorgonShape::Spiral, GorgonParam::Enable)
-                }
-            },
-        }
-    }
-    pub fn decr_y(&mut self) {
-        (self.row, self.subrow) = match self.row {
-            GorgonShape::Spiral => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Cartesian, GorgonParam::Amplitude),
-                GorgonParam::Frequency => (GorgonShape::Spiral, GorgonParam::Enable),
-                GorgonParam::Speed => (GorgonShape::Spiral, GorgonParam::Frequency),
-                GorgonParam::Amplitude => (GorgonShape::Spiral, GorgonParam::Speed),
-                GorgonParam::Curl => (GorgonShape::Spiral, GorgonParam::Amplitude),
-            },
-            GorgonShape::Latitude => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Spiral, GorgonParam::Curl),
-                GorgonParam::Frequency => (GorgonShape::Latitude, GorgonParam::Enable),
-                GorgonParam::Speed => (GorgonShape::Latitude, GorgonParam::Frequency),
-                GorgonParam::Amplitude => (GorgonShape::Latitude, GorgonParam::Speed),
-                GorgonParam::Curl => (GorgonShape::Latitude, GorgonParam::Amplitude),
-            },
-
-            GorgonShape::Cartesian => match self.subrow {
-                GorgonParam::Enable => (GorgonShape::Latitude, GorgonParam::Amplitude),
-                GorgonParam::Frequency => (GorgonShape::Cartesian, GorgonParam::Enable),
-                GorgonParam::Speed => (GorgonShape::Cartesian, GorgonParam::Frequency),
-                GorgonParam::Amplitude => (GorgonShape::Cartesian, GorgonParam::Speed),
-                GorgonParam::Curl => (GorgonShape::Cartesian, GorgonParam::Amplitude),
-            },
-        }
-    }
 }
 
 //
 
-pub fn default_font() -> Option<&'static Font<'static>> {
-    static RVAL: Lazy<Option<Font>> =
-        Lazy::new(|| Font::try_from_bytes(include_bytes!("AlbertText-Bold.ttf")));
-
-    RVAL.as_ref()
+/// Live renders of single layers.  The parameters are uniforms,
+/// so each layer's program is compiled once no matter how its settings change.
+#[derive(Default)]
+struct LayerIcons {
+    /// `None` if the program failed to compile; that icon is left blank rather than retried
+    icons: RefCell<Vec<(GorgonShape, GorgonAxis, Option<GorgonPreview>)>>,
 }
 
-//
-
-pub struct ValueEditor {
-    val: FormattableValue,
-    texture: Texture,
-    pix_dimensions: (GLsizei, GLsizei),
-}
-
-impl ValueEditor {
-    fn new(val: FormattableValue, texture: Texture, width: GLsizei, height: GLsizei) -> Self {
-        Self {
-            val,
-            texture,
-            pix_dimensions: (width, height),
+impl LayerIcons {
+    #[allow(clippy::too_many_arguments)]
+    fn paint(
+        &self,
+        shape: GorgonShape,
+        axis: GorgonAxis,
+        settings: &GorgonSettings,
+        matrix: &XrMatrix4x4f,
+        phase: GLfloat,
+        square: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let mut icons = self.icons.borrow_mut();
+        let idx = match icons.iter().position(|(s, a, _)| *s == shape && *a == axis) {
+            Some(idx) => idx,
+            None => {
+                let preview = match Self::build(shape, axis, square, gpu_state) {
+                    Ok(preview) => Some(preview),
+                    Err(e) => {
+                        log::error!("failed to build the {:?} {:?} icon: {}", shape, axis, e);
+                        None
+                    }
+                };
+                icons.push((shape, axis, preview));
+                icons.len() - 1
+            }
+        };
+        match &icons[idx].2 {
+            // the icon looks the same whether or not the layer is enabled
+            Some(preview) => preview.paint(matrix, phase, &[*settings], gpu_state),
+            None => Ok(()),
         }
     }
-}
 
-#[derive(PartialEq, Copy, Clone)]
-pub enum FormattableValue {
-    U8(u8),
-    F32(f32),
-}
-
-impl Display for FormattableValue {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            FormattableValue::U8(val) => write!(f, "{}", val),
-            FormattableValue::F32(val) => write!(f, "{:.1}", val),
-        }
+    fn build(
+        shape: GorgonShape,
+        axis: GorgonAxis,
+        square: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<GorgonPreview, GLErrorWrapper> {
+        let mut builder = GorgonFragmentShaderBuilder::default();
+        builder.add_uniform_layer(shape, axis);
+        GorgonPreview::new(&builder, square, gpu_state)
     }
 }
@@ -1,3 +1,5 @@
+use crate::error_overlay::ReportedError;
+use crate::fonts;
 use crate::scene::MyScene;
 use crate::xr_input::XrInputs;
 use crate::Drawable;
@@ -15,10 +17,12 @@ use glutin::context::{AsRawContext, ContextAttributesBuilder, RawContext};
 use glutin::display::{AsRawDisplay, Display, DisplayApiPreference, GlDisplay, RawDisplay};
 use log::debug;
 use openxr::{Graphics, SpaceLocation, View, ViewConfigurationView};
-use openxr_sys::{Time, ViewConfigurationType};
+use openxr_sys::{Posef, Time, ViewConfigurationType};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle, RawWindowHandle};
+use std::cell::Cell;
 use std::error::Error;
 use std::ffi::c_void;
+use std::path::PathBuf;
 use winit::event_loop::EventLoopWindowTarget;
 
 //
@@ -61,6 +65,19 @@ impl FrameEnv {
 
 //
 
+/// the poses located for a frame
+#[derive(Copy, Clone)]
+struct Located {
+    /// the right controller
+    controller_1: Option<SpaceLocation>,
+    /// the left controller's aim pose
+    pointer: Option<SpaceLocation>,
+    /// the left controller's grip, while it is squeezed
+    grip: Option<SpaceLocation>,
+    /// the VIEW reference space
+    head: Option<Posef>,
+}
+
 pub fn skybox_view_matrix(rotation: &XrQuaternionf) -> XrMatrix4x4f {
     let view_matrix = xr_matrix4x4f_create_from_quaternion(rotation);
     xr_matrix4x4f_invert_rigid_body(&view_matrix)
@@ -73,6 +90,8 @@ pub struct ActiveRenderer {
     pub gpu_state: GPUState,
 
     inputs: XrInputs,
+    /// where exported shaders are written, and where the `shaders/` hot-reload directory lives
+    data_dir: Option<PathBuf>,
 }
 
 impl Drawable for ActiveRenderer {
@@ -83,9 +102,12 @@ impl Drawable for ActiveRenderer {
         //
 
         match self.draw_inner() {
-            Ok(_) => {}
+            Ok(_) => self.scene.errors.clear(Self::DRAW_CONTEXT),
             Err(e) => {
-                log::error!("malfunction during draw_inner() {}", e);
+                // this also logs the error
+                self.scene
+                    .errors
+                    .report(ReportedError::runtime(Self::DRAW_CONTEXT, e));
             }
         };
     }
@@ -96,6 +118,11 @@ impl Drawable for ActiveRenderer {
 }
 
 impl ActiveRenderer {
+    /// the [ReportedError::context] for failures of [Self::draw_inner]
+    const DRAW_CONTEXT: &'static str = "draw_inner()";
+    /// the [ReportedError::context] for failures of [Self::paint_one_view]
+    const PAINT_CONTEXT: &'static str = "paint_one_view()";
+
     /// Create template to find OpenGL config.
     pub fn config_template(raw_window_handle: RawWindowHandle) -> ConfigTemplate {
         let builder = ConfigTemplateBuilder::new()
@@ -108,7 +135,10 @@ impl ActiveRenderer {
         builder.build()
     }
 
-    pub fn new<T>(event_loop: &EventLoopWindowTarget<T>) -> Result<Self, Box<dyn Error>> {
+    pub fn new<T>(
+        event_loop: &EventLoopWindowTarget<T>,
+        data_dir: Option<PathBuf>,
+    ) -> Result<Self, Box<dyn Error>> {
         let (display_ptr, raw_context) = Self::build_android_egl_context(event_loop)?;
 
         let mut gpu_state = GPUState::new();
@@ -121,7 +151,22 @@ impl ActiveRenderer {
             vcv0.recommended_image_rect_height,
             &mut gpu_state,
         )?;
-        let scene = MyScene::new(&mut gpu_state)?;
+        if let Some(dir) = &data_dir {
+            fonts::install_font_directory(&dir.join("fonts"));
+        }
+        let mut scene = MyScene::new(&mut gpu_state)?;
+        if let Some(dir) = &data_dir {
+            scene.watch_shader_directory(dir.join("shaders"));
+            scene.watch_locale_directory(dir.join("locales"));
+            scene.persist_panel_anchor(dir.join("panel-anchor.txt"));
+            scene.persist_gorgon_settings(dir.join("gorgon-settings.txt"));
+            scene.persist_theme(dir.join("panel-theme.txt"));
+            scene.load_icons(
+                &dir.join("icons"),
+                &dir.join("icon-atlas.png"),
+                &mut gpu_state,
+            )?;
+        }
 
         let inputs = XrInputs::new(&openxr.xr_instance, &openxr.xr_session)?;
 
@@ -131,6 +176,7 @@ impl ActiveRenderer {
             openxr,
             gpu_state,
             inputs,
+            data_dir,
         })
     }
 
@@ -190,11 +236,14 @@ impl ActiveRenderer {
 
     /// iterate through the various OpenXR views and paint them
     pub fn draw_inner(&mut self) -> Result<(), XrErrorWrapped> {
-        type Arg<'a> = (Option<SpaceLocation>, &'a mut GPUState);
+        type Arg<'a> = (Located, &'a mut GPUState);
         let gpu_state = &mut self.gpu_state;
 
         self.inputs.sync_actions(&self.openxr.xr_session).unwrap();
 
+        self.scene.poll_shader_directory(gpu_state);
+        self.scene.poll_locale_directory();
+
         if let Ok(action) = self.inputs.a_clicked(&self.openxr.xr_session) {
             if action.changed_since_last_sync && action.current_state {
                 if false {
@@ -207,10 +256,74 @@ impl ActiveRenderer {
             }
         }
 
+        if let Ok(action) = self.inputs.b_clicked(&self.openxr.xr_session) {
+            if action.changed_since_last_sync && action.current_state {
+                match &self.data_dir {
+                    Some(dir) => self.scene.export_shaders(dir),
+                    None => log::warn!("no data directory to export shaders into"),
+                }
+            }
+        }
+
+        if let Ok(action) = self.inputs.x_clicked(&self.openxr.xr_session) {
+            if action.changed_since_last_sync && action.current_state {
+                self.scene.undo();
+            }
+        }
+
+        if let Ok(action) = self.inputs.y_clicked(&self.openxr.xr_session) {
+            if action.changed_since_last_sync && action.current_state {
+                self.scene.redo();
+            }
+        }
+
+        if let Ok(action) = self
+            .inputs
+            .right_thumbstick_clicked(&self.openxr.xr_session)
+        {
+            if action.changed_since_last_sync && action.current_state {
+                self.scene.mutate();
+            }
+        }
+
+        if let Ok(action) = self.inputs.right_trigger(&self.openxr.xr_session) {
+            if action.changed_since_last_sync && action.current_state {
+                self.scene.cycle_mutation_amount();
+            }
+        }
+
         if let Ok(action) = self.inputs.right_joy(&self.openxr.xr_session) {
             self.scene.handle_thumbstick(action.current_state);
         }
 
+        let (cycle_anchor, left_stick_pressed) =
+            match self.inputs.left_thumbstick_clicked(&self.openxr.xr_session) {
+                Ok(action) => (
+                    action.changed_since_last_sync && action.current_state,
+                    action.current_state,
+                ),
+                Err(_) => (false, false),
+            };
+
+        if let Ok(action) = self.inputs.left_joy(&self.openxr.xr_session) {
+            self.scene
+                .handle_left_thumbstick(action.current_state, left_stick_pressed);
+        }
+
+        let (trigger_pressed, trigger_held) =
+            match self.inputs.left_trigger(&self.openxr.xr_session) {
+                Ok(action) => (
+                    action.changed_since_last_sync && action.current_state,
+                    action.current_state,
+                ),
+                Err(_) => (false, false),
+            };
+
+        let toggle_evolution = match self.inputs.left_menu_clicked(&self.openxr.xr_session) {
+            Ok(action) => action.changed_since_last_sync && action.current_state,
+            Err(_) => false,
+        };
+
         let before_paint = |openxr: &OpenXRComponent, frame_state: &openxr::FrameState| {
             // self.inputs.sync_actions(&openxr.xr_session).unwrap();
 
@@ -220,18 +333,41 @@ impl ActiveRenderer {
                 frame_state.predicted_display_time,
             );
 
+            let pointer = self.inputs.pointer_locate_if_active(
+                &openxr.xr_session,
+                &openxr.xr_space,
+                frame_state.predicted_display_time,
+            );
+
+            let grip = self.inputs.left_grip_locate_if_squeezed(
+                &openxr.xr_session,
+                &openxr.xr_space,
+                frame_state.predicted_display_time,
+            );
+
+            let head = self
+                .inputs
+                .head_locate(&openxr.xr_space, frame_state.predicted_display_time);
+
             if false {
                 debug!("space location {:?}", location.map(|sl| sl.pose));
             }
-            (location, gpu_state)
+            let located = Located {
+                controller_1: location,
+                pointer,
+                grip,
+                head,
+            };
+            (located, gpu_state)
         };
 
+        let paint_failed = Cell::new(false);
         let lambda = |view_i: &View,
                       vcv: &ViewConfigurationView,
                       predicted_display_time,
                       render_destination,
-                      (controller_1, gpu_state): &mut Arg| {
-            Self::paint_one_view(
+                      (located, gpu_state): &mut Arg| {
+            if let Err(e) = Self::paint_one_view(
                 view_i,
                 vcv,
                 predicted_display_time,
@@ -239,18 +375,50 @@ impl ActiveRenderer {
                 &self.frame_env,
                 render_destination,
                 gpu_state,
-                controller_1,
-            )
-            .unwrap();
+                &located.controller_1,
+            ) {
+                paint_failed.set(true);
+                self.scene
+                    .errors
+                    .report(ReportedError::runtime(Self::PAINT_CONTEXT, e));
+            }
+        };
+        // the poses are only located while painting, so the input that needs them is handled once we are done
+        let located = Cell::new(None);
+        let after_paint = |_: &OpenXRComponent, _: &openxr::FrameState, (frame_poses, _): Arg| {
+            located.set(Some(frame_poses));
         };
-        let after_paint = |_: &OpenXRComponent, _: &openxr::FrameState, _: Arg| {};
 
         self.openxr.paint_vr_multiview(
             before_paint,
             lambda,
             after_paint,
             ViewConfigurationType::PRIMARY_STEREO,
-        )
+        )?;
+
+        if !paint_failed.get() {
+            self.scene.errors.clear(Self::PAINT_CONTEXT);
+        }
+
+        if let Some(located) = located.take() {
+            if cycle_anchor {
+                self.scene.cycle_panel_anchor(located.controller_1.as_ref());
+            }
+            if toggle_evolution {
+                self.scene.toggle_evolution(located.head.as_ref());
+            }
+            self.scene
+                .update_panel_anchor(located.head.as_ref(), located.grip.as_ref());
+            self.scene.save_gorgon_settings();
+            self.scene.handle_pointer(
+                located.controller_1.as_ref(),
+                located.pointer.as_ref(),
+                trigger_pressed,
+                trigger_held,
+                &mut self.gpu_state,
+            );
+        }
+        Ok(())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -0,0 +1,264 @@
+use crate::control_panel::{fab_uv_square_lesser, SpriteRectG};
+use crate::glsl_include::{preprocess, LineOrigin};
+use crate::glyph_atlas::GlyphAtlas;
+use crate::menu::PanelRect;
+use crate::text_layout::TextStyle;
+use crate::theme::Theme;
+use gl_thin::gl_fancy::GPUState;
+use gl_thin::gl_helper::GLErrorWrapper;
+use gl_thin::linear::XrMatrix4x4f;
+use std::cell::RefCell;
+use std::fmt::Display;
+
+/// how tall a line is, in panel units, to match the size of the menu labels
+const LINE_HEIGHT: f32 = 0.05;
+/// lines wider than this (in panel units) wrap
+const WRAP_WIDTH: f32 = 2.0;
+/// how many lines of the driver's error message we show before the shader excerpts
+const MAX_MESSAGE_LINES: usize = 6;
+/// how many lines of GLSL to show on either side of an offending line
+const EXCERPT_CONTEXT: usize = 2;
+/// how many offending lines get an excerpt
+const MAX_EXCERPTS: usize = 3;
+
+/// An error worth showing to someone who is wearing the headset and can not see `adb logcat`.
+#[derive(Clone, PartialEq)]
+pub struct ReportedError {
+    /// what we were doing, like "gorgon shader" or "draw_inner()"
+    pub context: String,
+    pub message: String,
+    /// the GLSL that failed to compile, if this was a compile error
+    pub source: Option<String>,
+}
+
+impl ReportedError {
+    pub fn runtime(context: impl Into<String>, error: impl Display) -> Self {
+        Self {
+            context: context.into(),
+            message: error.to_string(),
+            source: None,
+        }
+    }
+
+    pub fn shader_compile(
+        context: impl Into<String>,
+        error: impl Display,
+        source: impl Into<String>,
+    ) -> Self {
+        Self {
+            context: context.into(),
+            message: error.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// the text of the overlay: the context, the start of the message, and
+    /// line-numbered excerpts of the GLSL around any line the message complains about.
+    pub fn lines(&self) -> Vec<String> {
+        let mut rval = vec![format!("{}:", self.context)];
+        rval.extend(
+            self.message
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .take(MAX_MESSAGE_LINES)
+                .map(|line| format!("  {}", line.trim())),
+        );
+
+        if let Some(source) = &self.source {
+            // the compiler saw the shader with its #includes expanded, so its line numbers refer to that
+            let preprocessed = preprocess(source);
+            let source_lines: Vec<&str> = preprocessed.text.lines().collect();
+            for line_number in error_line_numbers(&self.message)
+                .into_iter()
+                .take(MAX_EXCERPTS)
+            {
+                rval.push(String::new());
+                if let Some(LineOrigin {
+                    snippet: Some(snippet),
+                    line,
+                }) = preprocessed.origin(line_number)
+                {
+                    rval.push(format!("in #include \"{}\" line {}:", snippet, line));
+                }
+                rval.extend(shader_excerpt(&source_lines, line_number, EXCERPT_CONTEXT));
+            }
+        }
+
+        rval
+    }
+}
+
+/// Pick the line numbers out of a GLSL compiler log.
+/// Drivers mostly use `ERROR: 0:12: ...` or `0:12(5): ...`, where 0 is the source string index.
+pub fn error_line_numbers(message: &str) -> Vec<usize> {
+    let mut rval = vec![];
+    for line in message.lines() {
+        let fields: Vec<&str> = line.split(':').map(|field| field.trim()).collect();
+        for pair in fields.windows(2) {
+            let leading_digits = |field: &str| -> Option<usize> {
+                let digits: String = field.chars().take_while(|c| c.is_ascii_digit()).collect();
+                digits.parse().ok()
+            };
+            let string_index = pair[0].parse::<usize>().ok();
+            if let (Some(_), Some(line_number)) = (string_index, leading_digits(pair[1])) {
+                if !rval.contains(&line_number) {
+                    rval.push(line_number);
+                }
+                break;
+            }
+        }
+    }
+    rval
+}
+
+/// the lines of `source` around the 1-based `line_number`, with the offending line marked
+pub fn shader_excerpt(source_lines: &[&str], line_number: usize, context: usize) -> Vec<String> {
+    let first = line_number.saturating_sub(context).max(1);
+    let last = (line_number + context).min(source_lines.len());
+    (first..=last)
+        .map(|n| {
+            let marker = if n == line_number { '>' } else { ' ' };
+            format!("{}{:4}| {}", marker, n, source_lines[n - 1])
+        })
+        .collect()
+}
+
+//
+
+/// A panel showing the most recent [ReportedError], painted next to the control panel
+/// with the same glyphs as the panel's own labels.
+pub struct ErrorOverlay {
+    sprite: SpriteRectG,
+    error: RefCell<Option<ReportedError>>,
+    /// the text of `error` and where it goes, or `None` if it needs to be measured again.
+    /// The glyphs themselves are cached by the [GlyphAtlas].
+    text: RefCell<Option<PlacedText>>,
+}
+
+/// the text of an error, wrapped by `style` to fit `rect`
+struct PlacedText {
+    text: String,
+    style: TextStyle,
+    rect: PanelRect,
+}
+
+impl ErrorOverlay {
+    pub fn new(gpu_state: &mut GPUState) -> Result<Self, GLErrorWrapper> {
+        let square = fab_uv_square_lesser(gpu_state)?;
+        Ok(Self {
+            sprite: SpriteRectG::new(&square, gpu_state)?,
+            error: RefCell::new(None),
+            text: RefCell::new(None),
+        })
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.sprite.set_theme(theme);
+    }
+
+    /// This takes `&self` so errors can be reported from the middle of painting.
+    pub fn report(&self, error: ReportedError) {
+        if self.error.borrow().as_ref() == Some(&error) {
+            return;
+        }
+        log::error!("{}: {}", error.context, error.message);
+        self.error.replace(Some(error));
+        self.text.replace(None);
+    }
+
+    /// dismiss the current error if it came from `context`
+    pub fn clear(&self, context: &str) {
+        let matches = match self.error.borrow().as_ref() {
+            Some(error) => error.context == context,
+            None => false,
+        };
+        if matches {
+            self.error.replace(None);
+            self.text.replace(None);
+        }
+    }
+
+    /// `matrix` is the control panel's matrix and `atlas` its glyphs; the overlay is painted to its right
+    pub fn draw(
+        &self,
+        matrix: &XrMatrix4x4f,
+        atlas: &GlyphAtlas,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let error = self.error.borrow();
+        let error = match error.as_ref() {
+            Some(error) => error,
+            None => return Ok(()),
+        };
+
+        let mut placed = self.text.borrow_mut();
+        let placed = placed.get_or_insert_with(|| Self::place(error, atlas));
+        self.sprite.draw_text_block(
+            atlas,
+            &placed.text,
+            &placed.style,
+            matrix,
+            &placed.rect,
+            true,
+            gpu_state,
+        )
+    }
+
+    fn place(error: &ReportedError, atlas: &GlyphAtlas) -> PlacedText {
+        let text = error.lines().join("\n");
+        // panel units per pixel
+        let k = LINE_HEIGHT / atlas.pixel_size();
+        let style = TextStyle {
+            max_width: Some(WRAP_WIDTH / k),
+            ..TextStyle::default()
+        };
+        let [w, h] = atlas.measure(&text, &style);
+        let half_size = [w * k / 2.0, h * k / 2.0];
+        let rect = PanelRect {
+            center: [1.1 + half_size[0], -1.0 + half_size[1]],
+            half_size,
+        };
+        PlacedText { text, style, rect }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_numbers_come_from_either_driver_format() {
+        let adreno = "ERROR: 0:12: 'colour' : undeclared identifier\n\
+                      ERROR: 0:30: '' : compilation terminated\n\
+                      ERROR: 2 compilation errors.  No code generated.";
+        assert_eq!(error_line_numbers(adreno), vec![12, 30]);
+        let mali = "0:7(14): error: `colour' undeclared\n0:7(3): error: type mismatch";
+        assert_eq!(error_line_numbers(mali), vec![7]);
+        assert_eq!(error_line_numbers("link failed"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn excerpts_mark_the_offending_line() {
+        let source = ["a", "b", "c", "d", "e", "f"];
+        assert_eq!(
+            shader_excerpt(&source, 3, 1),
+            vec!["    2| b", ">   3| c", "    4| d"]
+        );
+    }
+
+    #[test]
+    fn excerpts_of_the_first_line_start_there() {
+        let source = ["a", "b", "c", "d"];
+        assert_eq!(
+            shader_excerpt(&source, 1, 2),
+            vec![">   1| a", "    2| b", "    3| c"]
+        );
+    }
+
+    #[test]
+    fn excerpts_stop_at_the_end_of_the_source() {
+        let source = ["a", "b", "c"];
+        assert_eq!(shader_excerpt(&source, 4, 2), vec!["    2| b", "    3| c"]);
+        assert_eq!(shader_excerpt(&source, 40, 2), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::control_panel::{fab_uv_square_lesser, BoxOutlineG, GorgonAxis, GorgonShape};
+use crate::gorgon1::{
+    GorgonFragmentShaderBuilder, GorgonPreview, GorgonSettings, MultiGorgonSettings,
+};
+use crate::laser_pointer::Ray;
+use crate::panel_anchor::{pose_compose, pose_matrix};
+use crate::surprise::{self, SeededRng};
+use crate::theme::Theme;
+use gl::types::GLfloat;
+use gl_thin::gl_fancy::{GPUState, VertexBufferLite};
+use gl_thin::gl_helper::GLErrorWrapper;
+use gl_thin::linear::{
+    xr_matrix4x4f_create_scale, xr_matrix4x4f_invert_rigid_body, xr_matrix4x4f_uniform_scale,
+    XrMatrix4x4f,
+};
+use openxr_sys::{Posef, Quaternionf, Vector3f};
+
+/// how many variants each generation offers.  The arc has room for 4 to 9.
+pub const CANDIDATE_COUNT: usize = 6;
+/// a generation with more candidates than this is split into two rows
+const MAX_ROW_LENGTH: usize = 5;
+/// distance from the head to the previews, in meters
+const PREVIEW_RADIUS: f32 = 1.2;
+/// half the width of a preview, in meters
+const PREVIEW_HALF_SIZE: f32 = 0.15;
+/// the angle between neighbouring previews in a row, in radians
+const COLUMN_ANGLE: f32 = 0.45;
+const ROW_SPACING: f32 = 0.4;
+
+/// every layer of a [MultiGorgonSettings], as returned by [MultiGorgonSettings::layers]
+pub type Layers = Vec<(GorgonShape, GorgonAxis, GorgonSettings)>;
+
+/// the enabled layers of a candidate, which is all a [GorgonPreview] program depends on
+type LayerKinds = Vec<(GorgonShape, GorgonAxis)>;
+
+struct Candidate {
+    layers: Layers,
+    kinds: LayerKinds,
+    /// the parameters of each of [Self::kinds], for [GorgonPreview::paint]
+    uniforms: Vec<GorgonSettings>,
+    pose: Posef,
+}
+
+impl Candidate {
+    fn new(layers: Layers, pose: Posef) -> Self {
+        let (kinds, uniforms) = layers
+            .iter()
+            .filter(|(_, _, settings)| settings.enabled)
+            .map(|(shape, axis, settings)| ((*shape, *axis), *settings))
+            .unzip();
+        Self {
+            layers,
+            kinds,
+            uniforms,
+            pose,
+        }
+    }
+}
+
+/// The previews currently on offer.  The first candidate is always the parent itself,
+/// so the user can keep breeding from it if none of the children are an improvement.
+struct Generation {
+    number: u32,
+    candidates: Vec<Candidate>,
+    /// the head's pose when breeding started, with the pitch and roll removed
+    center: Posef,
+    /// the candidate under the laser pointer
+    hover: Option<usize>,
+}
+
+/// Interactive evolution: pick the best of several mutations of the current settings,
+/// and it becomes the parent of the next generation.
+pub struct Evolution {
+    square: VertexBufferLite<'static, GLfloat, u8>,
+    ring: BoxOutlineG,
+    /// the colors of the ring around the hovered candidate
+    theme: Theme,
+    generation: Option<Generation>,
+    /// Programs for each combination of enabled layers, shared by every candidate that uses it.
+    /// `None` if the program failed to compile.
+    previews: Vec<(LayerKinds, Option<GorgonPreview>)>,
+}
+
+impl Evolution {
+    pub fn new(gpu_state: &mut GPUState) -> Result<Self, GLErrorWrapper> {
+        let square = fab_uv_square_lesser(gpu_state)?;
+        Ok(Self {
+            ring: BoxOutlineG::new(&square, gpu_state)?,
+            square,
+            theme: Theme::default(),
+            generation: None,
+            previews: vec![],
+        })
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.generation.is_some()
+    }
+
+    /// is the laser pointer on one of the candidates?
+    pub fn is_hovering(&self) -> bool {
+        matches!(&self.generation, Some(generation) if generation.hover.is_some())
+    }
+
+    /// Arrange the first generation in an arc in front of `head`, bred from `parent`.
+    pub fn start(&mut self, parent: &MultiGorgonSettings, head: &Posef, amount: f32) {
+        let center = level_pose(head);
+        self.breed(parent.layers(), center, 1, amount);
+    }
+
+    pub fn stop(&mut self) {
+        self.generation = None;
+    }
+
+    /// Hover over the candidate under the laser pointer, and pick it if the trigger was just pressed.
+    /// Picking breeds the next generation, and returns the chosen layers for the caller to apply.
+    pub fn handle_pointer(
+        &mut self,
+        pointer: Option<&Posef>,
+        trigger_pressed: bool,
+        amount: f32,
+    ) -> Option<Layers> {
+        let generation = self.generation.as_mut()?;
+        generation.hover = pointer.and_then(|pointer| {
+            let ray = Ray::from_pose(pointer);
+            generation
+                .candidates
+                .iter()
+                .position(|candidate| preview_hit(&ray, &candidate.pose))
+        });
+
+        let picked = match (trigger_pressed, generation.hover) {
+            (true, Some(idx)) => generation.candidates.swap_remove(idx).layers,
+            _ => return None,
+        };
+        let (center, number) = (generation.center, generation.number + 1);
+        self.breed(picked.clone(), center, number, amount);
+        Some(picked)
+    }
+
+    /// Replace the generation with mutations of `parent`.  No programs are compiled here;
+    /// [Self::build_next_preview] catches up on the ones the new candidates need.
+    fn breed(&mut self, parent: Layers, center: Posef, number: u32, amount: f32) {
+        let seed = surprise::fresh_seed();
+        log::info!("evolution: generation {} from seed {}", number, seed);
+        let candidates: Vec<Candidate> = offspring(&parent, seed, amount)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, layers)| {
+                let pose = pose_compose(&center, &arc_offset(idx, CANDIDATE_COUNT));
+                Candidate::new(layers, pose)
+            })
+            .collect();
+        self.previews
+            .retain(|(kinds, _)| candidates.iter().any(|candidate| candidate.kinds == *kinds));
+        self.generation = Some(Generation {
+            number,
+            candidates,
+            center,
+            hover: None,
+        });
+    }
+
+    /// Compile at most one of the programs the current candidates are waiting for,
+    /// so a new generation costs a frame or two of compiles instead of one long stall.
+    /// A program that fails is not retried.
+    pub fn build_next_preview(&mut self, gpu_state: &mut GPUState) -> Result<(), GLErrorWrapper> {
+        let generation = match &self.generation {
+            Some(generation) => generation,
+            None => return Ok(()),
+        };
+        let missing = generation.candidates.iter().find(|candidate| {
+            !self
+                .previews
+                .iter()
+                .any(|(kinds, _)| *kinds == candidate.kinds)
+        });
+        let kinds = match missing {
+            Some(candidate) => candidate.kinds.clone(),
+            None => return Ok(()),
+        };
+        let mut builder = GorgonFragmentShaderBuilder::default();
+        for (shape, axis) in &kinds {
+            builder.add_uniform_layer(*shape, *axis);
+        }
+        let result = GorgonPreview::new(&builder, &self.square, gpu_state);
+        let (preview, result) = match result {
+            Ok(preview) => (Some(preview), Ok(())),
+            Err(e) => (None, Err(e)),
+        };
+        self.previews.push((kinds, preview));
+        result
+    }
+
+    fn preview_for(&self, kinds: &LayerKinds) -> Option<&GorgonPreview> {
+        self.previews
+            .iter()
+            .find(|(k, _)| k == kinds)
+            .and_then(|(_, preview)| preview.as_ref())
+    }
+
+    pub fn draw(
+        &self,
+        matrix_pv: &XrMatrix4x4f,
+        phase: GLfloat,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let generation = match &self.generation {
+            Some(generation) => generation,
+            None => return Ok(()),
+        };
+        for (idx, candidate) in generation.candidates.iter().enumerate() {
+            let model = pose_matrix(&candidate.pose);
+            // a candidate whose program is not compiled yet is just its ring, if hovered
+            if let Some(preview) = self.preview_for(&candidate.kinds) {
+                let matrix = matrix_pv * model * xr_matrix4x4f_uniform_scale(PREVIEW_HALF_SIZE);
+                preview.paint(&matrix, phase, &candidate.uniforms, gpu_state)?;
+            }
+
+            if generation.hover == Some(idx) {
+                let s = PREVIEW_HALF_SIZE * 1.15;
+                let matrix = matrix_pv * model * xr_matrix4x4f_create_scale(s, s, 1.0);
+                self.ring.draw(
+                    &matrix,
+                    &self.theme.outline,
+                    self.theme.outline_thickness,
+                    gpu_state,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The [CANDIDATE_COUNT] layers of a generation: `parent` itself, then its mutations.
+/// Each mutation gets its own seed from a [SeededRng] started at `seed`, so the same
+/// `seed` breeds the same generation, and no two candidates share a mutation.
+fn offspring(parent: &Layers, seed: u64, amount: f32) -> Vec<Layers> {
+    let mut rng = SeededRng::new(seed);
+    (0..CANDIDATE_COUNT)
+        .map(|idx| {
+            let mut settings = MultiGorgonSettings::default();
+            settings.replace_layers(parent);
+            if idx > 0 {
+                surprise::mutate(&mut settings, rng.next_u64(), amount);
+            }
+            settings.layers()
+        })
+        .collect()
+}
+
+/// where preview `idx` of `count` sits relative to the center of the arc.
+/// It faces back toward the center.
+fn arc_offset(idx: usize, count: usize) -> Posef {
+    let rows = if count > MAX_ROW_LENGTH { 2 } else { 1 };
+    let columns = count.div_ceil(rows);
+    let (row, column) = (idx / columns, idx % columns);
+    let yaw = (column as f32 - (columns - 1) as f32 / 2.0) * -COLUMN_ANGLE;
+    let y = (row as f32 - (rows - 1) as f32 / 2.0) * -ROW_SPACING;
+    Posef {
+        orientation: yaw_quaternion(yaw),
+        position: Vector3f {
+            x: -yaw.sin() * PREVIEW_RADIUS,
+            y,
+            z: -yaw.cos() * PREVIEW_RADIUS,
+        },
+    }
+}
+
+/// `head`, turned to face the same compass direction but level with the floor
+fn level_pose(head: &Posef) -> Posef {
+    let m = pose_matrix(head).m;
+    // the head looks down its -Z axis
+    let (forward_x, forward_z) = (-m[8], -m[10]);
+    Posef {
+        orientation: yaw_quaternion((-forward_x).atan2(-forward_z)),
+        position: head.position,
+    }
+}
+
+/// a rotation of `yaw` radians about the vertical axis
+fn yaw_quaternion(yaw: f32) -> Quaternionf {
+    Quaternionf {
+        x: 0.0,
+        y: (yaw / 2.0).sin(),
+        z: 0.0,
+        w: (yaw / 2.0).cos(),
+    }
+}
+
+/// does `ray` pass through the preview at `pose`?
+fn preview_hit(ray: &Ray, pose: &Posef) -> bool {
+    let to_preview = xr_matrix4x4f_invert_rigid_body(&pose_matrix(pose));
+    match ray.transformed(&to_preview).hit_z0() {
+        Some([x, y]) => x.abs() <= PREVIEW_HALF_SIZE && y.abs() <= PREVIEW_HALF_SIZE,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surprise::{surprise_me, MUTATION_AMOUNTS};
+
+    fn parent() -> Layers {
+        let mut settings = MultiGorgonSettings::default();
+        surprise_me(&mut settings, 7);
+        settings.layers()
+    }
+
+    #[test]
+    fn the_first_candidate_is_the_parent() {
+        assert_eq!(offspring(&parent(), 1, MUTATION_AMOUNTS[1])[0], parent());
+    }
+
+    #[test]
+    fn the_candidates_of_a_generation_differ() {
+        for amount in MUTATION_AMOUNTS {
+            let candidates = offspring(&parent(), 1234, amount);
+            assert_eq!(candidates.len(), CANDIDATE_COUNT);
+            for (idx, candidate) in candidates.iter().enumerate() {
+                for other in &candidates[idx + 1..] {
+                    assert_ne!(candidate, other, "amount {}", amount);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_seed_breeds_the_same_generation() {
+        let amount = MUTATION_AMOUNTS[0];
+        assert_eq!(
+            offspring(&parent(), 99, amount),
+            offspring(&parent(), 99, amount)
+        );
+        assert_ne!(
+            offspring(&parent(), 99, amount),
+            offspring(&parent(), 100, amount)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// how often we look for modified files.  Checking every frame would be wasteful.
+const POLL_PERIOD: Duration = Duration::from_secs(1);
+
+/// what happened to a watched file since the previous poll
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FileChange {
+    /// the file appeared, or its modification time changed
+    Modified,
+    Removed,
+}
+
+/// Watch the files of a directory so they can be replaced with `adb push` while the app runs.
+/// There is no file notification API we can rely on, so we compare modification times.
+pub struct WatchedDirectory {
+    dir: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+    last_poll: Option<Instant>,
+}
+
+impl WatchedDirectory {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            modified: HashMap::new(),
+            last_poll: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Every file for which `relevant` is true that appeared, changed or disappeared since the previous poll.
+    /// The first poll returns every such file that is already present.
+    /// Polls less than [POLL_PERIOD] apart (or of a directory that can not be read) return nothing.
+    pub fn poll(&mut self, relevant: impl Fn(&Path) -> bool) -> Vec<(PathBuf, FileChange)> {
+        let now = Instant::now();
+        if let Some(last_poll) = self.last_poll {
+            if now.duration_since(last_poll) < POLL_PERIOD {
+                return vec![];
+            }
+        }
+        self.last_poll = Some(now);
+
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+
+        let mut rval = vec![];
+        let mut present = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !relevant(&path) {
+                continue;
+            }
+            present.push(path.clone());
+            let modified = match entry.metadata().and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            if self.modified.insert(path.clone(), modified) != Some(modified) {
+                rval.push((path, FileChange::Modified));
+            }
+        }
+
+        let removed: Vec<PathBuf> = self
+            .modified
+            .keys()
+            .filter(|path| !present.contains(path))
+            .cloned()
+            .collect();
+        for path in removed {
+            self.modified.remove(&path);
+            rval.push((path, FileChange::Removed));
+        }
+        rval
+    }
+
+    /// report `path` as modified again on the next poll, even if it has not changed (say, because it could not be read)
+    pub fn retry(&mut self, path: &Path) {
+        self.modified.remove(path);
+    }
+
+    /// let the next poll look at the directory without waiting for [POLL_PERIOD]
+    #[cfg(test)]
+    pub fn expire(&mut self) {
+        self.last_poll = None;
+    }
+}
+
+/// a fresh, empty directory for tests to fill, removed when dropped
+#[cfg(test)]
+pub struct ScratchDirectory(pub PathBuf);
+
+#[cfg(test)]
+impl ScratchDirectory {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("vr-gorgon-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// write `contents` to `name`, with a modification time `age` seconds after the epoch
+    /// so that a rewrite is noticed even on file systems with coarse timestamps
+    pub fn write(&self, name: &str, contents: &[u8], age: u64) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::write(&path, contents).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(age);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScratchDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll_now(watched: &mut WatchedDirectory) -> Vec<(PathBuf, FileChange)> {
+        watched.expire();
+        let mut rval = watched.poll(|path| path.extension().is_some_and(|ext| ext == "txt"));
+        rval.sort_by(|a, b| a.0.cmp(&b.0));
+        rval
+    }
+
+    #[test]
+    fn the_first_poll_reports_files_already_present() {
+        let scratch = ScratchDirectory::new("watch-first");
+        let a = scratch.write("a.txt", b"a", 1000);
+        let b = scratch.write("b.txt", b"b", 1000);
+        scratch.write("ignored.bin", b"c", 1000);
+        let mut watched = WatchedDirectory::new(scratch.0.clone());
+        assert_eq!(
+            poll_now(&mut watched),
+            vec![(a, FileChange::Modified), (b, FileChange::Modified)]
+        );
+        assert_eq!(poll_now(&mut watched), vec![]);
+    }
+
+    #[test]
+    fn appearing_changing_and_removed_files_are_reported() {
+        let scratch = ScratchDirectory::new("watch-changes");
+        let mut watched = WatchedDirectory::new(scratch.0.clone());
+        assert_eq!(poll_now(&mut watched), vec![]);
+
+        let a = scratch.write("a.txt", b"a", 1000);
+        assert_eq!(
+            poll_now(&mut watched),
+            vec![(a.clone(), FileChange::Modified)]
+        );
+
+        scratch.write("a.txt", b"aa", 2000);
+        assert_eq!(
+            poll_now(&mut watched),
+            vec![(a.clone(), FileChange::Modified)]
+        );
+
+        std::fs::remove_file(&a).unwrap();
+        assert_eq!(poll_now(&mut watched), vec![(a, FileChange::Removed)]);
+        assert_eq!(poll_now(&mut watched), vec![]);
+    }
+
+    #[test]
+    fn polls_too_close_together_report_nothing() {
+        let scratch = ScratchDirectory::new("watch-period");
+        let mut watched = WatchedDirectory::new(scratch.0.clone());
+        assert_eq!(poll_now(&mut watched), vec![]);
+        scratch.write("a.txt", b"a", 1000);
+        assert_eq!(watched.poll(|_| true), vec![]);
+    }
+
+    #[test]
+    fn a_retried_file_is_reported_again() {
+        let scratch = ScratchDirectory::new("watch-retry");
+        let a = scratch.write("a.txt", b"a", 1000);
+        let mut watched = WatchedDirectory::new(scratch.0.clone());
+        assert_eq!(poll_now(&mut watched).len(), 1);
+        watched.retry(&a);
+        assert_eq!(poll_now(&mut watched), vec![(a, FileChange::Modified)]);
+    }
+}
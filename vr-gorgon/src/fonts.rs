@@ -0,0 +1,234 @@
+use once_cell::sync::{Lazy, OnceCell};
+use rusttype::{Font, Glyph, GlyphId};
+use std::path::{Path, PathBuf};
+
+/// the file in the font directory which says which font is for what (see [FontRegistry::load])
+const MANIFEST: &str = "fonts.txt";
+
+static REGISTRY: OnceCell<FontRegistry> = OnceCell::new();
+
+/// the font compiled into the APK, which is the last resort of every [FontChain]
+fn embedded_font() -> &'static Font<'static> {
+    static RVAL: Lazy<Font> =
+        Lazy::new(|| Font::try_from_bytes(include_bytes!("AlbertText-Bold.ttf")).unwrap());
+
+    &RVAL
+}
+
+/// which kind of typeface a piece of text asks for
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum FontRole {
+    Regular,
+    Bold,
+}
+
+impl FontRole {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "regular" => Some(FontRole::Regular),
+            "bold" => Some(FontRole::Bold),
+            _ => None,
+        }
+    }
+}
+
+/// Fonts to try in order: each char is drawn in the first one which has a glyph for it,
+/// so a font with symbols or accented letters can fill the gaps of a prettier one.
+pub struct FontChain {
+    fonts: Vec<&'static Font<'static>>,
+}
+
+impl FontChain {
+    /// the font whose vertical metrics decide how tall a line is
+    pub fn primary(&self) -> &'static Font<'static> {
+        self.fonts[0]
+    }
+
+    /// `idx` is the index returned by [Self::glyph]
+    pub fn font(&self, idx: usize) -> &'static Font<'static> {
+        self.fonts[idx]
+    }
+
+    /// The index of the first font with a glyph for `c`, and that glyph.
+    /// If none of them have one, the primary font's missing-glyph box.
+    pub fn glyph(&self, c: char) -> (usize, Glyph<'static>) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .map(|(idx, font)| (idx, font.glyph(c)))
+            .find(|(_, glyph)| glyph.id() != GlyphId(0))
+            .unwrap_or_else(|| (0, self.primary().glyph(c)))
+    }
+}
+
+/// The fonts all text is drawn with, one [FontChain] per [FontRole].
+pub struct FontRegistry {
+    regular: FontChain,
+    /// None if no bold font was listed, in which case bold text uses [Self::regular]
+    bold: Option<FontChain>,
+}
+
+impl FontRegistry {
+    fn embedded() -> Self {
+        Self {
+            regular: FontChain {
+                fonts: vec![embedded_font()],
+            },
+            bold: None,
+        }
+    }
+
+    /// Load the TTF and OTF fonts in `dir`.
+    ///
+    /// If `dir` has a `fonts.txt`, each of its lines lists the files for one [FontRole], in the order they are tried:
+    /// ```text
+    /// regular = NotoSans-Regular.ttf, DejaVuSans.ttf
+    /// bold = NotoSans-Bold.ttf
+    /// ```
+    /// Bold text falls back to the regular fonts, and the embedded font comes last.
+    /// Without a `fonts.txt`, every font in `dir` is a fallback for glyphs the embedded font lacks.
+    pub fn load(dir: &Path) -> Self {
+        match std::fs::read_to_string(dir.join(MANIFEST)) {
+            Ok(manifest) => {
+                let (regular, bold) = parse_manifest(&manifest);
+                let load_all = |names: Vec<String>| -> Vec<_> {
+                    names
+                        .into_iter()
+                        .filter_map(|name| load_font(&dir.join(name)))
+                        .collect()
+                };
+                let mut regular = load_all(regular);
+                regular.push(embedded_font());
+                let bold = load_all(bold);
+                Self {
+                    bold: (!bold.is_empty()).then(|| FontChain {
+                        fonts: bold.into_iter().chain(regular.iter().copied()).collect(),
+                    }),
+                    regular: FontChain { fonts: regular },
+                }
+            }
+            Err(_) => {
+                let mut fonts = vec![embedded_font()];
+                fonts.extend(
+                    font_files(dir)
+                        .into_iter()
+                        .filter_map(|path| load_font(&path)),
+                );
+                Self {
+                    regular: FontChain { fonts },
+                    bold: None,
+                }
+            }
+        }
+    }
+
+    pub fn chain(&self, role: FontRole) -> &FontChain {
+        match role {
+            FontRole::Regular => &self.regular,
+            FontRole::Bold => self.bold.as_ref().unwrap_or(&self.regular),
+        }
+    }
+
+    /// false if text in `role` has to be drawn with fonts meant for something else (so bold has to be faked)
+    pub fn has_fonts_for(&self, role: FontRole) -> bool {
+        match role {
+            FontRole::Regular => true,
+            FontRole::Bold => self.bold.is_some(),
+        }
+    }
+}
+
+/// the fonts to draw text with: the embedded font, unless [install_font_directory] was called first
+pub fn registry() -> &'static FontRegistry {
+    REGISTRY.get_or_init(FontRegistry::embedded)
+}
+
+/// Draw all text with the fonts in `dir` (see [FontRegistry::load]), if it exists.
+/// Text already laid out with the embedded font would not match, so this has to happen before any is.
+pub fn install_font_directory(dir: &Path) {
+    if !dir.is_dir() {
+        return;
+    }
+    log::info!("loading fonts from {:?}", dir);
+    if REGISTRY.set(FontRegistry::load(dir)).is_err() {
+        log::warn!("fonts are already in use; ignoring {:?}", dir);
+    }
+}
+
+/// `(regular, bold)` file names from a `fonts.txt`
+fn parse_manifest(manifest: &str) -> (Vec<String>, Vec<String>) {
+    let mut regular = vec![];
+    let mut bold = vec![];
+    for line in manifest.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (role, files) = match line.split_once('=') {
+            Some(split) => split,
+            None => {
+                log::warn!("{}: expected `role = files` in {:?}", MANIFEST, line);
+                continue;
+            }
+        };
+        let list = match FontRole::from_name(role.trim()) {
+            Some(FontRole::Regular) => &mut regular,
+            Some(FontRole::Bold) => &mut bold,
+            None => {
+                log::warn!("{}: unknown font role {:?}", MANIFEST, role.trim());
+                continue;
+            }
+        };
+        list.extend(
+            files
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+        );
+    }
+    (regular, bold)
+}
+
+/// the `.ttf` and `.otf` files in `dir`, sorted by name
+fn font_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("unable to list {:?}: {}", dir, e);
+            return vec![];
+        }
+    };
+    let mut rval: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("ttf") || ext.eq_ignore_ascii_case("otf"))
+                .unwrap_or(false)
+        })
+        .collect();
+    rval.sort();
+    rval
+}
+
+/// Fonts are loaded once and used until the app exits, so they are leaked to make them `'static` like the embedded one.
+fn load_font(path: &Path) -> Option<&'static Font<'static>> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::warn!("unable to read font {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match Font::try_from_vec(bytes) {
+        Some(font) => {
+            log::info!("loaded font {:?}", path);
+            Some(Box::leak(Box::new(font)))
+        }
+        None => {
+            log::warn!("{:?} is not a font we can read", path);
+            None
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::glsl_include::preprocess;
+use gl_thin::gl_helper::{GLErrorWrapper, Program};
+use once_cell::sync::OnceCell;
+use std::ffi::CStr;
+
+/// Our shaders are written once, in GLSL ES 1.00 (`attribute`, `varying`, `gl_FragColor`).
+/// When the context supports it we translate them to `#version 300 es` on the way to the
+/// compiler, which gives them integer ops, `fwidth()` and better precision control.
+/// Shader code can test `#ifdef GLSL_ES_300` to take advantage of that.
+///
+/// Only programs built with [compile_program] are translated.  [crate::suzanne::Suzanne] and
+/// [crate::rainbow_triangle::RainbowTriangle] use programs that `bob_shaders` compiles itself,
+/// so those stay GLSL ES 1.00 (which every GLES 3 context still accepts).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GlslDialect {
+    Es100,
+    Es300,
+}
+
+static DIALECT: OnceCell<GlslDialect> = OnceCell::new();
+
+impl GlslDialect {
+    /// ask the GL context which is current on this thread
+    pub fn detect() -> Self {
+        let version = unsafe {
+            let version = gl::GetString(gl::VERSION);
+            if version.is_null() {
+                return GlslDialect::Es100;
+            }
+            CStr::from_ptr(version as *const _)
+                .to_string_lossy()
+                .into_owned()
+        };
+        let rval = Self::from_version_string(&version);
+        log::debug!("GL_VERSION {:?} -> {:?}", version, rval);
+        rval
+    }
+
+    /// parse a `GL_VERSION` like `OpenGL ES 3.2 V@0502.0`
+    pub fn from_version_string(version: &str) -> Self {
+        let major = version
+            .strip_prefix("OpenGL ES ")
+            .and_then(|rest| rest.split('.').next())
+            .and_then(|major| major.trim().parse::<u32>().ok());
+        match major {
+            Some(major) if major >= 3 => GlslDialect::Es300,
+            _ => GlslDialect::Es100,
+        }
+    }
+
+    /// the dialect of the current context, detected the first time this is called
+    pub fn current() -> Self {
+        *DIALECT.get_or_init(Self::detect)
+    }
+
+    pub fn translate_vertex_shader(&self, source: &str) -> String {
+        match self {
+            GlslDialect::Es100 => source.into(),
+            GlslDialect::Es300 => {
+                if has_version_directive(source) {
+                    return source.into();
+                }
+                let body = replace_identifier(source, "attribute", "in");
+                let body = replace_identifier(&body, "varying", "out");
+                let body = replace_identifier(&body, "texture2D", "texture");
+                format!("{}#line 1\n{}", ES300_PREAMBLE, body)
+            }
+        }
+    }
+
+    pub fn translate_fragment_shader(&self, source: &str) -> String {
+        match self {
+            GlslDialect::Es100 => source.into(),
+            GlslDialect::Es300 => {
+                if has_version_directive(source) {
+                    return source.into();
+                }
+                let body = strip_derivatives_extension(source);
+                let body = replace_identifier(&body, "varying", "in");
+                let body = replace_identifier(&body, "texture2D", "texture");
+                let body = replace_identifier(&body, "gl_FragColor", "frag_color");
+                format!(
+                    "{}out mediump vec4 frag_color;\n#line 1\n{}",
+                    ES300_PREAMBLE, body
+                )
+            }
+        }
+    }
+}
+
+/// The translated source is followed by `#line 1`, so the compiler's line numbers
+/// still match the untranslated source in error messages.
+const ES300_PREAMBLE: &str = "#version 300 es\n#define GLSL_ES_300 1\n";
+
+/// compile a program written in GLSL ES 1.00, with its `#include`s expanded
+/// (see [crate::glsl_include]) and translated for [GlslDialect::current].
+/// Programs that do not come through here (like the `bob_shaders` ones) are not translated.
+pub fn compile_program(
+    vertex_shader: &str,
+    fragment_shader: &str,
+) -> Result<Program, GLErrorWrapper> {
+    let dialect = GlslDialect::current();
+    Program::compile(
+        dialect.translate_vertex_shader(&preprocess(vertex_shader).text),
+        dialect.translate_fragment_shader(&preprocess(fragment_shader).text),
+    )
+}
+
+fn has_version_directive(source: &str) -> bool {
+    source
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with("#version"))
+}
+
+/// `fwidth()` and friends are core in 3.00, where the extension directive is an error.
+/// The line is blanked rather than removed so the line numbers do not shift.
+fn strip_derivatives_extension(source: &str) -> String {
+    source
+        .split('\n')
+        .map(|line| {
+            if line
+                .trim_start()
+                .starts_with("#extension GL_OES_standard_derivatives")
+            {
+                ""
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// replace whole-word occurrences of `from` with `to`
+fn replace_identifier(source: &str, from: &str, to: &str) -> String {
+    let mut rval = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(idx) = rest.find(from) {
+        rval.push_str(&rest[..idx]);
+        let after = &rest[idx + from.len()..];
+        let starts_word = !rval.chars().next_back().is_some_and(is_identifier_char);
+        let ends_word = !after.chars().next().is_some_and(is_identifier_char);
+        rval.push_str(if starts_word && ends_word { to } else { from });
+        rest = after;
+    }
+    rval.push_str(rest);
+    rval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_strings_pick_the_dialect() {
+        let es300 = [
+            "OpenGL ES 3.2 V@0502.0 (GIT@5eaa426211, I07ee46fc66, 1633700387) (Date:10/08/21)",
+            "OpenGL ES 3.0 Mesa 23.2.1",
+            "OpenGL ES 3.1 v1.r32p1-01eac0.0b0c2cb5c0dfb7ba7f59b3a4c1a0d5d6",
+        ];
+        for version in es300 {
+            assert_eq!(
+                GlslDialect::from_version_string(version),
+                GlslDialect::Es300
+            );
+        }
+        let es100 = [
+            "OpenGL ES 2.0",
+            "OpenGL ES 2.0 build 1.13@5776728",
+            "OpenGL ES-CM 1.1",
+            "4.6.0 NVIDIA 535.129.03",
+            "",
+        ];
+        for version in es100 {
+            assert_eq!(
+                GlslDialect::from_version_string(version),
+                GlslDialect::Es100
+            );
+        }
+    }
+
+    #[test]
+    fn vertex_shaders_get_ins_and_outs() {
+        let source =
+            "attribute vec3 position;\nvarying vec3 ray;\nvoid main() { ray = position; }\n";
+        let translated = GlslDialect::Es300.translate_vertex_shader(source);
+        assert_eq!(
+            translated,
+            format!(
+                "{}#line 1\nin vec3 position;\nout vec3 ray;\nvoid main() {{ ray = position; }}\n",
+                ES300_PREAMBLE
+            )
+        );
+        assert_eq!(GlslDialect::Es100.translate_vertex_shader(source), source);
+    }
+
+    #[test]
+    fn fragment_shaders_get_an_output_and_texture() {
+        let source = "#extension GL_OES_standard_derivatives : enable\n\
+                      varying vec2 uv;\n\
+                      void main() { gl_FragColor = texture2D(tex, uv); }\n";
+        let translated = GlslDialect::Es300.translate_fragment_shader(source);
+        assert_eq!(
+            translated,
+            format!(
+                "{}out mediump vec4 frag_color;\n#line 1\n\nin vec2 uv;\n\
+                 void main() {{ frag_color = texture(tex, uv); }}\n",
+                ES300_PREAMBLE
+            )
+        );
+    }
+
+    #[test]
+    fn sources_with_a_version_are_left_alone() {
+        let source = "\n#version 300 es\nin vec2 uv;\nvarying vec2 kept;\n";
+        assert_eq!(GlslDialect::Es300.translate_fragment_shader(source), source);
+        assert_eq!(GlslDialect::Es300.translate_vertex_shader(source), source);
+    }
+
+    #[test]
+    fn only_whole_identifiers_are_replaced() {
+        assert_eq!(
+            replace_identifier(
+                "texture2D(a) my_texture2Dx texture2D_lod texture2Dx(b) _texture2D texture2D",
+                "texture2D",
+                "texture"
+            ),
+            "texture(a) my_texture2Dx texture2D_lod texture2Dx(b) _texture2D texture"
+        );
+        assert_eq!(
+            replace_identifier("varying_count varying", "varying", "in"),
+            "varying_count in"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+/// GLSL pieces shared between shaders, available to `#include "name"`
+static SNIPPETS: &[(&str, &str)] = &[
+    ("pi", "#define PI 3.1415926538"),
+    (
+        "c2s",
+        "
+// cartesian to spherical: (latitude, longitude, distance from the Z axis)
+vec3 c2s(vec3 rayn)
+{
+    float r = length(rayn.xy);
+
+    float theta = atan(rayn.z, r);
+    float phi = atan(rayn.y, rayn.x);
+    return vec3(theta, phi, r);
+}",
+    ),
+    (
+        "gorgon_fragment",
+        "varying vec3 ray;
+uniform float phase;",
+    ),
+    (
+        "uv_vertex",
+        "uniform mat4 matrix;
+
+attribute vec3 position;
+attribute vec2 uv;
+
+varying vec2 tex_coord;",
+    ),
+    ("uv_fragment", "varying vec2 tex_coord;"),
+];
+
+pub fn snippet(name: &str) -> Option<(&'static str, &'static str)> {
+    SNIPPETS.iter().copied().find(|(key, _)| *key == name)
+}
+
+/// where a line of [Preprocessed::text] came from
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LineOrigin {
+    /// `None` for the shader itself, otherwise the name of the `#include`d snippet
+    pub snippet: Option<&'static str>,
+    /// 1-based
+    pub line: usize,
+}
+
+/// The output of [preprocess]: GLSL with every `#include` expanded,
+/// plus a map back to the original lines for error messages.
+pub struct Preprocessed {
+    pub text: String,
+    origins: Vec<LineOrigin>,
+}
+
+/// Expand `#include "name"` directives from the snippet registry.
+/// Each snippet is only expanded once, no matter how many times it is included.
+/// An unknown name is left in place so the GLSL compiler reports it.
+pub fn preprocess(source: &str) -> Preprocessed {
+    let mut rval = Preprocessed {
+        text: String::with_capacity(source.len()),
+        origins: vec![],
+    };
+    let mut included = vec![];
+    rval.expand(source, None, &mut included);
+    rval
+}
+
+impl Preprocessed {
+    fn expand(
+        &mut self,
+        source: &str,
+        snippet_name: Option<&'static str>,
+        included: &mut Vec<&'static str>,
+    ) {
+        for (i, line) in source.lines().enumerate() {
+            let origin = LineOrigin {
+                snippet: snippet_name,
+                line: i + 1,
+            };
+            let target = include_target(line);
+            match target.and_then(snippet) {
+                Some((name, body)) => {
+                    if !included.contains(&name) {
+                        included.push(name);
+                        self.expand(body, Some(name), included);
+                    }
+                }
+                None => {
+                    if let Some(target) = target {
+                        log::warn!("no GLSL snippet named {:?}", target);
+                    }
+                    self.push_line(line, origin);
+                }
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: &str, origin: LineOrigin) {
+        if !self.origins.is_empty() {
+            self.text.push('\n');
+        }
+        self.text.push_str(line);
+        self.origins.push(origin);
+    }
+
+    /// map a 1-based line number of [Self::text] (as reported by the compiler) to its origin
+    pub fn origin(&self, line_number: usize) -> Option<LineOrigin> {
+        self.origins.get(line_number.checked_sub(1)?).copied()
+    }
+}
+
+/// `#include "c2s"` -> `Some("c2s")`
+fn include_target(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("#include")?.trim();
+    rest.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_snippet_is_included_once() {
+        let source = "#include \"pi\"\n  #include \"pi\"\nfloat tau = 2.0*PI;\n#include \"pi\"";
+        assert_eq!(
+            preprocess(source).text,
+            "#define PI 3.1415926538\nfloat tau = 2.0*PI;"
+        );
+    }
+
+    #[test]
+    fn unknown_snippets_are_left_for_the_compiler() {
+        let source = "#include \"nonexistent\"\n#include nonexistent\nvoid main() {}";
+        let preprocessed = preprocess(source);
+        assert_eq!(preprocessed.text, source);
+        assert_eq!(
+            preprocessed.origin(1),
+            Some(LineOrigin {
+                snippet: None,
+                line: 1
+            })
+        );
+    }
+
+    #[test]
+    fn lines_map_back_to_where_they_came_from() {
+        let preprocessed = preprocess("// before\n#include \"gorgon_fragment\"\nvoid main() {}\n");
+        assert_eq!(
+            preprocessed.text,
+            "// before\nvarying vec3 ray;\nuniform float phase;\nvoid main() {}"
+        );
+        let shader = |line| LineOrigin {
+            snippet: None,
+            line,
+        };
+        let snippet = |line| LineOrigin {
+            snippet: Some("gorgon_fragment"),
+            line,
+        };
+        assert_eq!(preprocessed.origin(0), None);
+        assert_eq!(preprocessed.origin(1), Some(shader(1)));
+        assert_eq!(preprocessed.origin(2), Some(snippet(1)));
+        assert_eq!(preprocessed.origin(3), Some(snippet(2)));
+        // the #include line itself is gone, so the shader's third line is the output's fourth
+        assert_eq!(preprocessed.origin(4), Some(shader(3)));
+        assert_eq!(preprocessed.origin(5), None);
+    }
+}
@@ -0,0 +1,530 @@
+use crate::fonts::{FontChain, FontRegistry, FontRole};
+use crate::glsl_dialect::GlslDialect;
+use crate::sdf;
+use crate::sprite_atlas::ShelfPacker;
+use crate::sprites::{with_unpack_alignment, SpriteLocation};
+use crate::text_layout::{self, Align, TextStyle};
+use crate::text_painting::{Icon, TextSpan};
+use gl::types::{GLenum, GLint, GLsizei};
+use gl_thin::gl_fancy::GPUState;
+use gl_thin::gl_helper::{explode_if_gl_error, GLErrorWrapper, Texture};
+use image::{GrayImage, Luma};
+use rusttype::{point, GlyphId, PositionedGlyph, Scale};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// the atlas is always this wide; it grows downward
+const ATLAS_WIDTH: u32 = 512;
+const INITIAL_HEIGHT: u32 = 128;
+/// most GLES devices support textures at least this big
+const MAX_HEIGHT: u32 = 4096;
+/// Empty pixels between glyphs, so that linear filtering at the edge of one glyph's quad
+/// does not sample its neighbour.  The smaller mipmap levels still blend neighbours together,
+/// but each glyph's [SDF_SPREAD] margin keeps that away from the glyph itself until the text is unreadably small.
+const PADDING: u32 = 2;
+/// How far (in atlas pixels) the distance field extends beyond the edge of a glyph, in both directions.
+/// Each glyph's bitmap has this much margin on every side.
+pub const SDF_SPREAD: f32 = 4.0;
+/// glyphs are rasterized this many times bigger, to find their edges more precisely
+const OVERSAMPLE: u32 = 4;
+/// the layout cache is emptied when it gets this big, so labels which keep changing do not pile up
+const MAX_CACHED_LAYOUTS: usize = 256;
+
+/// where a rasterized glyph lives in the atlas
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct GlyphSlot {
+    /// top left corner in atlas pixels
+    min: [u32; 2],
+    size: [u32; 2],
+    /// from the pen position on the baseline to the top left corner of the bitmap, in pixels
+    bearing: [i32; 2],
+}
+
+/// One glyph of a laid out string
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GlyphQuad {
+    /// `[left, top, right, bottom]` in pixels, from the top left corner of the string
+    pub rect: [f32; 4],
+    /// the glyph's part of the atlas texture, for [SpriteLocation]
+    pub uv_scale: [f32; 2],
+    pub uv_offset: [f32; 2],
+    /// the index of the [TextSpan] this glyph is from
+    pub span: usize,
+}
+
+impl GlyphQuad {
+    pub fn sprite<'a>(&self, texture: &'a Texture) -> SpriteLocation<'a> {
+        SpriteLocation::new(self.uv_scale, self.uv_offset, texture)
+    }
+}
+
+/// A block of text laid out with [GlyphAtlas::layout]
+pub struct TextLayout {
+    pub glyphs: Vec<GlyphQuad>,
+    /// width and height in pixels
+    pub size: [f32; 2],
+}
+
+struct AtlasPixels {
+    image: GrayImage,
+    packer: ShelfPacker,
+    /// Glyphs which rasterize to nothing (like spaces) are stored as None.
+    slots: HashMap<SlotKey, Option<GlyphSlot>>,
+    /// the texture has to be allocated again at the size of the image, and all of it uploaded
+    resized: bool,
+    /// glyphs rasterized since the last upload, which only their own rectangles of the texture need
+    fresh: Vec<GlyphSlot>,
+    /// Everything [GlyphAtlas::layout] has laid out, so unchanged labels are not laid out every frame.
+    /// Emptied when the atlas grows, because that changes every glyph's UVs.
+    layouts: HashMap<LayoutKey, Rc<TextLayout>>,
+}
+
+/// what is rasterized into a [GlyphSlot]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum SlotKey {
+    /// a glyph of the font at this index of the [FontChain] for the role
+    Glyph(FontRole, usize, GlyphId),
+    /// an [Icon] as tall as the primary font's ascent
+    Icon(Icon),
+}
+
+/// Spans and the [TextStyle] they were laid out with.  The colors of the spans do not matter,
+/// so labels which only change color share a layout.  The floats are compared by their bits.
+#[derive(PartialEq, Eq, Hash)]
+struct LayoutKey {
+    text: String,
+    /// the length, boldness, size and icon of each span
+    spans: Vec<(usize, bool, u32, Option<Icon>)>,
+    max_width: Option<u32>,
+    align: Align,
+    line_spacing: u32,
+}
+
+impl LayoutKey {
+    fn new(spans: &[TextSpan], style: &TextStyle) -> Self {
+        Self {
+            text: spans.iter().map(|span| span.text.as_str()).collect(),
+            spans: spans
+                .iter()
+                .map(|span| (span.text.len(), span.bold, span.size.to_bits(), span.icon))
+                .collect(),
+            max_width: style.max_width.map(f32::to_bits),
+            align: style.align,
+            line_spacing: style.line_spacing.to_bits(),
+        }
+    }
+}
+
+/// a glyph's distance field, before it has a place in the atlas
+struct GlyphBitmap {
+    /// from the pen position to the top left corner, like [GlyphSlot::bearing]
+    bearing: [i32; 2],
+    size: [u32; 2],
+    /// one byte per pixel
+    field: Vec<u8>,
+}
+
+/// Glyphs of the [FontRegistry]'s fonts, rasterized on demand with `rusttype` and packed into a texture
+/// which grows as needed.  Any string (or run of [TextSpan]s) can then be drawn as one quad per glyph.
+///
+/// The texture holds signed distance fields rather than coverage, in its only channel (see [single_channel_format]):
+/// the edge of a glyph is where it crosses 0.5, and it changes by 0.5 over [SDF_SPREAD] pixels.
+/// Drawn with [crate::shaders::SpriteRect::sdf_text_fragment_shader], text stays sharp at any scale.
+pub struct GlyphAtlas {
+    fonts: &'static FontRegistry,
+    scale: Scale,
+    texture: Texture,
+    pixels: RefCell<AtlasPixels>,
+}
+
+impl GlyphAtlas {
+    pub fn new(fonts: &'static FontRegistry, pixel_size: f32) -> Result<Self, GLErrorWrapper> {
+        Ok(Self {
+            fonts,
+            scale: Scale::uniform(pixel_size),
+            texture: Texture::new()?,
+            pixels: RefCell::new(AtlasPixels {
+                image: GrayImage::new(ATLAS_WIDTH, INITIAL_HEIGHT),
+                packer: ShelfPacker::new(ATLAS_WIDTH),
+                slots: HashMap::new(),
+                resized: true,
+                fresh: vec![],
+                layouts: HashMap::new(),
+            }),
+        })
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// the size the glyphs are rasterized at
+    pub fn pixel_size(&self) -> f32 {
+        self.scale.y
+    }
+
+    /// true if bold text has no font of its own, so it has to be faked (by smearing each glyph sideways)
+    pub fn fakes_bold(&self) -> bool {
+        !self.fonts.has_fonts_for(FontRole::Bold)
+    }
+
+    /// the fonts for a span, and the role they are cached under
+    fn chain(&self, bold: bool) -> (FontRole, &'static FontChain) {
+        let role = if bold && !self.fakes_bold() {
+            FontRole::Bold
+        } else {
+            FontRole::Regular
+        };
+        (role, self.fonts.chain(role))
+    }
+
+    /// the size in pixels [Self::layout] would make `text`, without touching the atlas
+    pub fn measure(&self, text: &str, style: &TextStyle) -> [f32; 2] {
+        let pixels = self.pixels.borrow();
+        match pixels
+            .layouts
+            .get(&LayoutKey::new(&[Self::plain(text)], style))
+        {
+            Some(layout) => layout.size,
+            None => text_layout::measure(self.chain(false).1, self.scale, text, style),
+        }
+    }
+
+    /// `text` as a single span; its color is up to whoever draws it
+    fn plain(text: &str) -> TextSpan {
+        TextSpan::new(text, [1.0; 4])
+    }
+
+    /// Lay out `text` (see [text_layout::layout]), adding any glyphs it needs to the atlas
+    /// (and uploading the atlas if that changed it).  The result is cached until the atlas grows.
+    pub fn layout(
+        &self,
+        text: &str,
+        style: &TextStyle,
+        gpu_state: &mut GPUState,
+    ) -> Result<Rc<TextLayout>, GLErrorWrapper> {
+        self.layout_spans(&[Self::plain(text)], style, gpu_state)
+    }
+
+    /// Like [Self::layout], but each span has its own boldness, size and maybe an icon
+    /// (see [text_layout::layout_mixed]).  [GlyphQuad::span] says which span each glyph is from.
+    pub fn layout_spans(
+        &self,
+        spans: &[TextSpan],
+        style: &TextStyle,
+        gpu_state: &mut GPUState,
+    ) -> Result<Rc<TextLayout>, GLErrorWrapper> {
+        let key = LayoutKey::new(spans, style);
+        if let Some(layout) = self.pixels.borrow().layouts.get(&key) {
+            return Ok(layout.clone());
+        }
+
+        // the byte offset in the text where each span ends
+        let ends: Vec<usize> = key
+            .spans
+            .iter()
+            .scan(0, |end, (len, ..)| {
+                *end += len;
+                Some(*end)
+            })
+            .collect();
+        let span_at = |offset: usize| ends.iter().position(|end| offset < *end).unwrap_or(0);
+        let style_at = |offset: usize| {
+            let span = &spans[span_at(offset)];
+            (
+                self.chain(span.bold).1,
+                Scale::uniform(self.scale.y * span.size),
+            )
+        };
+        let laid_out = text_layout::layout_mixed(&key.text, &style_at, style);
+
+        let mut pixels = self.pixels.borrow_mut();
+        let mut glyphs = vec![];
+        for glyph in laid_out.glyphs {
+            let idx = span_at(glyph.source);
+            let span = &spans[idx];
+            let slot_key = match span.icon {
+                Some(icon) => SlotKey::Icon(icon),
+                None => SlotKey::Glyph(self.chain(span.bold).0, glyph.font, glyph.id),
+            };
+            if let Some(slot) = self.slot_for(&mut pixels, slot_key) {
+                // glyphs are rasterized at the atlas's size, and scaled to the span's
+                let k = span.size;
+                let pen = glyph.position;
+                let left = pen.x.round() + slot.bearing[0] as f32 * k;
+                let top = pen.y.round() + slot.bearing[1] as f32 * k;
+                glyphs.push((slot, [left, top], k, idx));
+            }
+        }
+
+        self.upload(&mut pixels, gpu_state)?;
+
+        // the atlas might have grown while we were adding glyphs, so the UVs are computed last
+        let (atlas_w, atlas_h) = pixels.image.dimensions();
+        let glyphs = glyphs
+            .into_iter()
+            .map(|(slot, [left, top], k, span)| GlyphQuad {
+                rect: [
+                    left,
+                    top,
+                    left + slot.size[0] as f32 * k,
+                    top + slot.size[1] as f32 * k,
+                ],
+                uv_scale: [
+                    slot.size[0] as f32 / atlas_w as f32,
+                    slot.size[1] as f32 / atlas_h as f32,
+                ],
+                uv_offset: [
+                    slot.min[0] as f32 / atlas_w as f32,
+                    slot.min[1] as f32 / atlas_h as f32,
+                ],
+                span,
+            })
+            .collect();
+
+        let layout = Rc::new(TextLayout {
+            glyphs,
+            size: laid_out.size,
+        });
+        if pixels.layouts.len() >= MAX_CACHED_LAYOUTS {
+            pixels.layouts.clear();
+        }
+        pixels.layouts.insert(key, layout.clone());
+        Ok(layout)
+    }
+
+    /// rasterize `key` into the atlas if it is not there yet
+    fn slot_for(&self, pixels: &mut AtlasPixels, key: SlotKey) -> Option<GlyphSlot> {
+        if let Some(slot) = pixels.slots.get(&key) {
+            return *slot;
+        }
+
+        let bitmap = match key {
+            SlotKey::Glyph(role, font_idx, id) => self.glyph_bitmap(role, font_idx, id),
+            SlotKey::Icon(icon) => Some(self.icon_bitmap(icon)),
+        };
+        let bitmap = match bitmap {
+            Some(bitmap) => bitmap,
+            None => {
+                pixels.slots.insert(key, None);
+                return None;
+            }
+        };
+
+        let size = bitmap.size;
+        let slot = Self::allocate(pixels, size[0] + PADDING, size[1] + PADDING).map(|corner| {
+            for y in 0..size[1] {
+                for x in 0..size[0] {
+                    let a = bitmap.field[(y * size[0] + x) as usize];
+                    pixels
+                        .image
+                        .put_pixel(corner[0] + x, corner[1] + y, Luma([a]));
+                }
+            }
+            GlyphSlot {
+                min: corner,
+                size,
+                bearing: bitmap.bearing,
+            }
+        });
+        if let Some(slot) = slot {
+            pixels.fresh.push(slot);
+        }
+        if slot.is_none() {
+            log::warn!("glyph atlas is full; dropping a glyph");
+        }
+        pixels.slots.insert(key, slot);
+        slot
+    }
+
+    /// glyph `id` of font `font_idx` of the fonts for `role`, or None if it has no pixels (like a space)
+    fn glyph_bitmap(&self, role: FontRole, font_idx: usize, id: GlyphId) -> Option<GlyphBitmap> {
+        let big_scale = Scale::uniform(self.scale.y * OVERSAMPLE as f32);
+        let glyph = self
+            .fonts
+            .chain(role)
+            .font(font_idx)
+            .glyph(id)
+            .scaled(big_scale)
+            .positioned(point(0.0, 0.0));
+        let bb = glyph.pixel_bounding_box()?;
+
+        // the glyph's bitmap in atlas pixels, including the margin for the distance field
+        let os = OVERSAMPLE as i32;
+        let spread = SDF_SPREAD as i32;
+        let min = [
+            bb.min.x.div_euclid(os) - spread,
+            bb.min.y.div_euclid(os) - spread,
+        ];
+        let max = [
+            (bb.max.x + os - 1).div_euclid(os) + spread,
+            (bb.max.y + os - 1).div_euclid(os) + spread,
+        ];
+        let size = [(max[0] - min[0]) as u32, (max[1] - min[1]) as u32];
+        Some(GlyphBitmap {
+            bearing: min,
+            size,
+            field: glyph_distance_field(&glyph, min, size),
+        })
+    }
+
+    /// `icon` sitting on the baseline, as wide and tall as the primary font's ascent
+    /// (which is where [text_layout::ICON_CHAR] leaves room for it)
+    fn icon_bitmap(&self, icon: Icon) -> GlyphBitmap {
+        let icon_size = self
+            .fonts
+            .chain(FontRole::Regular)
+            .primary()
+            .v_metrics(self.scale)
+            .ascent;
+        let spread = SDF_SPREAD as i32;
+        let side = icon_size.ceil() as i32;
+        let min = [-spread, -side - spread];
+        let size = [(side + 2 * spread) as u32; 2];
+
+        let os = OVERSAMPLE as f32;
+        let width = (size[0] * OVERSAMPLE) as usize;
+        let height = (size[1] * OVERSAMPLE) as usize;
+        let mut inside = vec![false; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                // relative to the top left corner of the icon, in atlas pixels
+                let ix = (x as f32 + 0.5) / os + min[0] as f32;
+                let iy = (y as f32 + 0.5) / os + min[1] as f32 + icon_size;
+                inside[y * width + x] = icon.coverage(ix, iy, icon_size) > 0.5;
+            }
+        }
+        GlyphBitmap {
+            bearing: min,
+            size,
+            field: encode_distance_field(&inside, size),
+        }
+    }
+
+    /// find room for a `w`x`h` rectangle, doubling the height of the atlas if necessary
+    fn allocate(pixels: &mut AtlasPixels, w: u32, h: u32) -> Option<[u32; 2]> {
+        loop {
+            let height = pixels.image.height();
+            if let Some(min) = pixels.packer.place(w, h, height) {
+                return Some(min);
+            }
+            if height >= MAX_HEIGHT {
+                return None;
+            }
+            let mut grown = GrayImage::new(ATLAS_WIDTH, height * 2);
+            image::imageops::replace(&mut grown, &pixels.image, 0, 0);
+            pixels.image = grown;
+            pixels.resized = true;
+            pixels.layouts.clear();
+        }
+    }
+
+    /// Bring the texture up to date with the image: all of it if the atlas grew,
+    /// otherwise just the rectangles of the glyphs added since the last upload.
+    fn upload(
+        &self,
+        pixels: &mut AtlasPixels,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let fresh = std::mem::take(&mut pixels.fresh);
+        if !pixels.resized && fresh.is_empty() {
+            return Ok(());
+        }
+        let image = &pixels.image;
+        let (width, height) = image.dimensions();
+        let (internal_format, format) = single_channel_format();
+        let bound = self.texture.bound(gl::TEXTURE_2D, gpu_state)?;
+        if pixels.resized {
+            pixels.resized = false;
+            return with_unpack_alignment(width as usize, || {
+                bound.write_pixels_and_generate_mipmap(
+                    0,
+                    internal_format,
+                    width as _,
+                    height as _,
+                    format,
+                    image.as_raw(),
+                )
+            });
+        }
+        for slot in fresh {
+            let [w, h] = slot.size;
+            let rect: Vec<u8> = (slot.min[1]..slot.min[1] + h)
+                .flat_map(|y| {
+                    let start = (y * width + slot.min[0]) as usize;
+                    image.as_raw()[start..start + w as usize].iter().copied()
+                })
+                .collect();
+            with_unpack_alignment(w as usize, || {
+                unsafe {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_2D,
+                        0,
+                        slot.min[0] as GLint,
+                        slot.min[1] as GLint,
+                        w as GLsizei,
+                        h as GLsizei,
+                        format,
+                        gl::UNSIGNED_BYTE,
+                        rect.as_ptr() as *const _,
+                    )
+                };
+                explode_if_gl_error()
+            })?;
+        }
+        unsafe { gl::GenerateMipmap(gl::TEXTURE_2D) };
+        explode_if_gl_error()
+    }
+}
+
+/// The `(internal format, format)` of the distance field: `GL_R8` where GLES 3 has it,
+/// otherwise `GL_LUMINANCE` (whose constant the `gl` crate's core profile bindings leave out).
+/// Either way the shader finds the distance in the red channel.
+fn single_channel_format() -> (GLint, GLenum) {
+    const LUMINANCE: GLenum = 0x1909;
+    match GlslDialect::current() {
+        GlslDialect::Es300 => (gl::R8 as GLint, gl::RED),
+        GlslDialect::Es100 => (LUMINANCE as GLint, LUMINANCE),
+    }
+}
+
+/// Rasterize `glyph` (scaled up by [OVERSAMPLE]) and encode its distance field for the atlas,
+/// one byte per atlas pixel of the `size` box whose top left corner is `min` (relative to the pen).
+fn glyph_distance_field(glyph: &PositionedGlyph, min: [i32; 2], size: [u32; 2]) -> Vec<u8> {
+    let bb = match glyph.pixel_bounding_box() {
+        Some(bb) => bb,
+        None => return vec![0; (size[0] * size[1]) as usize],
+    };
+    let width = (size[0] * OVERSAMPLE) as usize;
+    let height = (size[1] * OVERSAMPLE) as usize;
+    let os = OVERSAMPLE as i32;
+    let dx = (bb.min.x - min[0] * os) as usize;
+    let dy = (bb.min.y - min[1] * os) as usize;
+    let mut inside = vec![false; width * height];
+    glyph.draw(|x, y, v| {
+        inside[(y as usize + dy) * width + x as usize + dx] = v > 0.5;
+    });
+    encode_distance_field(&inside, size)
+}
+
+/// the distance field of `inside` (which is [OVERSAMPLE] times bigger than `size`),
+/// sampled and encoded for the atlas
+fn encode_distance_field(inside: &[bool], size: [u32; 2]) -> Vec<u8> {
+    let width = (size[0] * OVERSAMPLE) as usize;
+    let height = (size[1] * OVERSAMPLE) as usize;
+    let field = sdf::signed_distance_field(inside, width, height);
+
+    let mut rval = Vec::with_capacity((size[0] * size[1]) as usize);
+    for y in 0..size[1] {
+        for x in 0..size[0] {
+            // the oversampled pixel nearest the center of this one
+            let bx = (x * OVERSAMPLE + OVERSAMPLE / 2) as usize;
+            let by = (y * OVERSAMPLE + OVERSAMPLE / 2) as usize;
+            let distance = field[by * width + bx] / OVERSAMPLE as f32;
+            let encoded = (0.5 + distance / (2.0 * SDF_SPREAD)).clamp(0.0, 1.0);
+            rval.push((encoded * 255.0).round() as u8);
+        }
+    }
+    rval
+}
@@ -1,7 +1,6 @@
 precision highp float;
 
-varying vec3 ray;
-uniform float phase;
+#include "gorgon_fragment"
 
 void main() {
 
@@ -1,9 +1,8 @@
 precision highp float;
 
-varying vec3 ray;
-uniform float phase;
+#include "gorgon_fragment"
 
-#define PI 3.1415926538
+#include "pi"
 
 void main()
 {
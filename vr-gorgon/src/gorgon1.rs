@@ -1,10 +1,16 @@
-use crate::control_panel::{CPCursor, GorgonAxis, GorgonShape};
+use crate::control_panel::{CPCursor, GorgonAxis, GorgonParam, GorgonShape};
+use crate::error_overlay::ReportedError;
+use crate::glsl_dialect::compile_program;
+use crate::history::{History, ParamChange};
+use crate::shader_reload::ShaderFile;
 use gl::types::{GLfloat, GLint, GLuint};
-use gl_thin::gl_fancy::{GPUState, VertexBufferBundle};
+use gl_thin::gl_fancy::{GPUState, VertexBufferBundle, VertexBufferLite};
 use gl_thin::gl_helper::{GLErrorWrapper, Program};
 use gl_thin::linear::XrMatrix4x4f;
 use std::cell::RefCell;
 use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
 
 #[rustfmt::skip]
 static CUBE_VERTICES: &[GLfloat] = &[
@@ -43,6 +49,14 @@ impl GorgonSelector {
             GorgonSelector::TwoCircles => GorgonSelector::Spiral,
         }
     }
+
+    pub fn shader_file(&self) -> ShaderFile {
+        match self {
+            GorgonSelector::Spiral => ShaderFile::GorgonSpiral,
+            GorgonSelector::SphereAxes => ShaderFile::GorgonSphereAxes,
+            GorgonSelector::TwoCircles => ShaderFile::GorgonTwoCircles,
+        }
+    }
 }
 
 //
@@ -53,14 +67,47 @@ pub struct Gorgon1 {
     pub indices_len: usize,
 
     selector: GorgonSelector,
+    /// The program came from a file pushed to the shader directory.  Edits to the settings
+    /// wait (leaving them dirty) until the file is removed, rather than silently replacing it.
+    overridden: bool,
+    /// the outcome of the most recent rebuild from [MultiGorgonSettings], for [Self::take_rebuild_result]
+    rebuild_result: Option<Result<(), ReportedError>>,
 }
 
 impl Gorgon1 {
     pub(crate) fn next_gorgon(&mut self) -> Result<(), GLErrorWrapper> {
         self.selector.next();
         self.program = GorgonShader1::new(self.selector)?;
+        self.overridden = false;
         Ok(())
     }
+
+    /// Recompile if `file` is the shader for the current [GorgonSelector], and keep it
+    /// until [Self::revert_shader] even if the settings change.
+    /// Returns `Ok(false)` if the file is not ours.
+    pub fn reload_shader(
+        &mut self,
+        file: ShaderFile,
+        source: &str,
+    ) -> Result<bool, GLErrorWrapper> {
+        if file != self.selector.shader_file() {
+            return Ok(false);
+        }
+        self.program.rebuild_from_source(source)?;
+        self.overridden = true;
+        Ok(true)
+    }
+
+    /// Forget a replacement for `file`, if it is the shader in use, by rebuilding from `settings`.
+    /// Returns false if `file` is not ours.
+    pub fn revert_shader(&mut self, file: ShaderFile, settings: &MultiGorgonSettings) -> bool {
+        if file != self.selector.shader_file() {
+            return false;
+        }
+        self.overridden = false;
+        settings.invalidate_shader();
+        true
+    }
 }
 
 impl Gorgon1 {
@@ -84,11 +131,21 @@ impl Gorgon1 {
             indices_len,
             program,
             selector,
+            overridden: false,
+            rebuild_result: None,
         };
 
         Ok(rval)
     }
 
+    /// the [ReportedError::context] for failures to compile the shader built from [MultiGorgonSettings]
+    pub const SHADER_CONTEXT: &'static str = "gorgon shader";
+
+    /// if [Self::paint] rebuilt the program since the last call, did it work?
+    pub fn take_rebuild_result(&mut self) -> Option<Result<(), ReportedError>> {
+        self.rebuild_result.take()
+    }
+
     /// # parameters
     /// `phase` - should be a floating point number from \[0..1.0)
     pub fn paint(
@@ -98,9 +155,18 @@ impl Gorgon1 {
         settings: &MultiGorgonSettings,
         gpu_state: &mut GPUState,
     ) -> Result<(), GLErrorWrapper> {
-        if *settings.dirty.borrow() {
-            self.program.rebuild(settings)?;
+        if *settings.dirty.borrow() && !self.overridden {
+            // a broken shader must not leave us retrying every frame with a black sky,
+            // so we keep painting with the last good program and report the failure.
             settings.dirty.replace(false);
+            let fragment_shader = settings.fragment_shader();
+            let result = self
+                .program
+                .rebuild_from_source(fragment_shader.as_ref())
+                .map_err(|e| {
+                    ReportedError::shader_compile(Self::SHADER_CONTEXT, e, fragment_shader.as_ref())
+                });
+            self.rebuild_result = Some(result);
         }
 
         let program = &self.program.program;
@@ -120,7 +186,7 @@ impl Gorgon1 {
 
 //
 
-const VERTEX_SHADER: &str = "
+pub const VERTEX_SHADER: &str = "
 uniform mat4 matrix;
 
 attribute vec3 position;
@@ -133,8 +199,24 @@ void main() {
 }
 ";
 
+/// for painting a gorgon on the -1..1 square of [crate::control_panel::fab_uv_square_lesser]
+pub const PREVIEW_VERTEX_SHADER: &str = "
+uniform mat4 matrix;
+
+attribute vec2 position;
+
+varying vec3 ray;
+
+void main() {
+    gl_Position = matrix * vec4(position, 0.0, 1.0);
+    // a window onto the sky straight ahead, 90 degrees across
+    ray = vec3(position, -1.0);
+}
+";
+
 pub struct GorgonShader1 {
     program: Program,
+    vertex_shader: &'static str,
     sul_matrix: GLuint,
     sul_phase: GLuint,
     sal_position: GLuint,
@@ -147,12 +229,20 @@ impl GorgonShader1 {
             GorgonSelector::Spiral => gorgon_spiral(),
             GorgonSelector::TwoCircles => gorgon_two_circles(),
         };
-        let program = Program::compile(VERTEX_SHADER, fragment_shader)?;
+        Self::from_source(VERTEX_SHADER, fragment_shader)
+    }
+
+    pub fn from_source(
+        vertex_shader: &'static str,
+        fragment_shader: &str,
+    ) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(vertex_shader, fragment_shader)?;
         let sul_matrix = program.get_uniform_location("matrix")?;
         let sul_phase = program.get_uniform_location("phase")?;
         let sal_position = program.get_attribute_location("position")?;
         Ok(Self {
             program,
+            vertex_shader,
             sul_matrix,
             sul_phase,
             sal_position,
@@ -167,8 +257,12 @@ impl GorgonShader1 {
         Ok(())
     }
 
-    pub fn rebuild(&mut self, settings: &MultiGorgonSettings) -> Result<(), GLErrorWrapper> {
-        let program = Program::compile(VERTEX_SHADER, settings.fragment_shader())?;
+    /// on failure the previous program remains in place
+    pub fn rebuild_from_source(
+        &mut self,
+        fragment_shader: impl AsRef<str>,
+    ) -> Result<(), GLErrorWrapper> {
+        let program = compile_program(self.vertex_shader, fragment_shader.as_ref())?;
         self.sul_matrix = program.get_uniform_location("matrix")?;
         self.sul_phase = program.get_uniform_location("phase")?;
         self.sal_position = program.get_attribute_location("position")?;
@@ -177,10 +271,69 @@ impl GorgonShader1 {
     }
 }
 
+/// A gorgon painted on a quad instead of the sky, so several can be seen at once.
+/// Its layers read their parameters from uniforms, so changing them does not recompile.
+pub struct GorgonPreview {
+    program: GorgonShader1,
+    /// one per [GorgonFragmentShaderBuilder::add_uniform_layer]
+    sul_layers: Vec<GLuint>,
+    quad: VertexBufferBundle<'static, GLfloat, u8>,
+}
+
+impl GorgonPreview {
+    /// `builder` holds the layers added with [GorgonFragmentShaderBuilder::add_uniform_layer].
+    /// `square` is from [crate::control_panel::fab_uv_square_lesser]
+    pub fn new(
+        builder: &GorgonFragmentShaderBuilder,
+        square: &VertexBufferLite<'static, GLfloat, u8>,
+        gpu_state: &mut GPUState,
+    ) -> Result<Self, GLErrorWrapper> {
+        let program = GorgonShader1::from_source(PREVIEW_VERTEX_SHADER, &builder.build())?;
+        let sul_layers = (0..builder.uniform_layers)
+            .map(|idx| {
+                program
+                    .program
+                    .get_uniform_location(&format!("layer{}", idx))
+            })
+            .collect::<Result<_, _>>()?;
+        let quad = VertexBufferBundle::from_buffers(
+            gpu_state,
+            square,
+            4,
+            &[(program.sal_position, 2, 0)],
+        )?;
+        Ok(Self {
+            program,
+            sul_layers,
+            quad,
+        })
+    }
+
+    /// `matrix` maps the -1..1 square into clip space.
+    /// `layers` supplies the parameters of the uniform layers, in the order they were added.
+    pub fn paint(
+        &self,
+        matrix: &XrMatrix4x4f,
+        phase: GLfloat,
+        layers: &[GorgonSettings],
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        self.program.program.use_()?;
+        self.program.set_params(matrix, phase)?;
+        for (sul, settings) in self.sul_layers.iter().zip(layers) {
+            self.program
+                .program
+                .set_uniform_4fv(*sul as GLint, &settings.uniform_vec4())?;
+        }
+        let binding = self.quad.bind(gpu_state)?;
+        binding.draw_elements(gl::TRIANGLE_STRIP, 4, 0)?;
+        Ok(())
+    }
+}
+
 pub fn gorgon_two_circles() -> &'static str {
     "
-varying vec3 ray;
-uniform float phase;
+#include \"gorgon_fragment\"
 
 void main() {
 float d1 = distance(ray.xy, vec2(1,0));
@@ -211,6 +364,7 @@ pub fn gorgon_spiral() -> &'static str {
 
 //
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct GorgonSettings {
     pub enabled: bool,
     pub frequency: u8,
@@ -221,85 +375,162 @@ pub struct GorgonSettings {
 
 impl Default for GorgonSettings {
     fn default() -> Self {
-        Self {
+        let mut rval = Self {
             enabled: false,
-            frequency: 4,
-            speed: 1.0,
+            frequency: 0,
+            speed: 0.0,
             amplitude: 0.0,
             curl: 0.0,
+        };
+        for param in GorgonParam::ALL {
+            rval.set(param, param.meta().default);
         }
+        rval
     }
 }
 
 impl GorgonSettings {
+    pub fn get(&self, param: GorgonParam) -> f32 {
+        match param {
+            GorgonParam::Enable => self.enabled as u8 as f32,
+            GorgonParam::Frequency => self.frequency as f32,
+            GorgonParam::Speed => self.speed,
+            GorgonParam::Amplitude => self.amplitude,
+            GorgonParam::Curl => self.curl,
+        }
+    }
+
+    /// `value` is clamped to the parameter's [crate::param_meta::ParamMeta] range
+    pub fn set(&mut self, param: GorgonParam, value: f32) {
+        let value = param.meta().clamp(value);
+        match param {
+            GorgonParam::Enable => self.enabled = value != 0.0,
+            GorgonParam::Frequency => self.frequency = value as u8,
+            GorgonParam::Speed => self.speed = value,
+            GorgonParam::Amplitude => self.amplitude = value,
+            GorgonParam::Curl => self.curl = value,
+        }
+    }
+
     pub fn shader_header() -> String {
         "
 precision highp float;
 
-varying vec3 ray;
-uniform float phase;
-
-#define PI 3.1415926538
-
-vec3 c2s(vec3 rayn)
-{
-    float r = length(rayn.xy);
+#include \"gorgon_fragment\"
+"
+        .into()
+    }
 
-    float theta = atan(rayn.z, r);
-    float phi = atan(rayn.y, rayn.x);
-    return vec3(theta, phi, r);
-}
+    /// the helpers shared by every checker function.  Unlike [Self::shader_header] this does not
+    /// declare any uniforms or varyings, so it can be pasted into other shader environments
+    /// once [crate::glsl_include::preprocess] has expanded the `#include`s.
+    pub fn shader_common() -> String {
+        "
+#include \"pi\"
+#include \"c2s\"
 "
         .into()
     }
 
     pub fn spiral_shader(&self, index: usize) -> String {
-        format!(
-            "
+        spiral_checker(index, &LayerTerms::baked(self))
+    }
+
+    pub fn latitude_shader(&self, index: usize) -> String {
+        latitude_checker(index, &LayerTerms::baked(self))
+    }
+
+    pub fn cartesian_shader(&self, index: usize) -> String {
+        cartesian_checker(index, &LayerTerms::baked(self))
+    }
+
+    /// the values of the `layerN` uniform declared by [GorgonFragmentShaderBuilder::add_uniform_layer]
+    pub fn uniform_vec4(&self) -> [GLfloat; 4] {
+        [self.frequency as f32, self.speed, self.amplitude, self.curl]
+    }
+}
+
+/// The GLSL expressions a checker function uses for each parameter: either constants baked into
+/// the source, or the components of a `uniform vec4` so one program can show any settings.
+struct LayerTerms {
+    frequency: String,
+    speed: String,
+    amplitude: String,
+    curl: String,
+}
+
+impl LayerTerms {
+    fn baked(settings: &GorgonSettings) -> Self {
+        Self {
+            frequency: format!("{}.0", settings.frequency),
+            speed: format!("{:.6}", settings.speed),
+            amplitude: format!("{:.6}", settings.amplitude),
+            curl: format!("{:.6}", settings.curl),
+        }
+    }
+
+    /// in the order of [GorgonSettings::uniform_vec4]
+    fn uniform(name: &str) -> Self {
+        Self {
+            frequency: format!("{}.x", name),
+            speed: format!("{}.y", name),
+            amplitude: format!("{}.z", name),
+            curl: format!("{}.w", name),
+        }
+    }
+}
+
+fn spiral_checker(index: usize, terms: &LayerTerms) -> String {
+    format!(
+        "
 bool checker{index}(vec3 sc, vec3 rayn)
 {{
     float theta = sc.x;
     float phi = sc.y;
-    return 0.5 > mod(phi*{frequency}.0/(2.0*PI) + {curl:.6}*theta/PI + phase*{speed:.6}, 1.0);
+    return 0.5 > mod(phi*{frequency}/(2.0*PI) + {curl}*theta/PI + phase*{speed}, 1.0);
 }}
 ",
-            index = index,
-            frequency = self.frequency,
-            speed = self.speed,
-            curl = self.curl,
-        )
-    }
+        index = index,
+        frequency = terms.frequency,
+        speed = terms.speed,
+        curl = terms.curl,
+    )
+}
 
-    pub fn latitude_shader(&self, index: usize) -> String {
-        format!(
-            "
+fn latitude_checker(index: usize, terms: &LayerTerms) -> String {
+    format!(
+        "
 bool checker{index}(vec3 sc, vec3 rayn)
 {{
     float theta = sc.x;
-    float offset = {amplitude:.6}*sin(mod(phase*2.0*{speed:.6}, 2.0)*PI);
-    return 0.5 > mod( theta * {frequency}.0 / (2.0*PI) + offset, 1.0);
+    float offset = {amplitude}*sin(mod(phase*2.0*{speed}, 2.0)*PI);
+    // curl makes the bands wave up and down as they go around the axis
+    float twist = {curl}*sin(sc.y);
+    return 0.5 > mod( theta * {frequency} / (2.0*PI) + offset + twist, 1.0);
 }}",
-            index = index,
-            frequency = self.frequency,
-            speed = self.speed,
-            amplitude = self.amplitude,
-        )
-    }
+        index = index,
+        frequency = terms.frequency,
+        speed = terms.speed,
+        amplitude = terms.amplitude,
+        curl = terms.curl,
+    )
+}
 
-    pub fn cartesian_shader(&self, index: usize) -> String {
-        format!(
-            "
+fn cartesian_checker(index: usize, terms: &LayerTerms) -> String {
+    format!(
+        "
 bool checker{index}(vec3 sc, vec3 rayn)
 {{
-    float offset = {amplitude:.6}*sin(mod(phase*2.0*{speed:.6}, 2.0)*PI);
-    return 0.5 > mod( rayn.z * {frequency}.0 + offset, 1.0);
+    float offset = {amplitude}*sin(mod(phase*2.0*{speed}, 2.0)*PI);
+    float twist = {curl}*sin(sc.y);
+    return 0.5 > mod( rayn.z * {frequency} + offset + twist, 1.0);
 }}",
-            index = index,
-            frequency = self.frequency,
-            speed = self.speed,
-            amplitude = self.amplitude,
-        )
-    }
+        index = index,
+        frequency = terms.frequency,
+        speed = terms.speed,
+        amplitude = terms.amplitude,
+        curl = terms.curl,
+    )
 }
 
 //
@@ -307,6 +538,8 @@ bool checker{index}(vec3 sc, vec3 rayn)
 #[derive(Default)]
 pub struct GorgonFragmentShaderBuilder {
     pieces: Vec<(String, String)>,
+    /// how many `layerN` uniforms [Self::add_uniform_layer] has declared
+    uniform_layers: usize,
 }
 
 impl GorgonFragmentShaderBuilder {
@@ -328,9 +561,55 @@ impl GorgonFragmentShaderBuilder {
         self.pieces.push((swizzle.into(), glsl))
     }
 
+    /// add one layer, using the swizzle for `axis` from [AXIS_SWIZZLES]
+    pub fn add_layer(&mut self, shape: GorgonShape, axis: GorgonAxis, settings: &GorgonSettings) {
+        let swizzle = AXIS_SWIZZLES[axis.index() as usize];
+        match shape {
+            GorgonShape::Spiral => self.add_spiral(settings, swizzle),
+            GorgonShape::Latitude => self.add_latitude(settings, swizzle),
+            GorgonShape::Cartesian => self.add_cartesian(settings, swizzle),
+        }
+    }
+
+    /// Like [Self::add_layer], but the parameters come from a `uniform vec4 layerN`
+    /// (see [GorgonSettings::uniform_vec4]) where N counts the uniform layers added so far.
+    /// A [GorgonPreview] built from this source can show any settings without recompiling.
+    pub fn add_uniform_layer(&mut self, shape: GorgonShape, axis: GorgonAxis) {
+        let index = self.pieces.len();
+        let name = format!("layer{}", self.uniform_layers);
+        let terms = LayerTerms::uniform(&name);
+        let checker = match shape {
+            GorgonShape::Spiral => spiral_checker(index, &terms),
+            GorgonShape::Latitude => latitude_checker(index, &terms),
+            GorgonShape::Cartesian => cartesian_checker(index, &terms),
+        };
+        let glsl = format!("\nuniform vec4 {};\n{}", name, checker);
+        let swizzle = AXIS_SWIZZLES[axis.index() as usize];
+        self.pieces.push((swizzle.into(), glsl));
+        self.uniform_layers += 1;
+    }
+
     pub fn build(&self) -> String {
-        use std::fmt::Write;
         let mut rval = GorgonSettings::shader_header();
+        rval.push_str(&self.build_library());
+        rval.push_str(
+            "
+void main()
+{
+    float g = gorgon_intensity(normalize(ray));
+    gl_FragColor = vec4(g,g,g, 1.0);
+}
+",
+        );
+
+        rval
+    }
+
+    /// The checker functions and a `float gorgon_intensity(vec3 rayn)` which combines them.
+    /// The caller must provide `uniform float phase` (or an equivalent global) before this code.
+    pub fn build_library(&self) -> String {
+        use std::fmt::Write;
+        let mut rval = GorgonSettings::shader_common();
 
         let mut swizzles = BTreeSet::new();
         let mut swizzle_glsl = String::new();
@@ -354,13 +633,10 @@ impl GorgonFragmentShaderBuilder {
         let _ = writeln!(
             &mut rval,
             "
-void main()
+float gorgon_intensity(vec3 rayn)
 {{
-    vec3 rayn = normalize(ray);
-
 {swizzle_glsl}
-    float g = ({xor_glsl}        ) ? 1.0 : 0.0;
-    gl_FragColor = vec4(g,g,g, 1.0);
+    return ({xor_glsl}        ) ? 1.0 : 0.0;
 }}",
             swizzle_glsl = swizzle_glsl,
             xor_glsl = xor_glsl
@@ -372,16 +648,32 @@ void main()
 
 //
 
+/// the coordinate swizzle used for the X, Y, and Z layer of each [GorgonShape]
+pub const AXIS_SWIZZLES: [&str; 3] = ["yzx", "zxy", "xyz"];
+
 #[derive(Default)]
 pub struct MultiGorgonSettings {
     dirty: RefCell<bool>,
-    pub spirals: [GorgonSettings; 3],
-    pub latitudes: [GorgonSettings; 3],
-    pub cartesians: [GorgonSettings; 3],
+    /// every edit made through the methods below, for undo and redo
+    history: History,
+    /// private, like [Self::lookup_mut], so that edits can only be made through methods which record them
+    spirals: [GorgonSettings; 3],
+    latitudes: [GorgonSettings; 3],
+    cartesians: [GorgonSettings; 3],
 }
 
 impl MultiGorgonSettings {
-    pub fn lookup_mut(&mut self, shape: GorgonShape, axis: GorgonAxis) -> &mut GorgonSettings {
+    /// the defaults, except for `layers`, with nothing to undo
+    pub fn new(layers: &[(GorgonShape, GorgonAxis, GorgonSettings)]) -> Self {
+        let mut rval = Self::default();
+        for (shape, axis, settings) in layers {
+            *rval.lookup_mut(*shape, *axis) = *settings;
+        }
+        rval
+    }
+
+    /// private so that every edit goes through [Self::change] and lands in the [History]
+    fn lookup_mut(&mut self, shape: GorgonShape, axis: GorgonAxis) -> &mut GorgonSettings {
         let shape = self.shape_for_mut(shape);
         &mut shape[axis.index() as usize]
     }
@@ -392,60 +684,127 @@ impl MultiGorgonSettings {
     }
 
     pub fn toggle_enabled(&mut self, cursor: CPCursor) {
-        let gorgon = self.lookup_mut(cursor.row, cursor.axis);
-        gorgon.enabled = !gorgon.enabled;
-        self.dirty.replace(true);
+        let enabled = self.lookup(cursor.row, cursor.axis).enabled;
+        self.change(GorgonParam::Enable, !enabled as u8 as f32, cursor, false);
     }
 
-    pub fn adjust_frequency(&mut self, delta: i32, cursor: CPCursor) {
-        if delta == 0 {
+    /// add `delta` to one parameter, staying inside its range.
+    /// Consecutive adjustments of the same parameter are undone as one step, until [Self::end_adjustment].
+    pub fn adjust_param(&mut self, param: GorgonParam, delta: f32, cursor: CPCursor) {
+        if delta == 0.0 {
             return;
         }
 
-        let gorgon = self.lookup_mut(cursor.row, cursor.axis);
-        let freq = gorgon.frequency as i32 + delta;
-        gorgon.frequency = freq.max(1).min(255) as u8;
-        self.dirty.replace(true);
+        let value = self.lookup(cursor.row, cursor.axis).get(param) + delta;
+        self.change(param, value, cursor, true);
     }
 
-    pub fn adjust_speed(&mut self, delta: f32, cursor: CPCursor) {
-        if delta == 0.0 {
-            return;
-        }
+    /// replace one parameter outright, like typing it on the keypad
+    pub fn set_param(&mut self, param: GorgonParam, value: f32, cursor: CPCursor) {
+        self.change(param, value, cursor, false);
+    }
 
+    fn change(&mut self, param: GorgonParam, value: f32, cursor: CPCursor, coalesce: bool) {
         let gorgon = self.lookup_mut(cursor.row, cursor.axis);
-        gorgon.speed += 0.1 * delta;
+        let before = gorgon.get(param);
+        gorgon.set(param, value);
+        let change = ParamChange {
+            shape: cursor.row,
+            axis: cursor.axis,
+            param,
+            before,
+            after: gorgon.get(param),
+        };
+        self.history.record(change, coalesce);
         self.dirty.replace(true);
     }
 
-    pub fn adjust_amplitude(&mut self, delta: f32, cursor: CPCursor) {
-        if delta == 0.0 {
-            return;
-        }
+    /// make the next [Gorgon1::paint] rebuild its program from these settings
+    pub fn invalidate_shader(&self) {
+        self.dirty.replace(true);
+    }
 
-        let gorgon = self.lookup_mut(cursor.row, cursor.axis);
-        gorgon.amplitude += 0.1 * delta;
+    /// the thumbstick was released, or the pointer stopped dragging
+    pub fn end_adjustment(&mut self) {
+        self.history.seal();
+    }
+
+    /// every layer, in the order of [GorgonShape::ALL] then [GorgonAxis::ALL]
+    pub fn layers(&self) -> Vec<(GorgonShape, GorgonAxis, GorgonSettings)> {
+        GorgonShape::ALL
+            .into_iter()
+            .flat_map(|shape| {
+                GorgonAxis::ALL
+                    .into_iter()
+                    .map(move |axis| (shape, axis, *self.lookup(shape, axis)))
+            })
+            .collect()
+    }
+
+    /// overwrite the listed layers as a single undo step
+    pub fn replace_layers(&mut self, layers: &[(GorgonShape, GorgonAxis, GorgonSettings)]) {
+        let mut changes = vec![];
+        for (shape, axis, replacement) in layers {
+            let gorgon = self.lookup_mut(*shape, *axis);
+            for param in GorgonParam::ALL {
+                let before = gorgon.get(param);
+                gorgon.set(param, replacement.get(param));
+                changes.push(ParamChange {
+                    shape: *shape,
+                    axis: *axis,
+                    param,
+                    before,
+                    after: gorgon.get(param),
+                });
+            }
+        }
+        self.history.record_group(changes);
         self.dirty.replace(true);
     }
 
-    pub fn adjust_curl(&mut self, delta: f32, cursor: CPCursor) {
-        if delta == 0.0 {
-            return;
+    /// returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let changes = match self.history.undo() {
+            Some(changes) => changes.to_vec(),
+            None => return false,
+        };
+        for change in changes.iter().rev() {
+            self.revisit(change, change.before);
         }
+        true
+    }
 
-        let gorgon = self.lookup_mut(cursor.row, cursor.axis);
-        gorgon.curl += 0.1 * delta;
+    /// returns false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let changes = match self.history.redo() {
+            Some(changes) => changes.to_vec(),
+            None => return false,
+        };
+        for change in &changes {
+            self.revisit(change, change.after);
+        }
+        true
+    }
+
+    /// put one side of a recorded change back, without recording it again
+    fn revisit(&mut self, change: &ParamChange, value: f32) {
+        self.lookup_mut(change.shape, change.axis)
+            .set(change.param, value);
         self.dirty.replace(true);
     }
 
-    pub fn shape_for(&self, shape: GorgonShape) -> &[GorgonSettings; 3] {
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    fn shape_for(&self, shape: GorgonShape) -> &[GorgonSettings; 3] {
         match shape {
             GorgonShape::Spiral => &self.spirals,
             GorgonShape::Latitude => &self.latitudes,
             GorgonShape::Cartesian => &self.cartesians,
         }
     }
-    pub fn shape_for_mut(&mut self, shape: GorgonShape) -> &mut [GorgonSettings; 3] {
+    fn shape_for_mut(&mut self, shape: GorgonShape) -> &mut [GorgonSettings; 3] {
         match shape {
             GorgonShape::Spiral => &mut self.spirals,
             GorgonShape::Latitude => &mut self.latitudes,
@@ -453,27 +812,126 @@ impl MultiGorgonSettings {
         }
     }
 
-    pub(crate) fn fragment_shader(&self) -> impl AsRef<str> + Sized {
+    pub fn shader_builder(&self) -> GorgonFragmentShaderBuilder {
         let mut builder = GorgonFragmentShaderBuilder::default();
-        let swizzles = ["yzx", "zxy", "xyz"];
-        for (swizzle, settings) in swizzles.iter().zip(self.spirals.iter()) {
+        for (shape, axis, settings) in self.layers() {
             if settings.enabled {
-                builder.add_spiral(settings, swizzle);
+                builder.add_layer(shape, axis, &settings);
             }
         }
-        for (swizzle, settings) in swizzles.iter().zip(self.latitudes.iter()) {
-            if settings.enabled {
-                builder.add_latitude(settings, swizzle);
+
+        builder
+    }
+
+    /// `shape.axis.param=value` lines, as written by [Self::save]
+    pub fn to_text(&self) -> String {
+        let mut rval = String::new();
+        for (shape, axis, settings) in self.layers() {
+            for param in GorgonParam::ALL {
+                let _ = writeln!(
+                    &mut rval,
+                    "{}.{}.{}={}",
+                    shape.key(),
+                    axis.letter(),
+                    param.key(),
+                    settings.get(param)
+                );
             }
         }
-        for (swizzle, settings) in swizzles.iter().zip(self.cartesians.iter()) {
-            if settings.enabled {
-                builder.add_cartesian(settings, swizzle);
+        rval
+    }
+
+    /// The inverse of [Self::to_text], with a fresh [History].
+    /// Layers or parameters which are missing keep their defaults, and values outside
+    /// their [crate::param_meta::ParamMeta] are logged and ignored rather than clamped.
+    pub fn from_text(text: &str) -> Self {
+        let mut rval = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match parse_setting(line) {
+                Some((shape, axis, param, value)) if param.meta().contains(value) => {
+                    rval.lookup_mut(shape, axis).set(param, value)
+                }
+                Some(_) => log::warn!("ignoring out of range setting {:?}", line),
+                None => log::warn!("ignoring malformed setting {:?}", line),
             }
         }
+        rval.dirty.replace(true);
+        rval
+    }
 
-        let rval = builder.build();
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::from_text(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    pub(crate) fn fragment_shader(&self) -> impl AsRef<str> + Sized {
+        let rval = self.shader_builder().build();
         log::debug!("new shader\n{}", &rval);
         rval
     }
 }
+
+/// one `shape.axis.param=value` line of [MultiGorgonSettings::to_text]
+fn parse_setting(line: &str) -> Option<(GorgonShape, GorgonAxis, GorgonParam, f32)> {
+    let (key, value) = line.split_once('=')?;
+    let mut parts = key.trim().split('.');
+    let shape = parts.next()?;
+    let shape = GorgonShape::ALL.into_iter().find(|s| s.key() == shape)?;
+    let axis = parts.next()?;
+    let axis = GorgonAxis::ALL.into_iter().find(|a| a.letter() == axis)?;
+    let param = parts.next()?;
+    let param = GorgonParam::ALL.into_iter().find(|p| p.key() == param)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((shape, axis, param, value.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_through_text() {
+        let mut settings = MultiGorgonSettings::default();
+        let cursor = CPCursor {
+            row: GorgonShape::Latitude,
+            axis: GorgonAxis::Y,
+            subrow: GorgonParam::Speed,
+        };
+        settings.set_param(GorgonParam::Enable, 1.0, cursor);
+        settings.set_param(GorgonParam::Frequency, 40.0, cursor);
+        settings.set_param(GorgonParam::Speed, -2.5, cursor);
+        let loaded = MultiGorgonSettings::from_text(&settings.to_text());
+        assert_eq!(loaded.layers(), settings.layers());
+        assert_eq!(loaded.history().position(), (0, 0));
+    }
+
+    #[test]
+    fn loading_ignores_values_outside_their_range() {
+        let loaded = MultiGorgonSettings::from_text(
+            "spiral.x.frequency=1000\nspiral.x.speed=NaN\nspiral.x.frequency=2.5\n\
+             spiral.x.curl=1.5\nspiral.w.curl=1\nspiral.x.curl.extra=1\nnonsense\n",
+        );
+        let spiral = loaded.lookup(GorgonShape::Spiral, GorgonAxis::X);
+        let default = GorgonSettings::default();
+        assert_eq!(spiral.frequency, default.frequency);
+        assert_eq!(spiral.speed, default.speed);
+        assert_eq!(spiral.curl, 1.5);
+    }
+
+    #[test]
+    fn uniform_layers_read_their_own_uniform() {
+        let mut builder = GorgonFragmentShaderBuilder::default();
+        builder.add_uniform_layer(GorgonShape::Spiral, GorgonAxis::X);
+        builder.add_uniform_layer(GorgonShape::Cartesian, GorgonAxis::Z);
+        let source = builder.build();
+        assert!(source.contains("uniform vec4 layer0;"));
+        assert!(source.contains("uniform vec4 layer1;"));
+        assert!(source.contains("rayn.z * layer1.x"));
+        assert!(!source.contains("layer2"));
+    }
+}
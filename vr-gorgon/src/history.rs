@@ -0,0 +1,198 @@
+use crate::control_panel::{GorgonAxis, GorgonParam, GorgonShape};
+use std::collections::VecDeque;
+
+/// how many changes can be undone before the oldest are forgotten
+pub const HISTORY_LIMIT: usize = 100;
+
+/// One reversible change to a [crate::gorgon1::MultiGorgonSettings]:
+/// a single parameter of a single layer, and its value on either side of the change.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ParamChange {
+    pub shape: GorgonShape,
+    pub axis: GorgonAxis,
+    pub param: GorgonParam,
+    pub before: f32,
+    pub after: f32,
+}
+
+impl ParamChange {
+    /// changes to the same parameter of the same layer can be merged
+    fn same_target(&self, other: &ParamChange) -> bool {
+        self.shape == other.shape && self.axis == other.axis && self.param == other.param
+    }
+}
+
+/// The undo and redo stacks.  Each step is a group of changes which are undone together.
+#[derive(Default)]
+pub struct History {
+    done: VecDeque<Vec<ParamChange>>,
+    undone: Vec<Vec<ParamChange>>,
+    /// the most recent change is still being adjusted, and absorbs further adjustments of its parameter
+    open: bool,
+    /// the redo stack from before the open step, put back if the step comes to nothing
+    shelved: Vec<Vec<ParamChange>>,
+}
+
+impl History {
+    /// `coalesce` is for continuous adjustments (the thumbstick, or dragging with the pointer)
+    /// which should be undone all at once.  Recording anything discards the redo stack,
+    /// unless it is an adjustment which ends up back where it started.
+    pub fn record(&mut self, change: ParamChange, coalesce: bool) {
+        if change.before == change.after {
+            return;
+        }
+
+        if let (true, true, Some([last])) = (
+            coalesce,
+            self.open,
+            self.done.back_mut().map(Vec::as_mut_slice),
+        ) {
+            if last.same_target(&change) {
+                last.after = change.after;
+                if same_step(last.param, last.after, last.before) {
+                    // adjusted back to where it started, so there is nothing to undo,
+                    // and what could be redone before still can be
+                    self.done.pop_back();
+                    self.undone = std::mem::take(&mut self.shelved);
+                    self.open = false;
+                }
+                return;
+            }
+        }
+
+        self.seal();
+        let undone = std::mem::take(&mut self.undone);
+        if coalesce {
+            self.shelved = undone;
+        }
+        self.push(vec![change]);
+        self.open = coalesce;
+    }
+
+    /// several changes made at once, like replacing all the settings, which are undone as one step
+    pub fn record_group(&mut self, changes: Vec<ParamChange>) {
+        let changes: Vec<_> = changes
+            .into_iter()
+            .filter(|change| change.before != change.after)
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+        self.seal();
+        self.undone.clear();
+        self.push(changes);
+    }
+
+    fn push(&mut self, changes: Vec<ParamChange>) {
+        self.done.push_back(changes);
+        if self.done.len() > HISTORY_LIMIT {
+            self.done.pop_front();
+        }
+    }
+
+    /// the next adjustment starts a new step, even if it is for the same parameter
+    pub fn seal(&mut self) {
+        self.open = false;
+        self.shelved.clear();
+    }
+
+    /// the changes to revert, which move to the redo stack
+    pub fn undo(&mut self) -> Option<&[ParamChange]> {
+        self.seal();
+        let changes = self.done.pop_back()?;
+        self.undone.push(changes);
+        self.undone.last().map(Vec::as_slice)
+    }
+
+    /// the changes to reapply, which move back to the undo stack
+    pub fn redo(&mut self) -> Option<&[ParamChange]> {
+        self.seal();
+        let changes = self.undone.pop()?;
+        self.done.push_back(changes);
+        self.done.back().map(Vec::as_slice)
+    }
+
+    /// how many steps can be undone, out of how many steps are remembered
+    pub fn position(&self) -> (usize, usize) {
+        (self.done.len(), self.done.len() + self.undone.len())
+    }
+}
+
+/// `a` and `b` are the same to the nearest [crate::param_meta::ParamMeta::step],
+/// since adding and then subtracting a step rarely gets back exactly to where it started
+fn same_step(param: GorgonParam, a: f32, b: f32) -> bool {
+    let step = param.meta().step;
+    (a / step).round() == (b / step).round()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speed_change(before: f32, after: f32) -> ParamChange {
+        ParamChange {
+            shape: GorgonShape::Spiral,
+            axis: GorgonAxis::X,
+            param: GorgonParam::Speed,
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn adjustments_coalesce_into_one_step() {
+        let mut history = History::default();
+        history.record(speed_change(1.0, 1.1), true);
+        history.record(speed_change(1.1, 1.2), true);
+        assert_eq!(history.position(), (1, 1));
+        assert_eq!(history.undo(), Some(&[speed_change(1.0, 1.2)][..]));
+    }
+
+    #[test]
+    fn an_adjustment_back_to_the_start_leaves_nothing_to_undo() {
+        let mut history = History::default();
+        let step = GorgonParam::Speed.meta().step;
+        let start = 1.3;
+        let up = start + step;
+        let back = up - step;
+        history.record(speed_change(start, up), true);
+        history.record(speed_change(up, back), true);
+        assert_eq!(history.position(), (0, 0));
+        // and moving away again starts a fresh step
+        history.record(speed_change(back, 0.9), true);
+        assert_eq!(history.undo(), Some(&[speed_change(back, 0.9)][..]));
+    }
+
+    #[test]
+    fn an_adjustment_back_to_the_start_keeps_the_redo_stack() {
+        let mut history = History::default();
+        history.record(speed_change(1.0, 2.0), false);
+        history.undo();
+        assert_eq!(history.position(), (0, 1));
+        history.record(speed_change(1.0, 1.1), true);
+        history.record(speed_change(1.1, 1.0), true);
+        assert_eq!(history.position(), (0, 1));
+        assert_eq!(history.redo(), Some(&[speed_change(1.0, 2.0)][..]));
+    }
+
+    #[test]
+    fn a_kept_adjustment_discards_the_redo_stack() {
+        let mut history = History::default();
+        history.record(speed_change(1.0, 2.0), false);
+        history.undo();
+        history.record(speed_change(1.0, 1.1), true);
+        history.seal();
+        history.record(speed_change(1.1, 1.0), true);
+        assert_eq!(history.position(), (2, 2));
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn sealing_starts_a_new_step() {
+        let mut history = History::default();
+        history.record(speed_change(1.0, 1.1), true);
+        history.seal();
+        history.record(speed_change(1.1, 1.0), true);
+        assert_eq!(history.position(), (2, 2));
+    }
+}
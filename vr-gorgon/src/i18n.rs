@@ -0,0 +1,279 @@
+use crate::file_watch::WatchedDirectory;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// the locale whose strings stand in for any a translation lacks
+const FALLBACK_LOCALE: &str = "en";
+/// names the locale to use, in the watched directory
+const SELECTION_FILE: &str = "locale.txt";
+/// the locales [embedded_source] has
+const EMBEDDED_LOCALES: [&str; 2] = ["en", "de"];
+
+/// the translations compiled into the APK
+fn embedded_source(locale: &str) -> Option<&'static str> {
+    match locale {
+        "en" => Some(include_str!("locales/en.toml")),
+        "de" => Some(include_str!("locales/de.toml")),
+        _ => None,
+    }
+}
+
+static FALLBACK: Lazy<HashMap<String, String>> =
+    Lazy::new(|| parse_strings(embedded_source(FALLBACK_LOCALE).unwrap(), FALLBACK_LOCALE));
+
+/// the strings of the selected locale, keyed by message ID
+static CURRENT: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+/// the name of the selected locale
+static SELECTED: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(FALLBACK_LOCALE.to_string()));
+
+/// the locale [select_locale] last switched to
+pub fn current_locale() -> String {
+    SELECTED.read().unwrap().clone()
+}
+
+/// The text for message `id` in the current locale, or in English if it has not been translated.
+/// An unknown `id` is shown as itself, so the mistake is visible on the panel.
+pub fn tr(id: &str) -> String {
+    lookup(&CURRENT.read().unwrap(), id)
+}
+
+/// [tr] with `current` as the selected locale's strings
+fn lookup(current: &HashMap<String, String>, id: &str) -> String {
+    current
+        .get(id)
+        .or_else(|| FALLBACK.get(id))
+        .cloned()
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// like [tr], with each `{name}` in the text replaced by the value paired with `name`
+pub fn tr_args(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    substitute(tr(id), args)
+}
+
+/// the substitution of [tr_args]
+fn substitute(text: String, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(text, |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+/// Switch to `locale`, from `<locale>.toml` in `dir` if there is one, otherwise from the embedded copy.
+/// If neither exists every message falls back to English.
+pub fn select_locale(locale: &str, dir: Option<&Path>) {
+    let from_file = dir
+        .map(|dir| dir.join(format!("{}.toml", locale)))
+        .filter(|path| path.is_file())
+        .and_then(|path| match std::fs::read_to_string(&path) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                log::warn!("failed to read {:?}: {}", path, e);
+                None
+            }
+        });
+    let strings = match (&from_file, embedded_source(locale)) {
+        (Some(source), _) => parse_strings(source, locale),
+        (None, Some(source)) => parse_strings(source, locale),
+        (None, None) => {
+            log::warn!("no strings for locale {:?}; using English", locale);
+            HashMap::new()
+        }
+    };
+    log::info!("locale {} has {} strings", locale, strings.len());
+    *CURRENT.write().unwrap() = strings;
+    *SELECTED.write().unwrap() = locale.to_string();
+}
+
+/// Read the `message-id = "text"` lines of a locale file (a small subset of TOML).
+/// Blank lines and `#` comments are skipped; anything else that does not parse is logged and skipped.
+fn parse_strings(source: &str, locale: &str) -> HashMap<String, String> {
+    let mut rval = HashMap::new();
+    for (idx, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line
+            .split_once('=')
+            .and_then(|(id, value)| Some((id.trim(), unquote(value.trim())?)));
+        match parsed {
+            Some((id, text)) if !id.is_empty() => {
+                rval.insert(id.to_string(), text);
+            }
+            _ => log::warn!(
+                "{}.toml line {}: expected `message-id = \"text\"`",
+                locale,
+                idx + 1
+            ),
+        }
+    }
+    rval
+}
+
+/// the contents of a double-quoted string, with `\"`, `\\`, `\n` and `\t` escapes
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut rval = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => rval.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                other @ ('"' | '\\') => other,
+                _ => return None,
+            }),
+            '"' => return None,
+            c => rval.push(c),
+        }
+    }
+    Some(rval)
+}
+
+//
+
+/// Watch a directory for `locale.txt`, which names the locale to show, and for `<locale>.toml` translations,
+/// so the language can be changed (or a translation tried out) with `adb push` while the app runs.
+/// Removing `locale.txt` goes back to English.
+pub struct LocaleDirectory {
+    watched: WatchedDirectory,
+}
+
+impl LocaleDirectory {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            watched: WatchedDirectory::new(dir),
+        }
+    }
+
+    /// if `locale.txt` or any translation appeared, changed or disappeared since the previous poll,
+    /// select the locale again
+    pub fn poll(&mut self) {
+        let changes = self.watched.poll(|path| {
+            path.file_name().and_then(|name| name.to_str()) == Some(SELECTION_FILE)
+                || path.extension().and_then(|ext| ext.to_str()) == Some("toml")
+        });
+        if changes.is_empty() {
+            return;
+        }
+        let dir = self.watched.dir();
+        let locale = std::fs::read_to_string(dir.join(SELECTION_FILE))
+            .map(|text| text.trim().to_string())
+            .unwrap_or_default();
+        let locale = if locale.is_empty() {
+            FALLBACK_LOCALE
+        } else {
+            &locale
+        };
+        select_locale(locale, Some(dir));
+    }
+
+    /// Switch to the locale after the current one (see [available_locales]),
+    /// and write it to `locale.txt` so it is still selected next time.
+    pub fn select_next(&mut self) {
+        let dir = self.watched.dir();
+        let locale = next_locale(Some(dir));
+        if let Err(e) = std::fs::write(dir.join(SELECTION_FILE), format!("{}\n", locale)) {
+            log::warn!("failed to save the locale in {:?}: {}", dir, e);
+        }
+        select_locale(&locale, Some(dir));
+    }
+}
+
+/// the locales with embedded strings, and any with a `<locale>.toml` in `dir`, in alphabetical order
+pub fn available_locales(dir: Option<&Path>) -> Vec<String> {
+    let mut rval: BTreeSet<String> = EMBEDDED_LOCALES.iter().map(|s| s.to_string()).collect();
+    if let Some(entries) = dir.and_then(|dir| std::fs::read_dir(dir).ok()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    rval.insert(stem.to_string());
+                }
+            }
+        }
+    }
+    rval.into_iter().collect()
+}
+
+/// the locale after [current_locale] in [available_locales], wrapping around to the first
+pub fn next_locale(dir: Option<&Path>) -> String {
+    let locales = available_locales(dir);
+    let current = current_locale();
+    let next = match locales.iter().position(|locale| *locale == current) {
+        Some(idx) => (idx + 1) % locales.len(),
+        None => 0,
+    };
+    locales[next].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        assert_eq!(Some("plain".to_string()), unquote("\"plain\""));
+        assert_eq!(
+            Some("say \"hi\"\\\n\t".to_string()),
+            unquote(r#""say \"hi\"\\\n\t""#)
+        );
+        assert_eq!(Some(String::new()), unquote("\"\""));
+        assert_eq!(None, unquote(r#""\x""#), "unknown escape");
+        assert_eq!(None, unquote(r#""dangling\""#), "escape at the end");
+        assert_eq!(None, unquote(r#""a"b""#), "bare quote");
+        assert_eq!(None, unquote("unquoted"));
+        assert_eq!(None, unquote("\"open"));
+    }
+
+    #[test]
+    fn skips_what_does_not_parse() {
+        let source = r#"
+# a comment
+good = "kept"
+  spaced   =   "also kept"
+no-equals "text"
+unquoted = text
+= "no id"
+bad-escape = "\q"
+"#;
+        let strings = parse_strings(source, "test");
+        assert_eq!(2, strings.len());
+        assert_eq!("kept", strings["good"]);
+        assert_eq!("also kept", strings["spaced"]);
+    }
+
+    #[test]
+    fn embedded_locales_parse() {
+        let en = parse_strings(embedded_source("en").unwrap(), "en");
+        for locale in EMBEDDED_LOCALES {
+            let strings = parse_strings(embedded_source(locale).unwrap(), locale);
+            for id in strings.keys() {
+                assert!(en.contains_key(id), "{} has {} but en does not", locale, id);
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        let mut de = parse_strings(embedded_source("de").unwrap(), "de");
+        let translated = de["button-locale"].clone();
+        de.remove("undo-position");
+
+        assert_eq!(translated, lookup(&de, "button-locale"));
+        assert_eq!(FALLBACK["undo-position"], lookup(&de, "undo-position"));
+        assert_eq!("no-such-message", lookup(&de, "no-such-message"));
+    }
+
+    #[test]
+    fn substitution() {
+        let text = substitute(
+            "undo {position}/{len}, {position} again, {missing}".to_string(),
+            &[("position", &3), ("len", &"7"), ("unused", &1.5)],
+        );
+        assert_eq!("undo 3/7, 3 again, {missing}", text);
+    }
+}
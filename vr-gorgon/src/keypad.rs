@@ -0,0 +1,389 @@
+use crate::control_panel::CPCursor;
+use crate::fonts;
+use crate::i18n;
+use crate::menu::{NumericField, PanelRect, MENU_TOP};
+use crate::sprite_atlas::SpriteAtlasBuilder;
+use crate::surprise::SEED_LIMIT;
+use crate::text_layout::TextStyle;
+use crate::text_painting::TextSpan;
+use crate::widgets::Widgets;
+use gl_thin::gl_fancy::GPUState;
+use gl_thin::gl_helper::GLErrorWrapper;
+use gl_thin::linear::XrMatrix4x4f;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum KeypadKey {
+    Digit(u8),
+    Sign,
+    Point,
+    Backspace,
+    Cancel,
+    Enter,
+}
+
+impl KeypadKey {
+    pub fn label(&self) -> String {
+        match self {
+            KeypadKey::Digit(digit) => digit.to_string(),
+            KeypadKey::Sign => "+/-".into(),
+            KeypadKey::Point => ".".into(),
+            KeypadKey::Backspace => "<-".into(),
+            KeypadKey::Cancel => i18n::tr("key-cancel"),
+            KeypadKey::Enter => i18n::tr("key-enter"),
+        }
+    }
+
+    /// the keys whose label is a symbol are drawn from an image of this name (see [add_key_images])
+    pub fn image_name(&self) -> Option<&'static str> {
+        match self {
+            KeypadKey::Sign => Some("key-sign"),
+            KeypadKey::Backspace => Some("key-backspace"),
+            _ => None,
+        }
+    }
+}
+
+/// how big the symbols of [add_key_images] are rendered, in pixels
+const KEY_IMAGE_PIXELS: f32 = 64.0;
+
+/// Add an image of each symbol key's label (see [KeypadKey::image_name]), rendered white so it is
+/// tinted like the other icons.  A PNG with the same name can replace the text with a proper picture.
+pub fn add_key_images(builder: &mut SpriteAtlasBuilder) {
+    for key in KEYS {
+        if let Some(name) = key.image_name() {
+            let spans = [TextSpan::new(key.label(), [1.0; 4])];
+            builder.add_text(
+                name,
+                fonts::registry(),
+                KEY_IMAGE_PIXELS,
+                &spans,
+                &TextStyle::default(),
+            );
+        }
+    }
+}
+
+const COLUMNS: usize = 3;
+
+/// the keys, left to right and top to bottom
+pub const KEYS: [KeypadKey; 15] = [
+    KeypadKey::Digit(7),
+    KeypadKey::Digit(8),
+    KeypadKey::Digit(9),
+    KeypadKey::Digit(4),
+    KeypadKey::Digit(5),
+    KeypadKey::Digit(6),
+    KeypadKey::Digit(1),
+    KeypadKey::Digit(2),
+    KeypadKey::Digit(3),
+    KeypadKey::Sign,
+    KeypadKey::Digit(0),
+    KeypadKey::Point,
+    KeypadKey::Cancel,
+    KeypadKey::Backspace,
+    KeypadKey::Enter,
+];
+
+/// the keypad pops up to the left of the control panel
+const KEYPAD_CENTER_X: f32 = -1.8;
+const KEY_HALF_SIZE: f32 = 0.14;
+const KEY_PITCH: f32 = 0.32;
+/// the entry display sits above the keys
+const DISPLAY_CENTER_Y: f32 = MENU_TOP + 0.15;
+const FIRST_KEY_CENTER_Y: f32 = DISPLAY_CENTER_Y + 0.45;
+/// no value we edit needs more characters than this, and seeds have no more digits
+const MAX_CHARS: usize = 9;
+
+/// where `KEYS[idx]` is drawn, in panel coordinates
+pub fn key_rect(idx: usize) -> PanelRect {
+    let column = (idx % COLUMNS) as f32 - (COLUMNS - 1) as f32 / 2.0;
+    let row = (idx / COLUMNS) as f32;
+    PanelRect {
+        center: [
+            KEYPAD_CENTER_X + column * KEY_PITCH,
+            FIRST_KEY_CENTER_Y + row * KEY_PITCH,
+        ],
+        half_size: [KEY_HALF_SIZE; 2],
+    }
+}
+
+/// the index into [KEYS] of the key under `point`
+pub fn key_at(point: [f32; 2]) -> Option<usize> {
+    (0..KEYS.len()).find(|idx| key_rect(*idx).contains(point))
+}
+
+/// what the number typed on the keypad is for
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum KeypadTarget {
+    /// a parameter of the layer `cursor` is on
+    Field(NumericField, CPCursor),
+    /// a seed to replay with [crate::surprise::surprise_me]
+    Seed,
+}
+
+pub enum KeypadOutcome {
+    /// keep the keypad open
+    Editing,
+    /// a value for a [KeypadTarget::Field]
+    Commit(f32),
+    /// a [KeypadTarget::Seed]
+    Seed(u64),
+    Cancel,
+}
+
+/// Typing a number for a [KeypadTarget], which is only committed once it passes validation.
+pub struct KeypadEntry {
+    pub target: KeypadTarget,
+    text: String,
+    /// why the last attempt to commit was refused
+    complaint: Option<String>,
+    /// the key the thumbstick is on, an index into [KEYS]
+    pub focus: usize,
+}
+
+impl KeypadEntry {
+    pub fn new(target: KeypadTarget) -> Self {
+        Self {
+            target,
+            text: String::new(),
+            complaint: None,
+            focus: KEYS.len() - 1,
+        }
+    }
+
+    pub fn press(&mut self, key: KeypadKey) -> KeypadOutcome {
+        self.complaint = None;
+        match key {
+            KeypadKey::Digit(digit) => {
+                if self.text.len() < MAX_CHARS {
+                    self.text.push(char::from(b'0' + digit));
+                }
+            }
+            KeypadKey::Sign => {
+                if let Some(rest) = self.text.strip_prefix('-') {
+                    self.text = rest.to_string();
+                } else {
+                    self.text.insert(0, '-');
+                }
+            }
+            KeypadKey::Point => {
+                if !self.text.contains('.') && self.text.len() < MAX_CHARS {
+                    self.text.push('.');
+                }
+            }
+            KeypadKey::Backspace => {
+                self.text.pop();
+            }
+            KeypadKey::Cancel => return KeypadOutcome::Cancel,
+            KeypadKey::Enter => {
+                let outcome = match self.target {
+                    KeypadTarget::Field(field, _) => self.parse(field).map(KeypadOutcome::Commit),
+                    KeypadTarget::Seed => self.parse_seed().map(KeypadOutcome::Seed),
+                };
+                match outcome {
+                    Ok(outcome) => return outcome,
+                    Err(complaint) => self.complaint = Some(complaint),
+                }
+            }
+        }
+        KeypadOutcome::Editing
+    }
+
+    /// the typed value, if it is acceptable for `field`
+    pub fn parse(&self, field: NumericField) -> Result<f32, String> {
+        let text = self.text.as_str();
+        if text.is_empty() || text == "-" {
+            return Err(i18n::tr("keypad-empty"));
+        }
+        let value: f32 = text
+            .parse()
+            .map_err(|_| i18n::tr_args("keypad-not-a-number", &[("text", &text)]))?;
+        let meta = field.param.meta();
+        if meta.integer && value.fract() != 0.0 {
+            return Err(i18n::tr("keypad-whole-numbers"));
+        }
+        if !meta.contains(value) {
+            return Err(i18n::tr_args(
+                "keypad-range",
+                &[("min", &meta.min), ("max", &meta.max)],
+            ));
+        }
+        Ok(value)
+    }
+
+    /// the typed seed, which must be below [SEED_LIMIT] (as [MAX_CHARS] digits always are)
+    pub fn parse_seed(&self) -> Result<u64, String> {
+        let text = self.text.as_str();
+        if text.is_empty() {
+            return Err(i18n::tr("keypad-empty"));
+        }
+        if text.contains(['.', '-']) {
+            return Err(i18n::tr("keypad-whole-numbers"));
+        }
+        let seed: u64 = text
+            .parse()
+            .map_err(|_| i18n::tr_args("keypad-not-a-number", &[("text", &text)]))?;
+        if seed >= SEED_LIMIT {
+            return Err(i18n::tr_args(
+                "keypad-range",
+                &[("min", &0), ("max", &(SEED_LIMIT - 1))],
+            ));
+        }
+        Ok(seed)
+    }
+
+    /// move the focus around the grid of keys, wrapping at the edges
+    pub fn move_focus(&mut self, dx: isize, dy: isize) {
+        let rows = KEYS.len() / COLUMNS;
+        let column = (self.focus % COLUMNS) as isize + dx;
+        let row = (self.focus / COLUMNS) as isize + dy;
+        self.focus = row.rem_euclid(rows as isize) as usize * COLUMNS
+            + column.rem_euclid(COLUMNS as isize) as usize;
+    }
+
+    /// what the display shows: the complaint about a rejected value, or what has been typed so far
+    pub fn display_text(&self) -> String {
+        match &self.complaint {
+            Some(complaint) => complaint.clone(),
+            None if self.text.is_empty() => "_".into(),
+            None => self.text.clone(),
+        }
+    }
+}
+
+//
+
+/// the keys, and the display above them showing what has been typed
+pub fn draw(
+    entry: &KeypadEntry,
+    matrix: &XrMatrix4x4f,
+    widgets: &Widgets,
+    gpu_state: &mut GPUState,
+) -> Result<(), GLErrorWrapper> {
+    // a backdrop from the display to the last row of keys
+    let top = DISPLAY_CENTER_Y - KEY_HALF_SIZE - 0.1;
+    let bottom = key_rect(KEYS.len() - 1).center[1] + KEY_HALF_SIZE + 0.1;
+    let backdrop = PanelRect {
+        center: [KEYPAD_CENTER_X, (top + bottom) / 2.0],
+        half_size: [KEY_PITCH * 1.5 + 0.1, (bottom - top) / 2.0],
+    };
+    widgets.panel(matrix, &backdrop, gpu_state)?;
+
+    for (idx, key) in KEYS.iter().enumerate() {
+        let rect = key_rect(idx);
+        match key.image_name() {
+            Some(name) => widgets.icon_button(matrix, &rect, name, gpu_state)?,
+            None => widgets.button(matrix, &rect, &key.label(), false, gpu_state)?,
+        }
+    }
+
+    let rect = PanelRect {
+        center: [KEYPAD_CENTER_X, DISPLAY_CENTER_Y],
+        half_size: [KEY_PITCH * 1.5, KEY_HALF_SIZE],
+    };
+    let highlight = entry.complaint.is_some();
+    widgets.sprite.draw_text(
+        widgets.atlas,
+        &entry.display_text(),
+        matrix,
+        &rect,
+        highlight,
+        gpu_state,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_panel::GorgonParam;
+
+    const FREQUENCY: NumericField = NumericField::stepped(GorgonParam::Frequency);
+    const SPEED: NumericField = NumericField::continuous(GorgonParam::Speed);
+
+    /// press the key for each of `keys`: digits, `-` for the sign, `.` and `<` for backspace
+    fn typed(keys: &str) -> KeypadEntry {
+        let mut entry = KeypadEntry::new(KeypadTarget::Seed);
+        for c in keys.chars() {
+            let key = match c {
+                '-' => KeypadKey::Sign,
+                '.' => KeypadKey::Point,
+                '<' => KeypadKey::Backspace,
+                c => KeypadKey::Digit(c.to_digit(10).unwrap() as u8),
+            };
+            entry.press(key);
+        }
+        entry
+    }
+
+    #[test]
+    fn values_in_range_are_accepted() {
+        assert_eq!(typed("12").parse(FREQUENCY), Ok(12.0));
+        assert_eq!(typed("2.5-").parse(SPEED), Ok(-2.5));
+        assert_eq!(typed("-1.").parse(SPEED), Ok(-1.0));
+        assert_eq!(typed("3.0").parse(FREQUENCY), Ok(3.0));
+    }
+
+    #[test]
+    fn values_out_of_range_are_refused() {
+        let range = i18n::tr_args("keypad-range", &[("min", &1.0), ("max", &255.0)]);
+        assert_eq!(typed("256").parse(FREQUENCY), Err(range));
+        assert!(typed("0").parse(FREQUENCY).is_err());
+        assert!(typed("20.1").parse(SPEED).is_err());
+        assert!(typed("-25").parse(SPEED).is_err());
+    }
+
+    #[test]
+    fn fractions_are_refused_for_integer_fields() {
+        let whole = Err(i18n::tr("keypad-whole-numbers"));
+        assert_eq!(typed("4.5").parse(FREQUENCY), whole);
+        assert_eq!(typed("4.5").parse(SPEED), Ok(4.5));
+    }
+
+    #[test]
+    fn nothing_to_parse_is_refused() {
+        let empty = i18n::tr("keypad-empty");
+        assert_eq!(typed("").parse(SPEED), Err(empty.clone()));
+        assert_eq!(typed("-").parse(SPEED), Err(empty.clone()));
+        assert_eq!(typed("4<").parse(SPEED), Err(empty.clone()));
+        assert!(typed(".").parse(SPEED).is_err());
+        assert!(typed("-.").parse(SPEED).is_err());
+        assert_eq!(typed("").parse_seed(), Err(empty.clone()));
+    }
+
+    #[test]
+    fn entries_stop_at_max_chars() {
+        let entry = typed("12345678901234");
+        assert_eq!(entry.display_text(), "123456789");
+        assert_eq!(typed("12345678.9").display_text(), "12345678.");
+        // the sign does not count
+        assert_eq!(typed("123456789-").display_text(), "-123456789");
+    }
+
+    #[test]
+    fn seeds_are_whole_and_below_the_limit() {
+        assert_eq!(typed("999999999").parse_seed(), Ok(999_999_999));
+        assert_eq!(typed("9999999999").parse_seed(), Ok(999_999_999));
+        assert!(typed("12.5").parse_seed().is_err());
+        assert!(typed("12-").parse_seed().is_err());
+        let mut entry = KeypadEntry::new(KeypadTarget::Seed);
+        entry.text = SEED_LIMIT.to_string();
+        assert!(entry.parse_seed().is_err());
+    }
+
+    #[test]
+    fn a_refused_value_is_explained_until_the_next_key() {
+        let mut entry = KeypadEntry::new(KeypadTarget::Field(FREQUENCY, Default::default()));
+        entry.press(KeypadKey::Digit(0));
+        assert!(matches!(
+            entry.press(KeypadKey::Enter),
+            KeypadOutcome::Editing
+        ));
+        assert_ne!(entry.display_text(), "0");
+        entry.press(KeypadKey::Digit(7));
+        assert_eq!(entry.display_text(), "07");
+        assert!(matches!(
+            entry.press(KeypadKey::Enter),
+            KeypadOutcome::Commit(value) if value == 7.0
+        ));
+    }
+}
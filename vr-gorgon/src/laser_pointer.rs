@@ -0,0 +1,148 @@
+use gl_thin::linear::{
+    xr_matrix4x4f_create_from_quaternion, xr_matrix4x4f_create_translation_v, XrMatrix4x4f,
+};
+use openxr_sys::Posef;
+
+/// A ray cast from a controller, used to point at the control panel.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    /// OpenXR aim poses point down their -Z axis
+    pub fn from_pose(pose: &Posef) -> Self {
+        let matrix = xr_matrix4x4f_create_translation_v(&pose.position.into())
+            * xr_matrix4x4f_create_from_quaternion(&pose.orientation.into());
+        Ray {
+            origin: [0.0; 3],
+            direction: [0.0, 0.0, -1.0],
+        }
+        .transformed(&matrix)
+    }
+
+    /// The direction is not renormalized, so distances along the ray scale with `matrix`.
+    pub fn transformed(&self, matrix: &XrMatrix4x4f) -> Self {
+        Ray {
+            origin: transform(matrix, &self.origin, 1.0),
+            direction: transform(matrix, &self.direction, 0.0),
+        }
+    }
+
+    /// where the ray crosses the z=0 plane, if it does so in front of its origin
+    pub fn hit_z0(&self) -> Option<[f32; 2]> {
+        let dz = self.direction[2];
+        if dz.abs() < 1e-6 {
+            return None;
+        }
+        let t = -self.origin[2] / dz;
+        if t < 0.0 {
+            return None;
+        }
+        Some([
+            self.origin[0] + t * self.direction[0],
+            self.origin[1] + t * self.direction[1],
+        ])
+    }
+}
+
+/// multiply the column-major `matrix` by `(v, w)`
+fn transform(matrix: &XrMatrix4x4f, v: &[f32; 3], w: f32) -> [f32; 3] {
+    let m = &matrix.m;
+    let mut rval = [0.0; 3];
+    for (row, out) in rval.iter_mut().enumerate() {
+        *out = m[row] * v[0] + m[4 + row] * v[1] + m[8 + row] * v[2] + m[12 + row] * w;
+    }
+    rval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_panel::{CPCursor, GorgonAxis, GorgonParam, GorgonShape};
+    use crate::menu::{Menu, HEADER_HALF_HEIGHT, MENU_TOP};
+
+    fn translation(x: f32, y: f32, z: f32) -> XrMatrix4x4f {
+        let mut m = [0.0; 16];
+        m[0] = 1.0;
+        m[5] = 1.0;
+        m[10] = 1.0;
+        m[15] = 1.0;
+        m[12] = x;
+        m[13] = y;
+        m[14] = z;
+        XrMatrix4x4f { m }
+    }
+
+    fn toward_panel(x: f32, y: f32) -> Ray {
+        Ray {
+            origin: [x, y, 2.0],
+            direction: [0.0, 0.0, -1.0],
+        }
+    }
+
+    #[test]
+    fn hits_the_plane_in_front() {
+        let ray = Ray {
+            origin: [0.0, 0.0, 2.0],
+            direction: [0.5, -0.25, -1.0],
+        };
+        assert_eq!(ray.hit_z0(), Some([1.0, -0.5]));
+    }
+
+    #[test]
+    fn a_parallel_ray_misses() {
+        let ray = Ray {
+            origin: [0.0, 0.0, 2.0],
+            direction: [1.0, 0.0, 0.0],
+        };
+        assert_eq!(ray.hit_z0(), None);
+    }
+
+    #[test]
+    fn a_ray_from_behind_the_panel_pointing_away_misses() {
+        let ray = Ray {
+            origin: [0.0, 0.0, -1.0],
+            direction: [0.0, 0.0, -1.0],
+        };
+        assert_eq!(ray.hit_z0(), None);
+        // and so does one in front, pointing away
+        let ray = Ray {
+            origin: [0.0, 0.0, 1.0],
+            direction: [0.0, 0.0, 1.0],
+        };
+        assert_eq!(ray.hit_z0(), None);
+    }
+
+    #[test]
+    fn transforming_moves_the_origin_but_not_the_direction() {
+        let ray = toward_panel(0.25, 0.5).transformed(&translation(1.0, 2.0, -1.0));
+        assert_eq!(ray.origin, [1.25, 2.5, 1.0]);
+        assert_eq!(ray.direction, [0.0, 0.0, -1.0]);
+        assert_eq!(ray.hit_z0(), Some([1.25, 2.5]));
+    }
+
+    #[test]
+    fn a_hit_on_the_edge_between_rows_selects_the_upper_row() {
+        let menu = Menu::gorgon_layers();
+        let cursor = CPCursor {
+            row: GorgonShape::Spiral,
+            axis: GorgonAxis::X,
+            subrow: GorgonParam::Enable,
+        };
+        let layout = menu.layout(&cursor, 0.6);
+        let edge = MENU_TOP + 2.0 * HEADER_HALF_HEIGHT;
+        // the middle of a toggle, well away from its neighbours
+        let hit = toward_panel(GorgonAxis::Z.x1(), edge).hit_z0().unwrap();
+        let selected = Menu::hit_test(&layout, hit, GorgonAxis::X);
+        assert_eq!(
+            selected,
+            Some(CPCursor {
+                row: GorgonShape::Spiral,
+                axis: GorgonAxis::Z,
+                subrow: GorgonParam::Enable,
+            })
+        );
+    }
+}
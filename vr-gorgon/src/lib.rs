@@ -12,15 +12,38 @@ use winit::platform::android::EventLoopBuilderExtAndroid;
 
 mod control_panel;
 mod drawcore;
+mod error_overlay;
+mod evolution;
+mod file_watch;
+mod fonts;
+mod glsl_dialect;
+mod glsl_include;
+mod glyph_atlas;
 mod gorgon1;
+mod history;
+mod i18n;
+mod keypad;
+mod laser_pointer;
+mod markup;
+mod menu;
+mod panel_anchor;
+mod param_meta;
 mod rainbow_triangle;
 mod scene;
+mod sdf;
+mod shader_export;
+mod shader_reload;
 mod shaders;
+mod sprite_atlas;
 mod sprites;
+mod surprise;
 mod suzanne;
 mod suzanne_geometry;
+mod text_layout;
 mod text_painting;
+mod theme;
 mod thumbstick_smoother;
+mod widgets;
 mod xr_input;
 
 pub trait Drawable {
@@ -130,6 +153,11 @@ fn android_main(android_app: AndroidApp) {
         log::error!("android_main() called more than once. calling Activity.finish() to avoid EventLoop panic!");
         activity_finish(&android_app).unwrap();
     }
+    // exported files land somewhere `adb pull` can reach them
+    let data_dir = android_app
+        .external_data_path()
+        .or_else(|| android_app.internal_data_path());
+
     let mut builder: //winit::event_loop::
         EventLoopBuilder<_> = EventLoopBuilder::new();
     let event_loop: EventLoop<()> = builder.with_android_app(android_app).build();
@@ -141,7 +169,7 @@ fn android_main(android_app: AndroidApp) {
         event_loop_one_pass(evt, e_loop, ctx, &mut app, |event_loop| {
             initialize_gl_using_egli();
 
-            ActiveRenderer::new(event_loop)
+            ActiveRenderer::new(event_loop, data_dir.clone())
         })
     });
 }
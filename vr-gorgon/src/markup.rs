@@ -0,0 +1,160 @@
+use crate::text_layout::ICON_CHAR;
+use crate::text_painting::{Icon, TextSpan};
+
+/// Parse a label written in a tiny BBCode-like markup into [TextSpan]s:
+///
+/// * `[color=red]...[/color]`, with a color name or `#rrggbb`
+/// * `[b]...[/b]` for bold
+/// * `[size=1.5]...[/size]`, relative to the size the text is rendered at
+/// * `[icon=dot]`, `[icon=ring]` or `[icon=swatch]`, drawn in the current color
+/// * `[[` for a literal `[`
+///
+/// Tags nest.  Anything which is not a well-formed tag (including a closing tag with nothing open)
+/// is shown as it was written, so a typo is visible on the panel instead of silently vanishing.
+pub fn parse(markup: &str, color: [f32; 4]) -> Vec<TextSpan> {
+    let mut parser = Parser {
+        spans: vec![],
+        colors: vec![color],
+        sizes: vec![1.0],
+        bold: 0,
+    };
+    let mut rest = markup;
+    while let Some(idx) = rest.find('[') {
+        parser.push_text(&rest[..idx]);
+        rest = &rest[idx..];
+        if let Some(after) = rest.strip_prefix("[[") {
+            parser.push_text("[");
+            rest = after;
+            continue;
+        }
+        let tag_len = match rest.find(']') {
+            Some(end) if parser.apply(&rest[1..end]) => end + 1,
+            _ => {
+                parser.push_text("[");
+                1
+            }
+        };
+        rest = &rest[tag_len..];
+    }
+    parser.push_text(rest);
+    parser.spans
+}
+
+/// `text` with every `[` doubled, so [parse] shows it as written.
+/// Anything spliced into markup that did not come from the program itself (like a translation) should go through this.
+pub fn escape(text: &str) -> String {
+    text.replace('[', "[[")
+}
+
+struct Parser {
+    spans: Vec<TextSpan>,
+    /// the innermost is the current style; the first entry is never popped
+    colors: Vec<[f32; 4]>,
+    sizes: Vec<f32>,
+    bold: usize,
+}
+
+impl Parser {
+    fn current(&self, text: &str) -> TextSpan {
+        TextSpan {
+            bold: self.bold > 0,
+            size: *self.sizes.last().unwrap(),
+            ..TextSpan::new(text, *self.colors.last().unwrap())
+        }
+    }
+
+    /// append to the last span if it has the same style
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let span = self.current(text);
+        match self.spans.last_mut() {
+            Some(last)
+                if last.icon.is_none()
+                    && (last.color, last.bold, last.size) == (span.color, span.bold, span.size) =>
+            {
+                last.text.push_str(text)
+            }
+            _ => self.spans.push(span),
+        }
+    }
+
+    /// act on the contents of a `[tag]`, returning false if it is not one we understand
+    fn apply(&mut self, tag: &str) -> bool {
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (tag, None),
+        };
+        match (name, value) {
+            ("color", Some(value)) => match parse_color(value) {
+                Some(color) => self.colors.push(color),
+                None => return false,
+            },
+            ("/color", None) if self.colors.len() > 1 => {
+                self.colors.pop();
+            }
+            ("b", None) => self.bold += 1,
+            ("/b", None) if self.bold > 0 => self.bold -= 1,
+            ("size", Some(value)) => match value.parse::<f32>() {
+                Ok(size) if size > 0.0 => self.sizes.push(size),
+                _ => return false,
+            },
+            ("/size", None) if self.sizes.len() > 1 => {
+                self.sizes.pop();
+            }
+            ("icon", Some(value)) => match Icon::from_name(value) {
+                Some(icon) => {
+                    let mut span = self.current(&ICON_CHAR.to_string());
+                    span.icon = Some(icon);
+                    self.spans.push(span);
+                }
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// a few color names, or `#rrggbb`
+fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let named = match value {
+        "black" => Some([0.0, 0.0, 0.0, 1.0]),
+        "white" => Some([1.0, 1.0, 1.0, 1.0]),
+        "grey" | "gray" => Some([0.5, 0.5, 0.5, 1.0]),
+        "red" => Some([0.85, 0.1, 0.1, 1.0]),
+        "green" => Some([0.1, 0.6, 0.2, 1.0]),
+        "blue" => Some([0.1, 0.3, 0.8, 1.0]),
+        "yellow" => Some([0.9, 0.8, 0.1, 1.0]),
+        "orange" => Some([0.95, 0.5, 0.1, 1.0]),
+        _ => None,
+    };
+    if named.is_some() {
+        return named;
+    }
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2)?, 16)
+            .ok()
+            .map(|c| c as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?, 1.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_text_is_shown_as_written() {
+        let text = "[b]not bold[/b] [[x]";
+        let spans = parse(&format!("[b]{}[/b]", escape(text)), [1.0; 4]);
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].text, text);
+        assert!(spans[0].bold);
+    }
+}
@@ -0,0 +1,545 @@
+use crate::control_panel::{CPCursor, GorgonAxis, GorgonParam, GorgonShape};
+use crate::gorgon1::{GorgonSettings, MultiGorgonSettings};
+use crate::i18n;
+
+/// The y coordinate (in panel units) where the first row starts
+pub const MENU_TOP: f32 = -1.0;
+/// half the height of a section header (the layer icon and its X/Y/Z toggles)
+pub const HEADER_HALF_HEIGHT: f32 = 0.25;
+/// The box a numeric field's label is fitted into, at its narrowest.  The labels sit against the left edge of the panel.
+/// The height is also the height of the field's row.
+pub const FIELD_LABEL_HALF_SIZE: [f32; 2] = [0.5, 0.2];
+/// the widest the labels can be and still leave room for the value; longer ones are shrunk to fit
+pub const FIELD_LABEL_MAX_HALF_WIDTH: f32 = 0.65;
+/// the panel spans -1..1 horizontally
+pub const PANEL_HALF_WIDTH: f32 = 1.0;
+/// where the panel's backdrop starts, above the status line and the seed over the first row
+pub const BACKDROP_TOP: f32 = MENU_TOP - 0.45;
+/// how far the backdrop reaches past the panel's contents on the sides and at the bottom
+pub const BACKDROP_MARGIN: f32 = 0.08;
+/// half the height of the row of [PanelButton]s below the menu
+pub const BUTTON_HALF_HEIGHT: f32 = 0.12;
+/// the space between the menu and the buttons, and between neighbouring buttons
+const BUTTON_GAP: f32 = 0.1;
+
+/// The control panel's menu, declared as data.
+/// Layout, cursor navigation and rendering are all derived from this tree,
+/// and none of it needs a GL context.
+pub struct Menu {
+    pub sections: Vec<MenuSection>,
+}
+
+/// One kind of gorgon layer.  The header row shows the layer icon and the X/Y/Z toggles;
+/// the rest of the items are only shown while the cursor is inside the section.
+pub struct MenuSection {
+    pub shape: GorgonShape,
+    pub items: Vec<MenuItem>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MenuItem {
+    /// one toggle per axis, bound to [GorgonSettings::enabled].  Drawn in the section header.
+    Toggle,
+    Numeric(NumericField),
+}
+
+impl MenuItem {
+    /// the [CPCursor::subrow] which selects this item
+    pub fn param(&self) -> GorgonParam {
+        match self {
+            MenuItem::Toggle => GorgonParam::Enable,
+            MenuItem::Numeric(field) => field.param,
+        }
+    }
+}
+
+/// a number bound to one parameter of the layer the cursor is on
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct NumericField {
+    pub param: GorgonParam,
+    /// integer fields move one step per flick of the thumbstick instead of continuously
+    pub stepped: bool,
+}
+
+impl NumericField {
+    pub const fn continuous(param: GorgonParam) -> Self {
+        Self {
+            param,
+            stepped: false,
+        }
+    }
+
+    pub const fn stepped(param: GorgonParam) -> Self {
+        Self {
+            param,
+            stepped: true,
+        }
+    }
+
+    pub fn value(&self, settings: &GorgonSettings) -> f32 {
+        settings.get(self.param)
+    }
+
+    pub fn set(&self, settings: &mut MultiGorgonSettings, cursor: CPCursor, value: f32) {
+        settings.set_param(self.param, value, cursor)
+    }
+
+    /// `delta` is in the parameter's own units; see [crate::param_meta::ParamMeta::step]
+    pub fn adjust(&self, settings: &mut MultiGorgonSettings, cursor: CPCursor, delta: f32) {
+        settings.adjust_param(self.param, delta, cursor)
+    }
+}
+
+/// where a row of the menu lands on the panel
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RowLayout {
+    pub shape: GorgonShape,
+    pub item: MenuItem,
+    pub center_y: f32,
+    /// of a toggle, or of a field's label
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl RowLayout {
+    /// the widget a cursor on this row highlights.  `axis` picks the toggle in a header row.
+    pub fn widget_rect(&self, axis: GorgonAxis) -> PanelRect {
+        let center_x = match self.item {
+            MenuItem::Toggle => axis.x1(),
+            MenuItem::Numeric(_) => -PANEL_HALF_WIDTH + self.half_width,
+        };
+        PanelRect {
+            center: [center_x, self.center_y],
+            half_size: [self.half_width, self.half_height],
+        }
+    }
+
+    /// the cursor which selects the widget of this row under `point`, if any.
+    /// The whole width of a field row counts, so pointing at the value works too.
+    /// The layer of a field is chosen in the header, so fields keep `current_axis`.
+    pub fn hit_test(&self, point: [f32; 2], current_axis: GorgonAxis) -> Option<CPCursor> {
+        let cursor = |axis| CPCursor {
+            row: self.shape,
+            axis,
+            subrow: self.item.param(),
+        };
+        match self.item {
+            MenuItem::Toggle => GorgonAxis::ALL
+                .into_iter()
+                .find(|axis| self.widget_rect(*axis).contains(point))
+                .map(cursor),
+            MenuItem::Numeric(_) => {
+                let band = PanelRect {
+                    center: [0.0, self.center_y],
+                    half_size: [PANEL_HALF_WIDTH, self.half_height],
+                };
+                band.contains(point).then_some(cursor(current_axis))
+            }
+        }
+    }
+}
+
+/// Actions which do not belong to any layer, in a row of buttons below the menu.
+/// They only answer the laser pointer, so they can not be hit by accident with the thumbstick.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PanelButton {
+    /// [crate::surprise::surprise_me] with a fresh seed
+    Surprise,
+    /// type a seed on the keypad to replay its surprise
+    Seed,
+    /// switch to the next language (see [crate::i18n::next_locale])
+    Locale,
+}
+
+impl PanelButton {
+    pub const ALL: [PanelButton; 3] = [
+        PanelButton::Surprise,
+        PanelButton::Seed,
+        PanelButton::Locale,
+    ];
+
+    /// the button's label, in the current locale
+    pub fn label(&self) -> String {
+        match self {
+            PanelButton::Surprise => i18n::tr("button-surprise"),
+            PanelButton::Seed => i18n::tr("button-seed"),
+            PanelButton::Locale => {
+                i18n::tr_args("button-locale", &[("locale", &i18n::current_locale())])
+            }
+        }
+    }
+
+    /// where each button is drawn, side by side below the last row of `layout`
+    pub fn layout(layout: &[RowLayout]) -> impl Iterator<Item = (PanelButton, PanelRect)> {
+        let center_y = Menu::bottom(layout) + BUTTON_GAP + BUTTON_HALF_HEIGHT;
+        let pitch = 2.0 * PANEL_HALF_WIDTH / Self::ALL.len() as f32;
+        Self::ALL.into_iter().enumerate().map(move |(idx, button)| {
+            let rect = PanelRect {
+                center: [-PANEL_HALF_WIDTH + pitch * (idx as f32 + 0.5), center_y],
+                half_size: [(pitch - BUTTON_GAP) / 2.0, BUTTON_HALF_HEIGHT],
+            };
+            (button, rect)
+        })
+    }
+
+    /// the button under `point`, if any
+    pub fn hit_test(layout: &[RowLayout], point: [f32; 2]) -> Option<PanelButton> {
+        Self::layout(layout)
+            .find(|(_, rect)| rect.contains(point))
+            .map(|(button, _)| button)
+    }
+}
+
+/// an axis-aligned rectangle in panel coordinates
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PanelRect {
+    pub center: [f32; 2],
+    pub half_size: [f32; 2],
+}
+
+impl PanelRect {
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        (point[0] - self.center[0]).abs() <= self.half_size[0]
+            && (point[1] - self.center[1]).abs() <= self.half_size[1]
+    }
+}
+
+impl Menu {
+    /// the spiral, latitude and cartesian layers, in that order.
+    /// Each section has a field for every parameter its layer's shader uses;
+    /// spirals scroll rather than sway, so they have no amplitude.
+    pub fn gorgon_layers() -> Self {
+        let section = |shape| {
+            let mut items = vec![
+                MenuItem::Toggle,
+                MenuItem::Numeric(NumericField::stepped(GorgonParam::Frequency)),
+                MenuItem::Numeric(NumericField::continuous(GorgonParam::Speed)),
+            ];
+            if shape != GorgonShape::Spiral {
+                items.push(MenuItem::Numeric(NumericField::continuous(
+                    GorgonParam::Amplitude,
+                )));
+            }
+            items.push(MenuItem::Numeric(NumericField::continuous(
+                GorgonParam::Curl,
+            )));
+            MenuSection { shape, items }
+        };
+
+        Self {
+            sections: vec![
+                section(GorgonShape::Spiral),
+                section(GorgonShape::Latitude),
+                section(GorgonShape::Cartesian),
+            ],
+        }
+    }
+
+    /// every row of every section, in navigation order
+    fn rows(&self) -> impl Iterator<Item = (GorgonShape, MenuItem)> + '_ {
+        self.sections
+            .iter()
+            .flat_map(|section| section.items.iter().map(|item| (section.shape, *item)))
+    }
+
+    /// the item under the cursor, if the cursor is on an item of this menu
+    pub fn item_at(&self, cursor: &CPCursor) -> Option<MenuItem> {
+        self.rows()
+            .find(|(shape, item)| *shape == cursor.row && item.param() == cursor.subrow)
+            .map(|(_, item)| item)
+    }
+
+    /// move the cursor down a row, wrapping from the bottom of the menu to the top
+    pub fn next_row(&self, cursor: &mut CPCursor) {
+        self.step_row(cursor, 1)
+    }
+
+    /// move the cursor up a row, wrapping from the top of the menu to the bottom
+    pub fn prev_row(&self, cursor: &mut CPCursor) {
+        self.step_row(cursor, -1)
+    }
+
+    fn step_row(&self, cursor: &mut CPCursor, step: isize) {
+        let rows: Vec<_> = self.rows().collect();
+        if rows.is_empty() {
+            return;
+        }
+        let current = rows
+            .iter()
+            .position(|(shape, item)| *shape == cursor.row && item.param() == cursor.subrow);
+        let next = match current {
+            Some(idx) => (idx as isize + step).rem_euclid(rows.len() as isize) as usize,
+            None => 0,
+        };
+        let (shape, item) = rows[next];
+        cursor.row = shape;
+        cursor.subrow = item.param();
+    }
+
+    /// The visible rows from the top of the panel down:
+    /// every section header, plus the fields of the section the cursor is in.
+    /// `label_half_width` is for the fields' labels, which are as wide as the widest one needs.
+    pub fn layout(&self, cursor: &CPCursor, label_half_width: f32) -> Vec<RowLayout> {
+        let mut rval = vec![];
+        let mut y = MENU_TOP;
+        for section in &self.sections {
+            let expanded = section.shape == cursor.row;
+            for item in &section.items {
+                let [half_width, half_height] = match item {
+                    MenuItem::Toggle => [HEADER_HALF_HEIGHT; 2],
+                    MenuItem::Numeric(_) if expanded => {
+                        [label_half_width, FIELD_LABEL_HALF_SIZE[1]]
+                    }
+                    MenuItem::Numeric(_) => continue,
+                };
+                rval.push(RowLayout {
+                    shape: section.shape,
+                    item: *item,
+                    center_y: y + half_height,
+                    half_width,
+                    half_height,
+                });
+                y += 2.0 * half_height;
+            }
+        }
+        rval
+    }
+
+    /// the bottom edge of the last row of `layout`
+    pub fn bottom(layout: &[RowLayout]) -> f32 {
+        layout
+            .last()
+            .map_or(MENU_TOP, |row| row.center_y + row.half_height)
+    }
+
+    /// every numeric field, in navigation order
+    pub fn fields(&self) -> impl Iterator<Item = NumericField> + '_ {
+        self.rows().filter_map(|(_, item)| match item {
+            MenuItem::Numeric(field) => Some(field),
+            MenuItem::Toggle => None,
+        })
+    }
+
+    /// the widget under `point` (in panel coordinates), as the cursor which would select it
+    pub fn hit_test(
+        layout: &[RowLayout],
+        point: [f32; 2],
+        current_axis: GorgonAxis,
+    ) -> Option<CPCursor> {
+        layout
+            .iter()
+            .find_map(|row| row.hit_test(point, current_axis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(row: GorgonShape, subrow: GorgonParam) -> CPCursor {
+        CPCursor {
+            row,
+            axis: GorgonAxis::X,
+            subrow,
+        }
+    }
+
+    #[test]
+    fn next_row_walks_the_subrows_then_the_next_section() {
+        let menu = Menu::gorgon_layers();
+        let mut c = cursor(GorgonShape::Spiral, GorgonParam::Enable);
+        let mut visited = vec![];
+        for _ in 0..5 {
+            menu.next_row(&mut c);
+            visited.push((c.row, c.subrow));
+        }
+        assert_eq!(
+            visited,
+            vec![
+                (GorgonShape::Spiral, GorgonParam::Frequency),
+                (GorgonShape::Spiral, GorgonParam::Speed),
+                (GorgonShape::Spiral, GorgonParam::Curl),
+                (GorgonShape::Latitude, GorgonParam::Enable),
+                (GorgonShape::Latitude, GorgonParam::Frequency),
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_wrap_around_both_ends() {
+        let menu = Menu::gorgon_layers();
+        let mut c = cursor(GorgonShape::Cartesian, GorgonParam::Curl);
+        menu.next_row(&mut c);
+        assert_eq!(c, cursor(GorgonShape::Spiral, GorgonParam::Enable));
+        menu.prev_row(&mut c);
+        assert_eq!(c, cursor(GorgonShape::Cartesian, GorgonParam::Curl));
+    }
+
+    #[test]
+    fn prev_row_undoes_next_row() {
+        let menu = Menu::gorgon_layers();
+        let start = cursor(GorgonShape::Latitude, GorgonParam::Speed);
+        let mut c = start;
+        for _ in 0..7 {
+            menu.next_row(&mut c);
+        }
+        for _ in 0..7 {
+            menu.prev_row(&mut c);
+        }
+        assert_eq!(c, start);
+    }
+
+    #[test]
+    fn row_changes_keep_the_axis() {
+        let menu = Menu::gorgon_layers();
+        let mut c = cursor(GorgonShape::Spiral, GorgonParam::Curl);
+        c.axis = GorgonAxis::Z;
+        menu.next_row(&mut c);
+        assert_eq!(c.row, GorgonShape::Latitude);
+        assert_eq!(c.axis, GorgonAxis::Z);
+    }
+
+    #[test]
+    fn axis_wraps_both_ways() {
+        let mut c = CPCursor::default();
+        let mut visited = vec![];
+        for _ in 0..3 {
+            c.incr_x();
+            visited.push(c.axis);
+        }
+        assert_eq!(visited, vec![GorgonAxis::Y, GorgonAxis::Z, GorgonAxis::X]);
+        c.decr_x();
+        assert_eq!(c.axis, GorgonAxis::Z);
+        c.decr_x();
+        c.decr_x();
+        assert_eq!(c.axis, GorgonAxis::X);
+    }
+
+    #[test]
+    fn a_cursor_off_the_menu_goes_to_the_first_row() {
+        let menu = Menu {
+            sections: vec![MenuSection {
+                shape: GorgonShape::Latitude,
+                items: vec![MenuItem::Toggle],
+            }],
+        };
+        let mut c = cursor(GorgonShape::Spiral, GorgonParam::Curl);
+        menu.next_row(&mut c);
+        assert_eq!(c, cursor(GorgonShape::Latitude, GorgonParam::Enable));
+    }
+
+    #[test]
+    fn layout_expands_only_the_current_section() {
+        let menu = Menu::gorgon_layers();
+        let layout = menu.layout(&cursor(GorgonShape::Latitude, GorgonParam::Speed), 0.6);
+        let rows: Vec<_> = layout
+            .iter()
+            .map(|row| (row.shape, row.item.param()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (GorgonShape::Spiral, GorgonParam::Enable),
+                (GorgonShape::Latitude, GorgonParam::Enable),
+                (GorgonShape::Latitude, GorgonParam::Frequency),
+                (GorgonShape::Latitude, GorgonParam::Speed),
+                (GorgonShape::Latitude, GorgonParam::Amplitude),
+                (GorgonShape::Latitude, GorgonParam::Curl),
+                (GorgonShape::Cartesian, GorgonParam::Enable),
+            ]
+        );
+    }
+
+    #[test]
+    fn spirals_have_no_amplitude() {
+        let menu = Menu::gorgon_layers();
+        let spiral = cursor(GorgonShape::Spiral, GorgonParam::Amplitude);
+        assert_eq!(menu.item_at(&spiral), None);
+        let latitude = cursor(GorgonShape::Latitude, GorgonParam::Amplitude);
+        assert!(menu.item_at(&latitude).is_some());
+    }
+
+    #[test]
+    fn hit_test_picks_the_toggle_under_the_point() {
+        let menu = Menu::gorgon_layers();
+        let layout = menu.layout(&cursor(GorgonShape::Spiral, GorgonParam::Enable), 0.6);
+        let header_y = MENU_TOP + HEADER_HALF_HEIGHT;
+        for axis in GorgonAxis::ALL {
+            let hit = Menu::hit_test(&layout, [axis.x1(), header_y], GorgonAxis::X);
+            assert_eq!(
+                hit,
+                Some(CPCursor {
+                    row: GorgonShape::Spiral,
+                    axis,
+                    subrow: GorgonParam::Enable,
+                })
+            );
+        }
+        // left of the toggles is the layer icon, which is not a widget
+        assert_eq!(
+            Menu::hit_test(&layout, [-0.9, header_y], GorgonAxis::X),
+            None
+        );
+    }
+
+    #[test]
+    fn hit_test_on_a_field_keeps_the_axis() {
+        let menu = Menu::gorgon_layers();
+        let layout = menu.layout(&cursor(GorgonShape::Latitude, GorgonParam::Speed), 0.6);
+        let speed = layout
+            .iter()
+            .find(|row| {
+                row.shape == GorgonShape::Latitude && row.item.param() == GorgonParam::Speed
+            })
+            .unwrap();
+        // the value, to the right of the label, counts too
+        for x in [-0.9, 0.9] {
+            let hit = Menu::hit_test(&layout, [x, speed.center_y], GorgonAxis::Z);
+            assert_eq!(
+                hit,
+                Some(CPCursor {
+                    row: GorgonShape::Latitude,
+                    axis: GorgonAxis::Z,
+                    subrow: GorgonParam::Speed,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn hit_test_misses_outside_the_rows() {
+        let menu = Menu::gorgon_layers();
+        let layout = menu.layout(&cursor(GorgonShape::Spiral, GorgonParam::Enable), 0.6);
+        let bottom = layout.last().unwrap();
+        let below = bottom.center_y + bottom.half_height + 0.01;
+        assert_eq!(Menu::hit_test(&layout, [0.0, below], GorgonAxis::X), None);
+        assert_eq!(
+            Menu::hit_test(&layout, [0.0, MENU_TOP - 0.01], GorgonAxis::X),
+            None
+        );
+        let field_y = layout[1].center_y;
+        assert_eq!(
+            Menu::hit_test(&layout, [PANEL_HALF_WIDTH + 0.01, field_y], GorgonAxis::X),
+            None
+        );
+    }
+
+    #[test]
+    fn layout_stacks_rows_from_the_top() {
+        let menu = Menu::gorgon_layers();
+        let layout = menu.layout(&cursor(GorgonShape::Spiral, GorgonParam::Enable), 0.6);
+        assert_eq!(layout[0].center_y, MENU_TOP + HEADER_HALF_HEIGHT);
+        for pair in layout.windows(2) {
+            let bottom = pair[0].center_y + pair[0].half_height;
+            let top = pair[1].center_y - pair[1].half_height;
+            assert!((bottom - top).abs() < 1e-6, "{:?}", pair);
+        }
+        let field = &layout[1];
+        assert_eq!(field.half_width, 0.6);
+        assert_eq!(field.half_height, FIELD_LABEL_HALF_SIZE[1]);
+        assert_eq!(
+            field.widget_rect(GorgonAxis::Y).center[0],
+            -PANEL_HALF_WIDTH + 0.6
+        );
+    }
+}
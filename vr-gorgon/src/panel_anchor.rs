@@ -0,0 +1,427 @@
+use gl_thin::linear::{
+    xr_matrix4x4f_create_from_quaternion, xr_matrix4x4f_create_translation_v, XrMatrix4x4f,
+};
+use openxr_sys::{Posef, Quaternionf, Vector3f};
+use std::fmt::Write;
+use std::path::Path;
+
+/// How the control panel is positioned in the world.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum AnchorMode {
+    /// hangs off the right controller
+    #[default]
+    Controller,
+    /// pinned in world space where it was when this mode was chosen
+    World,
+    /// drifts after the head so it stays in front of you
+    Head,
+    /// pinned in world space, but can be picked up with the left grip and moved
+    Grabbable,
+}
+
+impl AnchorMode {
+    pub const ALL: [AnchorMode; 4] = [
+        AnchorMode::Controller,
+        AnchorMode::World,
+        AnchorMode::Head,
+        AnchorMode::Grabbable,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnchorMode::Controller => "controller",
+            AnchorMode::World => "world",
+            AnchorMode::Head => "head",
+            AnchorMode::Grabbable => "grabbable",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    pub fn next(&self) -> Self {
+        let idx = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// where the panel sits relative to the controller in [AnchorMode::Controller]:
+/// 20cm ahead, lying flat on top of it
+pub const CONTROLLER_OFFSET: Posef = Posef {
+    orientation: Quaternionf {
+        x: std::f32::consts::FRAC_1_SQRT_2,
+        y: 0.0,
+        z: 0.0,
+        w: std::f32::consts::FRAC_1_SQRT_2,
+    },
+    position: Vector3f {
+        x: 0.0,
+        y: 0.0,
+        z: -0.2,
+    },
+};
+
+/// where [AnchorMode::Head] wants the panel: a little below eye level, facing you
+pub const HEAD_OFFSET: Posef = Posef {
+    orientation: Quaternionf {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+        w: 0.0,
+    },
+    position: Vector3f {
+        x: 0.0,
+        y: -0.1,
+        z: -0.45,
+    },
+};
+
+/// how much of the remaining distance to the head-locked pose is covered each frame
+const HEAD_FOLLOW_RATE: f32 = 0.03;
+
+/// each step of [PanelAnchor::resize] makes the panel this much bigger (or smaller)
+const SCALE_STEP: f32 = 1.25;
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 3.0;
+
+/// The state behind [AnchorMode]s which pin the panel in the world
+pub struct PanelAnchor {
+    pub mode: AnchorMode,
+    /// where the panel is when it is not attached to the controller
+    pose: Posef,
+    /// the panel's pose relative to the hand which is holding it
+    grab: Option<Posef>,
+    /// how much bigger than usual the panel is drawn, for anyone who wants larger text
+    pub scale: f32,
+    /// the mode or pose changed in a way worth saving
+    changed: bool,
+}
+
+impl Default for PanelAnchor {
+    fn default() -> Self {
+        Self {
+            mode: AnchorMode::default(),
+            pose: Posef::IDENTITY,
+            grab: None,
+            scale: 1.0,
+            changed: false,
+        }
+    }
+}
+
+impl PanelAnchor {
+    /// The panel's pose this frame, if it can be shown.
+    /// In [AnchorMode::Controller] that depends on the controller being tracked.
+    pub fn panel_pose(&self, controller_1: Option<&Posef>) -> Option<Posef> {
+        match self.mode {
+            AnchorMode::Controller => {
+                controller_1.map(|controller| pose_compose(controller, &CONTROLLER_OFFSET))
+            }
+            _ => Some(self.pose),
+        }
+    }
+
+    /// Switch to the next mode.  Leaving [AnchorMode::Controller] pins the panel where it is.
+    pub fn cycle_mode(&mut self, controller_1: Option<&Posef>) {
+        if let Some(pose) = self.panel_pose(controller_1) {
+            self.pose = pose;
+        }
+        self.mode = self.mode.next();
+        self.grab = None;
+        self.changed = true;
+        log::info!("control panel anchored to {}", self.mode.name());
+    }
+
+    /// grow the panel by `steps` [SCALE_STEP]s, or shrink it if `steps` is negative
+    pub fn resize(&mut self, steps: i32) {
+        self.scale = (self.scale * SCALE_STEP.powi(steps)).clamp(MIN_SCALE, MAX_SCALE);
+        self.changed = true;
+        log::info!("control panel scale {}", self.scale);
+    }
+
+    /// call once a frame, after painting.
+    /// `grip` is the left hand's grip pose if the grip button is squeezed.
+    pub fn update(&mut self, head: Option<&Posef>, grip: Option<&Posef>) {
+        match self.mode {
+            AnchorMode::Controller | AnchorMode::World => {}
+            AnchorMode::Head => {
+                if let Some(head) = head {
+                    let target = pose_compose(head, &HEAD_OFFSET);
+                    self.pose = pose_lerp(&self.pose, &target, HEAD_FOLLOW_RATE);
+                }
+            }
+            AnchorMode::Grabbable => match (grip, self.grab) {
+                (Some(hand), None) => {
+                    self.grab = Some(pose_compose(&pose_inverse(hand), &self.pose));
+                }
+                (Some(hand), Some(grab)) => self.pose = pose_compose(hand, &grab),
+                (None, Some(_)) => {
+                    self.grab = None;
+                    self.changed = true;
+                }
+                (None, None) => {}
+            },
+        }
+    }
+
+    /// true once after each change that should be saved
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// `key=value` lines, as written by [Self::save]
+    pub fn to_text(&self) -> String {
+        let mut rval = String::new();
+        let _ = writeln!(&mut rval, "mode={}", self.mode.name());
+        let p = &self.pose.position;
+        let _ = writeln!(&mut rval, "position={} {} {}", p.x, p.y, p.z);
+        let q = &self.pose.orientation;
+        let _ = writeln!(&mut rval, "orientation={} {} {} {}", q.x, q.y, q.z, q.w);
+        let _ = writeln!(&mut rval, "scale={}", self.scale);
+        rval
+    }
+
+    /// the inverse of [Self::to_text].  Unrecognized or malformed lines are ignored.
+    pub fn from_text(text: &str) -> Self {
+        let mut rval = Self::default();
+        for line in text.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let numbers: Vec<f32> = value
+                .split_whitespace()
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            match (key.trim(), numbers.as_slice()) {
+                ("mode", _) => {
+                    if let Some(mode) = AnchorMode::from_name(value.trim()) {
+                        rval.mode = mode;
+                    }
+                }
+                ("position", &[x, y, z]) => rval.pose.position = Vector3f { x, y, z },
+                ("orientation", &[x, y, z, w]) => {
+                    rval.pose.orientation = quat_normalize(Quaternionf { x, y, z, w })
+                }
+                ("scale", &[scale]) => rval.scale = scale.clamp(MIN_SCALE, MAX_SCALE),
+                _ => {}
+            }
+        }
+        rval
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::from_text(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+}
+
+//
+
+pub fn pose_matrix(pose: &Posef) -> XrMatrix4x4f {
+    xr_matrix4x4f_create_translation_v(&pose.position.into())
+        * xr_matrix4x4f_create_from_quaternion(&pose.orientation.into())
+}
+
+/// `b` expressed in the frame of `a`, like multiplying their matrices
+pub fn pose_compose(a: &Posef, b: &Posef) -> Posef {
+    let offset = quat_rotate(&a.orientation, &b.position);
+    Posef {
+        orientation: quat_mul(&a.orientation, &b.orientation),
+        position: Vector3f {
+            x: a.position.x + offset.x,
+            y: a.position.y + offset.y,
+            z: a.position.z + offset.z,
+        },
+    }
+}
+
+pub fn pose_inverse(pose: &Posef) -> Posef {
+    let orientation = quat_conjugate(&pose.orientation);
+    let p = quat_rotate(&orientation, &pose.position);
+    Posef {
+        orientation,
+        position: Vector3f {
+            x: -p.x,
+            y: -p.y,
+            z: -p.z,
+        },
+    }
+}
+
+/// move `t` of the way from `a` to `b` (nlerp for the orientation)
+pub fn pose_lerp(a: &Posef, b: &Posef, t: f32) -> Posef {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let (qa, mut qb) = (a.orientation, b.orientation);
+    // take the short way around
+    if qa.x * qb.x + qa.y * qb.y + qa.z * qb.z + qa.w * qb.w < 0.0 {
+        qb = Quaternionf {
+            x: -qb.x,
+            y: -qb.y,
+            z: -qb.z,
+            w: -qb.w,
+        };
+    }
+    Posef {
+        orientation: quat_normalize(Quaternionf {
+            x: lerp(qa.x, qb.x),
+            y: lerp(qa.y, qb.y),
+            z: lerp(qa.z, qb.z),
+            w: lerp(qa.w, qb.w),
+        }),
+        position: Vector3f {
+            x: lerp(a.position.x, b.position.x),
+            y: lerp(a.position.y, b.position.y),
+            z: lerp(a.position.z, b.position.z),
+        },
+    }
+}
+
+fn quat_mul(a: &Quaternionf, b: &Quaternionf) -> Quaternionf {
+    Quaternionf {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    }
+}
+
+fn quat_conjugate(q: &Quaternionf) -> Quaternionf {
+    Quaternionf {
+        x: -q.x,
+        y: -q.y,
+        z: -q.z,
+        w: q.w,
+    }
+}
+
+fn quat_normalize(q: Quaternionf) -> Quaternionf {
+    let len = (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+    if len < 1e-6 {
+        return Quaternionf::IDENTITY;
+    }
+    Quaternionf {
+        x: q.x / len,
+        y: q.y / len,
+        z: q.z / len,
+        w: q.w / len,
+    }
+}
+
+fn quat_rotate(q: &Quaternionf, v: &Vector3f) -> Vector3f {
+    let p = Quaternionf {
+        x: v.x,
+        y: v.y,
+        z: v.z,
+        w: 0.0,
+    };
+    let r = quat_mul(&quat_mul(q, &p), &quat_conjugate(q));
+    Vector3f {
+        x: r.x,
+        y: r.y,
+        z: r.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    fn assert_pose_close(a: &Posef, b: &Posef) {
+        assert_close(a.position.x, b.position.x);
+        assert_close(a.position.y, b.position.y);
+        assert_close(a.position.z, b.position.z);
+        // q and -q are the same rotation
+        let q = (a.orientation, b.orientation);
+        let sign = if q.0.x * q.1.x + q.0.y * q.1.y + q.0.z * q.1.z + q.0.w * q.1.w < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        assert_close(q.0.x, sign * q.1.x);
+        assert_close(q.0.y, sign * q.1.y);
+        assert_close(q.0.z, sign * q.1.z);
+        assert_close(q.0.w, sign * q.1.w);
+    }
+
+    fn some_pose() -> Posef {
+        Posef {
+            orientation: quat_normalize(Quaternionf {
+                x: 0.3,
+                y: -0.5,
+                z: 0.1,
+                w: 0.8,
+            }),
+            position: Vector3f {
+                x: 1.5,
+                y: -0.25,
+                z: 2.0,
+            },
+        }
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let anchor = PanelAnchor {
+            mode: AnchorMode::Grabbable,
+            pose: some_pose(),
+            scale: 1.25,
+            ..PanelAnchor::default()
+        };
+        let loaded = PanelAnchor::from_text(&anchor.to_text());
+        assert_eq!(loaded.mode, AnchorMode::Grabbable);
+        assert_pose_close(&loaded.pose, &anchor.pose);
+        assert_close(loaded.scale, 1.25);
+    }
+
+    #[test]
+    fn garbage_falls_back_to_the_default() {
+        let loaded = PanelAnchor::from_text(
+            "mode=sideways\nposition=1 2\norientation=a b c d\nscale=\nnonsense\n",
+        );
+        let default = PanelAnchor::default();
+        assert_eq!(loaded.mode, default.mode);
+        assert_pose_close(&loaded.pose, &default.pose);
+        assert_eq!(loaded.scale, default.scale);
+    }
+
+    #[test]
+    fn out_of_range_scale_is_clamped() {
+        assert_eq!(PanelAnchor::from_text("scale=100").scale, MAX_SCALE);
+    }
+
+    #[test]
+    fn a_pose_composed_with_its_inverse_is_the_identity() {
+        let p = some_pose();
+        assert_pose_close(&pose_compose(&p, &pose_inverse(&p)), &Posef::IDENTITY);
+        assert_pose_close(&pose_compose(&pose_inverse(&p), &p), &Posef::IDENTITY);
+    }
+
+    #[test]
+    fn grabbing_keeps_the_panel_where_it_was() {
+        let mut anchor = PanelAnchor {
+            mode: AnchorMode::Grabbable,
+            pose: some_pose(),
+            ..PanelAnchor::default()
+        };
+        let hand = Posef {
+            orientation: Quaternionf::IDENTITY,
+            position: Vector3f {
+                x: 0.5,
+                y: 0.0,
+                z: 0.0,
+            },
+        };
+        anchor.update(None, Some(&hand));
+        anchor.update(None, Some(&hand));
+        assert_pose_close(&anchor.pose, &some_pose());
+    }
+}
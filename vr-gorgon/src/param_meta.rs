@@ -0,0 +1,188 @@
+use crate::control_panel::GorgonParam;
+use crate::i18n;
+
+/// What a [GorgonParam] can be set to, and how to show it.
+/// Anything that edits or checks a parameter should consult this instead of its own constants:
+/// today that is the control panel, the [crate::keypad], loading presets with
+/// [crate::gorgon1::MultiGorgonSettings::from_text], and [crate::surprise].
+/// There is no network control yet; when one is added it should validate through this table too.
+#[derive(PartialEq, Debug)]
+pub struct ParamMeta {
+    /// the message ID of the field's label (see [i18n::tr])
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    /// one flick of the thumbstick on a stepped field,
+    /// or one frame of full thumbstick deflection on a continuous one
+    pub step: f32,
+    /// a sweep of the laser pointer across a field's label
+    pub coarse_step: f32,
+    /// the message ID of the unit shown after the value; empty for plain numbers
+    pub unit: &'static str,
+    pub default: f32,
+    /// digits after the decimal point when displayed
+    pub decimals: usize,
+    /// the parameter is stored as an integer, so fractions are refused rather than rounded away
+    pub integer: bool,
+    /// how much of min..max, around the default, tends to look good (see [Self::tasteful_range])
+    pub tasteful: f32,
+}
+
+impl ParamMeta {
+    pub fn clamp(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        if self.integer {
+            value.round()
+        } else {
+            value
+        }
+    }
+
+    /// The part of min..max which [crate::surprise] picks values from: [Self::tasteful] of it,
+    /// centered on the default where that fits.  The full range is mostly for people who know what they want.
+    pub fn tasteful_range(&self) -> (f32, f32) {
+        let width = (self.max - self.min) * self.tasteful;
+        let low = (self.default - width / 2.0).clamp(self.min, self.max - width);
+        (low, low + width)
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value) && !(self.integer && value.fract() != 0.0)
+    }
+
+    /// the field's label in the current locale
+    pub fn label(&self) -> String {
+        i18n::tr(self.name)
+    }
+
+    pub fn format(&self, value: f32) -> String {
+        if self.unit.is_empty() {
+            format!("{:.*}", self.decimals, value)
+        } else {
+            format!("{:.*} {}", self.decimals, value, i18n::tr(self.unit))
+        }
+    }
+}
+
+const ENABLE: ParamMeta = ParamMeta {
+    name: "param-enable",
+    min: 0.0,
+    max: 1.0,
+    step: 1.0,
+    coarse_step: 1.0,
+    unit: "",
+    default: 0.0,
+    decimals: 0,
+    integer: true,
+    tasteful: 1.0,
+};
+
+/// the `{frequency}.0` in the shaders only works for whole numbers
+const FREQUENCY: ParamMeta = ParamMeta {
+    name: "param-frequency",
+    min: 1.0,
+    max: 255.0,
+    step: 1.0,
+    coarse_step: 10.0,
+    unit: "unit-bands",
+    default: 4.0,
+    decimals: 0,
+    integer: true,
+    tasteful: 0.06,
+};
+
+/// `phase` goes from 0 to 1 every loop of the animation (16 seconds)
+const SPEED: ParamMeta = ParamMeta {
+    name: "param-speed",
+    min: -20.0,
+    max: 20.0,
+    step: 0.1,
+    coarse_step: 1.0,
+    unit: "unit-per-loop",
+    default: 1.0,
+    decimals: 1,
+    integer: false,
+    tasteful: 0.1,
+};
+
+const AMPLITUDE: ParamMeta = ParamMeta {
+    name: "param-amplitude",
+    min: -10.0,
+    max: 10.0,
+    step: 0.1,
+    coarse_step: 1.0,
+    unit: "unit-bands",
+    default: 0.0,
+    decimals: 1,
+    integer: false,
+    tasteful: 0.05,
+};
+
+const CURL: ParamMeta = ParamMeta {
+    name: "param-curl",
+    min: -10.0,
+    max: 10.0,
+    step: 0.1,
+    coarse_step: 1.0,
+    unit: "unit-bands",
+    default: 0.0,
+    decimals: 1,
+    integer: false,
+    tasteful: 0.2,
+};
+
+impl GorgonParam {
+    pub const ALL: [GorgonParam; 5] = [
+        GorgonParam::Enable,
+        GorgonParam::Frequency,
+        GorgonParam::Speed,
+        GorgonParam::Amplitude,
+        GorgonParam::Curl,
+    ];
+
+    /// for files, unlike [ParamMeta::label]
+    pub fn key(&self) -> &'static str {
+        match self {
+            GorgonParam::Enable => "enable",
+            GorgonParam::Frequency => "frequency",
+            GorgonParam::Speed => "speed",
+            GorgonParam::Amplitude => "amplitude",
+            GorgonParam::Curl => "curl",
+        }
+    }
+
+    pub fn meta(&self) -> &'static ParamMeta {
+        match self {
+            GorgonParam::Enable => &ENABLE,
+            GorgonParam::Frequency => &FREQUENCY,
+            GorgonParam::Speed => &SPEED,
+            GorgonParam::Amplitude => &AMPLITUDE,
+            GorgonParam::Curl => &CURL,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasteful_ranges_are_inside_the_full_ranges() {
+        for param in GorgonParam::ALL {
+            let meta = param.meta();
+            let (low, high) = meta.tasteful_range();
+            assert!(
+                meta.min <= low && low < high && high <= meta.max,
+                "{:?}",
+                param
+            );
+        }
+    }
+
+    #[test]
+    fn tasteful_ranges_center_on_the_default_where_they_fit() {
+        assert_eq!(CURL.tasteful_range(), (-2.0, 2.0));
+        // the default frequency is too close to the minimum to be in the middle
+        assert_eq!(FREQUENCY.tasteful_range().0, FREQUENCY.min);
+    }
+}
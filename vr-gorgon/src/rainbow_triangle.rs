@@ -6,6 +6,8 @@ use gl_thin::linear::XrMatrix4x4f;
 
 //
 
+/// A triangle with a color at each corner, drawn by `bob_shaders`' [FlatColorShader].
+/// That program is compiled by `bob_shaders` as GLSL ES 1.00, without [crate::glsl_dialect]'s translation.
 pub struct RainbowTriangle<'a> {
     pub program: FlatColorShader,
     pub buffers: VertexBufferBundle<'a, GLfloat, u8>,
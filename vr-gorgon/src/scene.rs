@@ -1,8 +1,18 @@
-use crate::control_panel::ControlPanel;
+use crate::control_panel::{ControlPanel, GorgonAxis, GorgonShape};
 use crate::drawcore;
+use crate::error_overlay::{ErrorOverlay, ReportedError};
+use crate::evolution::Evolution;
 use crate::gorgon1::{Gorgon1, GorgonSettings, MultiGorgonSettings};
+use crate::i18n::{self, LocaleDirectory};
+use crate::laser_pointer::Ray;
+use crate::menu::PanelButton;
+use crate::panel_anchor::{pose_matrix, PanelAnchor};
 use crate::rainbow_triangle::RainbowTriangle;
+use crate::shader_export;
+use crate::shader_reload::{ShaderChange, ShaderDirectory, ShaderFile};
 use crate::suzanne::Suzanne;
+use crate::theme::Theme;
+use crate::thumbstick_smoother::ThumbstickSmoother;
 use gl_thin::gl_fancy::GPUState;
 use gl_thin::gl_helper::{explode_if_gl_error, GLErrorWrapper};
 use gl_thin::linear::{
@@ -13,45 +23,179 @@ use gl_thin::linear::{
     XrMatrix4x4f, XrQuaternionf, XrVector3f,
 };
 use openxr::SpaceLocation;
-use openxr_sys::{Time, Vector2f};
+use openxr_sys::{Posef, Time, Vector2f};
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::f32::consts::TAU;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub struct MyScene {
     pub rainbow_triangle: RainbowTriangle<'static>,
     pub suzanne: Suzanne,
     pub gorgon1: RefCell<Gorgon1>,
     pub controls: ControlPanel,
+    pub errors: ErrorOverlay,
+    evolution: Evolution,
     gorgon_settings: MultiGorgonSettings,
+    shader_directory: Option<ShaderDirectory>,
+    locale_directory: Option<LocaleDirectory>,
+    anchor: PanelAnchor,
+    /// where [Self::anchor] is saved
+    anchor_path: Option<PathBuf>,
+    /// where the name of the panel's [Theme] is saved
+    theme_path: Option<PathBuf>,
+    /// where [Self::gorgon_settings] are saved
+    settings_path: Option<PathBuf>,
+    /// what is in the file at [Self::settings_path], as far as we know
+    saved_settings: String,
+    /// when [Self::save_gorgon_settings] last compared the settings with [Self::saved_settings]
+    settings_checked: Option<Instant>,
+    theme_smoother: ThumbstickSmoother,
+    panel_size_smoother: ThumbstickSmoother,
 }
 
 impl MyScene {
     pub fn new(gpu_state: &mut GPUState) -> Result<Self, GLErrorWrapper> {
-        let mut gorgon_settings = MultiGorgonSettings::default();
-        gorgon_settings.spirals[2] = GorgonSettings {
-            enabled: true,
-            frequency: 8,
-            speed: 4.0,
-            amplitude: 0.0,
-            curl: std::f32::consts::PI,
-        };
-        gorgon_settings.latitudes[2] = GorgonSettings {
-            enabled: true,
-            frequency: 40,
-            speed: 1.0,
-            amplitude: 3.0,
-            curl: 0.0,
-        };
+        let gorgon_settings = MultiGorgonSettings::new(&[
+            (
+                GorgonShape::Spiral,
+                GorgonAxis::Z,
+                GorgonSettings {
+                    enabled: true,
+                    frequency: 8,
+                    speed: 4.0,
+                    amplitude: 0.0,
+                    curl: std::f32::consts::PI,
+                },
+            ),
+            (
+                GorgonShape::Latitude,
+                GorgonAxis::Z,
+                GorgonSettings {
+                    enabled: true,
+                    frequency: 40,
+                    speed: 1.0,
+                    amplitude: 3.0,
+                    curl: 0.0,
+                },
+            ),
+        ]);
         Ok(MyScene {
             rainbow_triangle: RainbowTriangle::new(gpu_state)?,
             suzanne: Suzanne::new(gpu_state)?,
             gorgon1: RefCell::new(Gorgon1::new(gpu_state)?),
             controls: ControlPanel::new(gpu_state)?,
+            errors: ErrorOverlay::new(gpu_state)?,
+            evolution: Evolution::new(gpu_state)?,
             gorgon_settings,
+            shader_directory: None,
+            locale_directory: None,
+            anchor: PanelAnchor::default(),
+            anchor_path: None,
+            theme_path: None,
+            settings_path: None,
+            saved_settings: String::new(),
+            settings_checked: None,
+            theme_smoother: ThumbstickSmoother::edge_triggered(),
+            panel_size_smoother: Default::default(),
         })
     }
 
+    /// Shader hot-reload is only enabled if `dir` exists (`adb shell mkdir` it to opt in).
+    pub fn watch_shader_directory(&mut self, dir: PathBuf) {
+        if dir.is_dir() {
+            log::info!("watching {:?} for replacement shaders", dir);
+            self.shader_directory = Some(ShaderDirectory::new(dir));
+        }
+    }
+
+    /// Translations are only looked for if `dir` exists, like [Self::watch_shader_directory].
+    pub fn watch_locale_directory(&mut self, dir: PathBuf) {
+        if dir.is_dir() {
+            log::info!("watching {:?} for locale selection and translations", dir);
+            self.locale_directory = Some(LocaleDirectory::new(dir));
+        }
+    }
+
+    /// switch languages if the watched `locale.txt` (or a translation) changed
+    pub fn poll_locale_directory(&mut self) {
+        if let Some(locale_directory) = &mut self.locale_directory {
+            locale_directory.poll();
+        }
+    }
+
+    /// recompile any shaders whose files changed in the watched directory.
+    /// If a replacement fails to compile, the shader it was meant to replace stays in use
+    /// and the error is reported in the [ErrorOverlay].  Removing the file goes back to the built-in shader.
+    pub fn poll_shader_directory(&mut self, gpu_state: &mut GPUState) {
+        let changes = match &mut self.shader_directory {
+            Some(shader_directory) => shader_directory.poll(),
+            None => return,
+        };
+
+        for (file, change) in changes {
+            let source = match change {
+                ShaderChange::Modified(source) => source,
+                ShaderChange::Removed => {
+                    self.revert_shader(file, gpu_state);
+                    continue;
+                }
+            };
+            match self.reload_shader(file, &source, gpu_state) {
+                Ok(true) => {
+                    log::info!(
+                        "reloaded {}; it stays in use until it is removed",
+                        file.file_name()
+                    );
+                    self.errors.clear(file.file_name());
+                }
+                Ok(false) => log::debug!("{} is not in use", file.file_name()),
+                Err(e) => {
+                    self.errors
+                        .report(ReportedError::shader_compile(file.file_name(), e, source))
+                }
+            }
+        }
+    }
+
+    /// `file` was removed from the watched directory, so stop using it
+    fn revert_shader(&mut self, file: ShaderFile, gpu_state: &mut GPUState) {
+        self.errors.clear(file.file_name());
+        // the gorgon's built-in shader is the one generated from the settings
+        if self
+            .gorgon1
+            .get_mut()
+            .revert_shader(file, &self.gorgon_settings)
+        {
+            log::info!(
+                "{} removed; rebuilding the gorgon from its settings",
+                file.file_name()
+            );
+            return;
+        }
+        match self
+            .controls
+            .reload_shader(file, file.embedded_source(), gpu_state)
+        {
+            Ok(true) => log::info!("{} removed; using the built-in copy", file.file_name()),
+            Ok(false) => {}
+            Err(e) => log::error!("built-in {} failed: {}", file.file_name(), e),
+        }
+    }
+
+    fn reload_shader(
+        &mut self,
+        file: ShaderFile,
+        source: &str,
+        gpu_state: &mut GPUState,
+    ) -> Result<bool, GLErrorWrapper> {
+        if self.gorgon1.get_mut().reload_shader(file, source)? {
+            return Ok(true);
+        }
+        self.controls.reload_shader(file, source, gpu_state)
+    }
+
     pub fn draw(
         &self,
         fov: &XrFovf,
@@ -116,15 +260,23 @@ impl MyScene {
             (phase as f32) / 16000.0
         };
 
-        self.gorgon1
-            .borrow_mut()
-            .paint(&skybox_pv, phase, &self.gorgon_settings, gpu_state)?;
+        {
+            let mut gorgon1 = self.gorgon1.borrow_mut();
+            gorgon1.paint(&skybox_pv, phase, &self.gorgon_settings, gpu_state)?;
+            match gorgon1.take_rebuild_result() {
+                Some(Ok(())) => self.errors.clear(Gorgon1::SHADER_CONTEXT),
+                Some(Err(e)) => self.errors.report(e),
+                None => {}
+            }
+        }
 
         //
 
         unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT) };
         explode_if_gl_error()?;
 
+        self.evolution.draw(&matrix_pv, phase, gpu_state)?;
+
         //
 
         {
@@ -153,21 +305,17 @@ impl MyScene {
                 self.suzanne.index_count(),
                 gpu_state,
             )?;
+        }
 
-            let model = {
-                let r1 = rotate_x2(0.0, 1.0);
-                let s1 = xr_matrix4x4f_uniform_scale(0.1);
-                let t1 = xr_matrix4x4f_create_translation(0.0, 0.0, -0.2);
-
-                let translate =
-                    xr_matrix4x4f_create_translation_v(&controller_1.pose.position.into());
-                let rotation_matrix =
-                    xr_matrix4x4f_create_from_quaternion(&controller_1.pose.orientation.into());
-                translate * rotation_matrix * t1 * r1 * s1
-            };
+        let controller_pose = controller_1.as_ref().map(|controller_1| &controller_1.pose);
+        if let Some(panel_pose) = self.anchor.panel_pose(controller_pose) {
+            let model = pose_matrix(&panel_pose) * xr_matrix4x4f_uniform_scale(self.panel_scale());
 
+            let matrix = matrix_pv * model;
             self.controls
-                .draw(&(matrix_pv * model), gpu_state, &self.gorgon_settings)?;
+                .draw(&matrix, phase, gpu_state, &self.gorgon_settings)?;
+            self.errors
+                .draw(&matrix, self.controls.atlas(), gpu_state)?;
         }
 
         /* {
@@ -196,8 +344,247 @@ impl MyScene {
     pub fn handle_a_click(&mut self) {
         self.controls.handle_a_click(&mut self.gorgon_settings)
     }
+
+    pub fn mutate(&mut self) {
+        self.controls.mutate(&mut self.gorgon_settings)
+    }
+
+    pub fn cycle_mutation_amount(&mut self) {
+        self.controls.cycle_mutation_amount()
+    }
+
+    pub fn undo(&mut self) {
+        if !self.gorgon_settings.undo() {
+            log::debug!("nothing to undo");
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if !self.gorgon_settings.redo() {
+            log::debug!("nothing to redo");
+        }
+    }
+
+    /// the [ReportedError::context] for failures to compile the evolution previews
+    const EVOLUTION_CONTEXT: &'static str = "evolution";
+
+    /// Enter or leave evolution mode.  The first generation is arranged in front of `head`.
+    pub fn toggle_evolution(&mut self, head: Option<&Posef>) {
+        if self.evolution.is_active() {
+            self.evolution.stop();
+            return;
+        }
+        if let Some(head) = head {
+            let amount = self.controls.mutation_amount();
+            self.evolution.start(&self.gorgon_settings, head, amount);
+            self.errors.clear(Self::EVOLUTION_CONTEXT);
+        }
+    }
+
+    /// Aim the laser pointer at the evolution previews or the control panel.
+    /// Picking a preview makes it the current settings (as one undo step).
+    /// `controller_1` is only needed while the panel is anchored to it.
+    pub fn handle_pointer(
+        &mut self,
+        controller_1: Option<&SpaceLocation>,
+        pointer: Option<&SpaceLocation>,
+        trigger_pressed: bool,
+        trigger_held: bool,
+        gpu_state: &mut GPUState,
+    ) {
+        if let Err(e) = self.evolution.build_next_preview(gpu_state) {
+            self.errors
+                .report(ReportedError::runtime(Self::EVOLUTION_CONTEXT, e));
+        }
+        let amount = self.controls.mutation_amount();
+        if let Some(picked) = self.evolution.handle_pointer(
+            pointer.map(|pointer| &pointer.pose),
+            trigger_pressed,
+            amount,
+        ) {
+            self.gorgon_settings.replace_layers(&picked);
+        }
+        // the previews are in front of the panel, so they get the trigger
+        let trigger_pressed = trigger_pressed && !self.evolution.is_hovering();
+
+        let panel_pose = self
+            .anchor
+            .panel_pose(controller_1.map(|controller_1| &controller_1.pose));
+        let hit = match (panel_pose, pointer) {
+            (Some(panel_pose), Some(pointer)) => {
+                let to_panel = xr_matrix4x4f_invert_rigid_body(&pose_matrix(&panel_pose));
+                Ray::from_pose(&pointer.pose)
+                    .transformed(&to_panel)
+                    .hit_z0()
+                    .map(|[x, y]| [x / self.panel_scale(), y / self.panel_scale()])
+            }
+            _ => None,
+        };
+        let pressed = self.controls.handle_pointer(
+            hit,
+            trigger_pressed,
+            trigger_held,
+            &mut self.gorgon_settings,
+        );
+        if pressed == Some(PanelButton::Locale) {
+            self.select_next_locale();
+        }
+    }
+
+    /// Switch to the next language.  With a watched locale directory the choice is saved there;
+    /// otherwise it lasts until the app exits.
+    pub fn select_next_locale(&mut self) {
+        match &mut self.locale_directory {
+            Some(locale_directory) => locale_directory.select_next(),
+            None => i18n::select_locale(&i18n::next_locale(None), None),
+        }
+    }
+
+    /// Restore the panel's [AnchorMode] and pose from `path`, and save them there whenever they change.
+    pub fn persist_panel_anchor(&mut self, path: PathBuf) {
+        match PanelAnchor::load(&path) {
+            Ok(anchor) => self.anchor = anchor,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("failed to load {:?}: {}", path, e),
+        }
+        self.anchor_path = Some(path);
+    }
+
+    /// Load the gorgon settings from `path` if it exists (values outside their [crate::param_meta::ParamMeta]
+    /// are ignored), and save them there as they change.
+    pub fn persist_gorgon_settings(&mut self, path: PathBuf) {
+        match MultiGorgonSettings::load(&path) {
+            Ok(settings) => self.gorgon_settings = settings,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("failed to load {:?}: {}", path, e),
+        }
+        self.saved_settings = self.gorgon_settings.to_text();
+        self.settings_path = Some(path);
+    }
+
+    /// Call once a frame.  The settings are saved at most every [SETTINGS_SAVE_PERIOD],
+    /// so dragging a value does not rewrite the file every frame.
+    pub fn save_gorgon_settings(&mut self) {
+        let path = match &self.settings_path {
+            Some(path) => path,
+            None => return,
+        };
+        if self
+            .settings_checked
+            .is_some_and(|checked| checked.elapsed() < SETTINGS_SAVE_PERIOD)
+        {
+            return;
+        }
+        self.settings_checked = Some(Instant::now());
+
+        let text = self.gorgon_settings.to_text();
+        if text == self.saved_settings {
+            return;
+        }
+        match std::fs::write(path, &text) {
+            Ok(()) => self.saved_settings = text,
+            Err(e) => log::warn!("failed to save {:?}: {}", path, e),
+        }
+    }
+
+    /// the size of the control panel's -1..1 square, in meters
+    fn panel_scale(&self) -> f32 {
+        PANEL_SCALE * self.anchor.scale
+    }
+
+    /// Let the PNGs in `dir` replace or add to the control panel's icons (see [ControlPanel::load_icons]).
+    pub fn load_icons(
+        &mut self,
+        dir: &Path,
+        dump: &Path,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        self.controls.load_icons(dir, dump, gpu_state)
+    }
+
+    /// Restore the panel's [Theme] from `path`, and save it there whenever it changes.
+    pub fn persist_theme(&mut self, path: PathBuf) {
+        match std::fs::read_to_string(&path) {
+            Ok(name) => match Theme::from_name(name.trim()) {
+                Some(theme) => self.set_theme(theme),
+                None => log::warn!("unknown theme {:?} in {:?}", name.trim(), path),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("failed to load {:?}: {}", path, e),
+        }
+        self.theme_path = Some(path);
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.controls.set_theme(theme);
+        self.errors.set_theme(theme);
+        self.evolution.set_theme(theme);
+    }
+
+    /// switch to the next built-in [Theme] (or the previous one, if `step` is negative)
+    pub fn cycle_theme(&mut self, step: isize) {
+        let theme = self.controls.theme().cycle(step);
+        log::info!("control panel theme {}", theme.name);
+        self.set_theme(theme);
+        if let Some(path) = &self.theme_path {
+            if let Err(e) = std::fs::write(path, theme.name) {
+                log::warn!("failed to save {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Each flick left or right on the left thumbstick picks the next [Theme]; up and down resize the panel.
+    /// Clicking the stick cycles the panel anchor instead, so while it is `pressed` the deflection is ignored,
+    /// and a flick that started during the click does nothing until the stick has been back to the middle.
+    pub fn handle_left_thumbstick(&mut self, delta: Vector2f, pressed: bool) {
+        let theme_step = self.theme_smoother.smooth_input(delta.x);
+        let size_step = self.panel_size_smoother.smooth_input(delta.y);
+        if pressed {
+            return;
+        }
+        match theme_step {
+            Ordering::Less => self.cycle_theme(-1),
+            Ordering::Equal => {}
+            Ordering::Greater => self.cycle_theme(1),
+        }
+        match size_step {
+            Ordering::Less => self.anchor.resize(-1),
+            Ordering::Equal => {}
+            Ordering::Greater => self.anchor.resize(1),
+        }
+    }
+
+    pub fn cycle_panel_anchor(&mut self, controller_1: Option<&SpaceLocation>) {
+        self.anchor
+            .cycle_mode(controller_1.map(|controller_1| &controller_1.pose));
+    }
+
+    /// Call once a frame, after painting.  `grip` is the left grip while it is squeezed.
+    pub fn update_panel_anchor(&mut self, head: Option<&Posef>, grip: Option<&SpaceLocation>) {
+        self.anchor.update(head, grip.map(|grip| &grip.pose));
+        if self.anchor.take_changed() {
+            if let Some(path) = &self.anchor_path {
+                if let Err(e) = self.anchor.save(path) {
+                    log::warn!("failed to save {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+
+    /// write standalone copies of the current gorgon shader into `dir`
+    pub fn export_shaders(&self, dir: &Path) {
+        match shader_export::write_exports(&self.gorgon_settings, dir) {
+            Ok(paths) => log::info!("exported gorgon shaders {:?}", paths),
+            Err(e) => log::error!("failed to export gorgon shaders into {:?}: {}", dir, e),
+        }
+    }
 }
 
+/// the size of the control panel's -1..1 square, in meters, before [PanelAnchor::scale]
+const PANEL_SCALE: f32 = 0.1;
+/// how often changed gorgon settings are written out
+const SETTINGS_SAVE_PERIOD: Duration = Duration::from_secs(2);
+
 fn rotation_matrix_for_now() -> (f32, XrMatrix4x4f) {
     let theta = if let Ok(duration) = SystemTime::now().duration_since(UNIX_EPOCH) {
         let tm = duration.as_millis();
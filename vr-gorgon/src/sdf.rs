@@ -0,0 +1,90 @@
+/// stands in for infinity in [squared_distance_transform], without making NaNs of `inf - inf`
+const FAR: f32 = 1e20;
+
+/// The signed distance (in pixels) from the center of each pixel to the edge of the shape made by
+/// the `inside` pixels of a `width`x`height` grid: positive inside the shape, negative outside.
+pub fn signed_distance_field(inside: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let to_outside = squared_distance_transform(
+        &inside.iter().map(|i| !i).collect::<Vec<_>>(),
+        width,
+        height,
+    );
+    let to_inside = squared_distance_transform(inside, width, height);
+    inside
+        .iter()
+        .enumerate()
+        .map(|(idx, &i)| {
+            // the edge runs between pixel centers, half a pixel from either
+            if i {
+                to_outside[idx].sqrt() - 0.5
+            } else {
+                0.5 - to_inside[idx].sqrt()
+            }
+        })
+        .collect()
+}
+
+/// The squared distance from each pixel to the nearest `target` pixel,
+/// using the separable algorithm of Felzenszwalb & Huttenlocher (columns, then rows).
+fn squared_distance_transform(target: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let mut grid: Vec<f32> = target.iter().map(|&t| if t { 0.0 } else { FAR }).collect();
+
+    let n = width.max(height);
+    let mut f = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let mut v = vec![0; n];
+    let mut z = vec![0.0; n + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = grid[y * width + x];
+        }
+        transform_1d(&f[..height], &mut d[..height], &mut v, &mut z);
+        for y in 0..height {
+            grid[y * width + x] = d[y];
+        }
+    }
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        f[..width].copy_from_slice(row);
+        transform_1d(&f[..width], row, &mut v, &mut z);
+    }
+    grid
+}
+
+/// one dimension of [squared_distance_transform]: the lower envelope of parabolas rooted at each `f`.
+/// `v` and `z` are scratch space at least as long as `f` (and one longer, for `z`).
+fn transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+    // parabola `k` is lowest between z[k] and z[k+1], and rooted at v[k]
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -FAR;
+    z[1] = FAR;
+    for q in 1..n {
+        let s = loop {
+            let p = v[k];
+            let s = ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2 * (q - p)) as f32;
+            if s > z[k] || k == 0 {
+                break s;
+            }
+            k -= 1;
+        };
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = FAR;
+    }
+
+    k = 0;
+    for (q, dq) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let dx = q as f32 - v[k] as f32;
+        *dq = dx * dx + f[v[k]];
+    }
+}
@@ -0,0 +1,199 @@
+use crate::glsl_include::preprocess;
+use crate::gorgon1::{MultiGorgonSettings, AXIS_SWIZZLES, VERTEX_SHADER};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// The flavors of standalone GLSL we can produce from a [MultiGorgonSettings]
+/// so that people without a headset can look at a preset.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ExportFlavor {
+    /// paste into <https://www.shadertoy.com/new>.  The camera ray comes from `fragCoord`
+    /// and the mouse can be dragged to look around.
+    Shadertoy,
+    /// the same GLSL ES 1.00 fragment shader the headset compiles.
+    /// The host must supply `varying vec3 ray` and `uniform float phase`.
+    GlslEs,
+}
+
+impl ExportFlavor {
+    pub const ALL: [ExportFlavor; 2] = [ExportFlavor::Shadertoy, ExportFlavor::GlslEs];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFlavor::Shadertoy => "gorgon-shadertoy.glsl",
+            ExportFlavor::GlslEs => "gorgon-es100.frag",
+        }
+    }
+}
+
+/// A `//` comment block listing every layer that contributed to the shader.
+/// The values are written in full, so the preset can be recovered from an export.
+pub fn settings_comment(settings: &MultiGorgonSettings) -> String {
+    let mut rval = String::new();
+    let _ = writeln!(&mut rval, "// exported from Bob's VR gorgon");
+    for (shape, axis, one) in settings.layers() {
+        if !one.enabled {
+            continue;
+        }
+        let _ = writeln!(
+            &mut rval,
+            "//   {} {} ({}): frequency={} speed={} amplitude={} curl={}",
+            shape.key(),
+            axis.letter(),
+            AXIS_SWIZZLES[axis.index() as usize],
+            one.frequency,
+            one.speed,
+            one.amplitude,
+            one.curl,
+        );
+    }
+    rval
+}
+
+pub fn export_shader(settings: &MultiGorgonSettings, flavor: ExportFlavor) -> String {
+    let mut rval = settings_comment(settings);
+    let builder = settings.shader_builder();
+
+    match flavor {
+        ExportFlavor::Shadertoy => {
+            rval.push_str(
+                "
+// Shadertoy has no phase uniform, so we derive it from iTime (one cycle every 16 seconds)
+float phase;
+",
+            );
+            rval.push_str(&builder.build_library());
+            rval.push_str(
+                "
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    phase = fract(iTime / 16.0);
+
+    vec2 uv = (2.0*fragCoord - iResolution.xy) / iResolution.y;
+    vec3 ray = normalize(vec3(uv, -1.5));
+
+    // drag with the mouse to look around the sphere
+    vec2 look = iMouse.z > 0.0 ? (iMouse.xy / iResolution.xy - 0.5) * vec2(2.0*PI, PI) : vec2(0.0);
+    float cp = cos(look.y);
+    float sp = sin(look.y);
+    ray = vec3(ray.x, cp*ray.y + sp*ray.z, -sp*ray.y + cp*ray.z);
+    float cy = cos(look.x);
+    float sy = sin(look.x);
+    ray = vec3(cy*ray.x + sy*ray.z, ray.y, -sy*ray.x + cy*ray.z);
+
+    float g = gorgon_intensity(ray);
+    fragColor = vec4(g,g,g, 1.0);
+}
+",
+            );
+        }
+        ExportFlavor::GlslEs => {
+            rval.push_str("//\n// pair it with this vertex shader (draw a cube around the eye):\n");
+            for line in VERTEX_SHADER.lines().filter(|line| !line.is_empty()) {
+                let _ = writeln!(&mut rval, "//   {}", line);
+            }
+            rval.push_str(&builder.build());
+        }
+    }
+
+    // the snippet library only exists inside the app
+    preprocess(&rval).text
+}
+
+/// write every [ExportFlavor] into `dir`, returning the paths of the files written
+pub fn write_exports(settings: &MultiGorgonSettings, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut rval = vec![];
+    for flavor in ExportFlavor::ALL {
+        let path = dir.join(flavor.file_name());
+        std::fs::write(&path, export_shader(settings, flavor))?;
+        rval.push(path);
+    }
+    Ok(rval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_panel::{CPCursor, GorgonAxis, GorgonParam, GorgonShape};
+    use crate::gorgon1::GorgonSettings;
+
+    fn preset() -> MultiGorgonSettings {
+        let mut settings = MultiGorgonSettings::default();
+        let cursor = CPCursor {
+            row: GorgonShape::Cartesian,
+            axis: GorgonAxis::Z,
+            subrow: GorgonParam::Speed,
+        };
+        settings.set_param(GorgonParam::Enable, 1.0, cursor);
+        settings.set_param(GorgonParam::Frequency, 7.0, cursor);
+        settings.set_param(GorgonParam::Speed, -1.3, cursor);
+        settings.set_param(GorgonParam::Amplitude, 0.37, cursor);
+        settings.set_param(GorgonParam::Curl, 0.1, cursor);
+        settings
+    }
+
+    /// the inverse of [settings_comment], for the layers it lists
+    fn parse_comment(comment: &str) -> Vec<(GorgonShape, GorgonAxis, GorgonSettings)> {
+        let mut rval = vec![];
+        for line in comment.lines().skip(1) {
+            let line = line.strip_prefix("//   ").unwrap();
+            let (names, values) = line.split_once(": ").unwrap();
+            let mut names = names.split(' ');
+            let shape = names.next().unwrap();
+            let shape = GorgonShape::ALL
+                .into_iter()
+                .find(|s| s.key() == shape)
+                .unwrap();
+            let axis = names.next().unwrap();
+            let axis = GorgonAxis::ALL
+                .into_iter()
+                .find(|a| a.letter() == axis)
+                .unwrap();
+            let mut settings = GorgonSettings {
+                enabled: true,
+                ..GorgonSettings::default()
+            };
+            for pair in values.split(' ') {
+                let (key, value) = pair.split_once('=').unwrap();
+                let param = GorgonParam::ALL
+                    .into_iter()
+                    .find(|p| p.key() == key)
+                    .unwrap();
+                settings.set(param, value.parse().unwrap());
+            }
+            rval.push((shape, axis, settings));
+        }
+        rval
+    }
+
+    #[test]
+    fn the_settings_comment_lists_every_enabled_layer_exactly() {
+        let settings = preset();
+        let enabled: Vec<_> = settings
+            .layers()
+            .into_iter()
+            .filter(|(_, _, one)| one.enabled)
+            .collect();
+        assert_eq!(parse_comment(&settings_comment(&settings)), enabled);
+    }
+
+    #[test]
+    fn shadertoy_exports_define_main_image() {
+        let source = export_shader(&preset(), ExportFlavor::Shadertoy);
+        assert!(source.starts_with(&settings_comment(&preset())));
+        assert!(source.contains("void mainImage(out vec4 fragColor, in vec2 fragCoord)"));
+        assert!(!source.contains("gl_FragColor"));
+        assert!(!source.contains("#include"));
+    }
+
+    #[test]
+    fn glsl_es_exports_are_complete_fragment_shaders() {
+        let source = export_shader(&preset(), ExportFlavor::GlslEs);
+        assert!(source.starts_with(&settings_comment(&preset())));
+        assert!(source.contains("precision"));
+        assert!(source.contains("gl_FragColor"));
+        assert!(!source.contains("mainImage"));
+        assert!(!source.contains("#include"));
+    }
+}
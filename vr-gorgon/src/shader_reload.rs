@@ -0,0 +1,177 @@
+use crate::file_watch::{FileChange, WatchedDirectory};
+use crate::gorgon1;
+use crate::shaders::{BoxOutline, ConcentricRings, Latitude, Latitwod, SpriteRect};
+use std::path::{Path, PathBuf};
+
+/// The fragment shaders which can be replaced by a file in the watched directory.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ShaderFile {
+    GorgonSpiral,
+    GorgonSphereAxes,
+    GorgonTwoCircles,
+    ConcentricRings,
+    Latitude,
+    Latitwod,
+    BoxOutline,
+    SpriteRect,
+    SdfText,
+    SpriteRgba,
+}
+
+impl ShaderFile {
+    pub const ALL: [ShaderFile; 10] = [
+        ShaderFile::GorgonSpiral,
+        ShaderFile::GorgonSphereAxes,
+        ShaderFile::GorgonTwoCircles,
+        ShaderFile::ConcentricRings,
+        ShaderFile::Latitude,
+        ShaderFile::Latitwod,
+        ShaderFile::BoxOutline,
+        ShaderFile::SpriteRect,
+        ShaderFile::SdfText,
+        ShaderFile::SpriteRgba,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ShaderFile::GorgonSpiral => "gorgon-spiral.glsl",
+            ShaderFile::GorgonSphereAxes => "gorgon-sphere-axes.glsl",
+            ShaderFile::GorgonTwoCircles => "gorgon-two-circles.glsl",
+            ShaderFile::ConcentricRings => "concentric-rings.glsl",
+            ShaderFile::Latitude => "latitude.glsl",
+            ShaderFile::Latitwod => "latitwod.glsl",
+            ShaderFile::BoxOutline => "box-outline.glsl",
+            ShaderFile::SpriteRect => "sprite-rect.glsl",
+            ShaderFile::SdfText => "sdf-text.glsl",
+            ShaderFile::SpriteRgba => "sprite-rgba.glsl",
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|file| file.file_name() == name)
+    }
+
+    /// the copy compiled into the APK, which we go back to when the replacement file is removed
+    pub fn embedded_source(&self) -> &'static str {
+        match self {
+            ShaderFile::GorgonSpiral => gorgon1::gorgon_spiral(),
+            ShaderFile::GorgonSphereAxes => gorgon1::gorgon_sphere_axes(),
+            ShaderFile::GorgonTwoCircles => gorgon1::gorgon_two_circles(),
+            ShaderFile::ConcentricRings => ConcentricRings::fragment_shader(),
+            ShaderFile::Latitude => Latitude::fragment_shader(),
+            ShaderFile::Latitwod => Latitwod::fragment_shader(),
+            ShaderFile::BoxOutline => BoxOutline::fragment_shader(),
+            ShaderFile::SpriteRect => SpriteRect::fragment_shader(),
+            ShaderFile::SdfText => SpriteRect::sdf_text_fragment_shader(),
+            ShaderFile::SpriteRgba => SpriteRect::rgba_fragment_shader(),
+        }
+    }
+}
+
+//
+
+/// what happened to a watched shader file since the previous poll
+#[derive(Clone, PartialEq, Debug)]
+pub enum ShaderChange {
+    /// the file appeared or changed; this is its new contents
+    Modified(String),
+    Removed,
+}
+
+/// Watch a directory for replacement fragment shaders so they can be tweaked with
+/// `adb push` instead of rebuilding the APK.
+pub struct ShaderDirectory {
+    watched: WatchedDirectory,
+}
+
+impl ShaderDirectory {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            watched: WatchedDirectory::new(dir),
+        }
+    }
+
+    /// return every recognized file that appeared, changed or disappeared since the previous poll.
+    /// The first poll returns every file that is already present.
+    pub fn poll(&mut self) -> Vec<(ShaderFile, ShaderChange)> {
+        let mut rval = vec![];
+        for (path, change) in self.watched.poll(|path| shader_file_for(path).is_some()) {
+            let file = match shader_file_for(&path) {
+                Some(file) => file,
+                None => continue,
+            };
+            match change {
+                FileChange::Modified => match std::fs::read_to_string(&path) {
+                    Ok(source) => rval.push((file, ShaderChange::Modified(source))),
+                    Err(e) => {
+                        log::warn!("failed to read {:?}: {}", path, e);
+                        self.watched.retry(&path);
+                    }
+                },
+                FileChange::Removed => rval.push((file, ShaderChange::Removed)),
+            }
+        }
+        rval
+    }
+}
+
+fn shader_file_for(path: &Path) -> Option<ShaderFile> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(ShaderFile::from_file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_watch::ScratchDirectory;
+
+    #[test]
+    fn file_names_map_back_to_their_shader() {
+        for file in ShaderFile::ALL {
+            assert_eq!(ShaderFile::from_file_name(file.file_name()), Some(file));
+        }
+        assert_eq!(ShaderFile::from_file_name("sdf-text"), None);
+        assert_eq!(ShaderFile::from_file_name("SDF-TEXT.GLSL"), None);
+        assert_eq!(ShaderFile::from_file_name("locale.txt"), None);
+    }
+
+    #[test]
+    fn only_recognized_files_are_reported() {
+        let scratch = ScratchDirectory::new("shaders-recognized");
+        scratch.write("sdf-text.glsl", b"void main() {}", 1000);
+        scratch.write("notes.glsl", b"hello", 1000);
+        let mut shaders = ShaderDirectory::new(scratch.0.clone());
+        assert_eq!(
+            shaders.poll(),
+            vec![(
+                ShaderFile::SdfText,
+                ShaderChange::Modified("void main() {}".into())
+            )]
+        );
+
+        std::fs::remove_file(scratch.0.join("sdf-text.glsl")).unwrap();
+        shaders.watched.expire();
+        assert_eq!(
+            shaders.poll(),
+            vec![(ShaderFile::SdfText, ShaderChange::Removed)]
+        );
+    }
+
+    #[test]
+    fn a_file_that_could_not_be_read_is_read_again() {
+        let scratch = ScratchDirectory::new("shaders-retry");
+        // not UTF-8
+        scratch.write("latitude.glsl", &[0xff, 0xfe], 1000);
+        let mut shaders = ShaderDirectory::new(scratch.0.clone());
+        assert_eq!(shaders.poll(), vec![]);
+
+        // the same modification time, so only the retry notices it
+        scratch.write("latitude.glsl", b"fixed", 1000);
+        shaders.watched.expire();
+        assert_eq!(
+            shaders.poll(),
+            vec![(ShaderFile::Latitude, ShaderChange::Modified("fixed".into()))]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::glsl_dialect::compile_program;
 use crate::sprites::SpriteLocation;
 use gl::types::{GLfloat, GLsizei, GLuint};
 use gl_thin::gl_fancy::{ActiveTextureUnit, GPUState, VertexBufferBundle};
@@ -7,12 +8,7 @@ use gl_thin::linear::XrMatrix4x4f;
 pub fn vertex_shader() -> &'static str {
     "
 precision mediump float;
-uniform mat4 matrix;
-
-attribute vec3 position;
-attribute vec2 uv;
-
-varying vec2 tex_coord;
+#include \"uv_vertex\"
 
 void main() {
     gl_Position = matrix * vec4(position, 1.0) ;
@@ -31,7 +27,12 @@ pub struct ConcentricRings {
 
 impl ConcentricRings {
     pub fn new() -> Result<Self, GLErrorWrapper> {
-        let program = Program::compile(vertex_shader(), Self::fragment_shader())?;
+        Self::with_fragment_shader(Self::fragment_shader())
+    }
+
+    /// like [Self::new], but with replacement GLSL (see [crate::shader_reload])
+    pub fn with_fragment_shader(fragment_shader: &str) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(vertex_shader(), fragment_shader)?;
         let sul_matrix = program.get_uniform_location("matrix")?;
         let sal_position = program.get_attribute_location("position")?;
         let sal_uv = program.get_attribute_location("uv")?;
@@ -46,7 +47,7 @@ impl ConcentricRings {
     pub fn fragment_shader() -> &'static str {
         "
 precision mediump float;
-varying vec2 tex_coord;
+#include \"uv_fragment\"
 void main()
 {
     float d1 = distance(vec2(0.5,0.5), tex_coord) ;
@@ -98,7 +99,12 @@ pub struct Latitude {
 
 impl Latitude {
     pub fn new() -> Result<Self, GLErrorWrapper> {
-        let program = Program::compile(vertex_shader(), Self::fragment_shader())?;
+        Self::with_fragment_shader(Self::fragment_shader())
+    }
+
+    /// like [Self::new], but with replacement GLSL (see [crate::shader_reload])
+    pub fn with_fragment_shader(fragment_shader: &str) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(vertex_shader(), fragment_shader)?;
         let sul_matrix = program.get_uniform_location("matrix")?;
         let sal_position = program.get_attribute_location("position")?;
         let sal_uv = program.get_attribute_location("uv")?;
@@ -140,8 +146,8 @@ impl Latitude {
     pub fn fragment_shader() -> &'static str {
         "
 precision mediump float;
-#define PI 3.1415926538
-varying vec2 tex_coord;
+#include \"pi\"
+#include \"uv_fragment\"
 void main() {
     vec2 dxy = tex_coord - vec2(0.5, 1.0);
     float d1 = length(dxy);
@@ -166,7 +172,12 @@ pub struct Latitwod {
 
 impl Latitwod {
     pub fn new() -> Result<Self, GLErrorWrapper> {
-        let program = Program::compile(vertex_shader(), Self::fragment_shader())?;
+        Self::with_fragment_shader(Self::fragment_shader())
+    }
+
+    /// like [Self::new], but with replacement GLSL (see [crate::shader_reload])
+    pub fn with_fragment_shader(fragment_shader: &str) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(vertex_shader(), fragment_shader)?;
         let sul_matrix = program.get_uniform_location("matrix")?;
         let sal_position = program.get_attribute_location("position")?;
         let sal_uv = program.get_attribute_location("uv")?;
@@ -208,8 +219,8 @@ impl Latitwod {
     pub fn fragment_shader() -> &'static str {
         "
 precision mediump float;
-#define PI 3.1415926538
-varying vec2 tex_coord;
+#include \"pi\"
+#include \"uv_fragment\"
 void main() {
     vec2 dxy = tex_coord - vec2(0.5, 1.0);
     float d1 = length(dxy);
@@ -238,7 +249,12 @@ pub struct SpriteRect {
 
 impl SpriteRect {
     pub fn new() -> Result<Self, GLErrorWrapper> {
-        let program = Program::compile(Self::vertex_shader(), Self::fragment_shader())?;
+        Self::with_fragment_shader(Self::fragment_shader())
+    }
+
+    /// like [Self::new], but with replacement GLSL (see [crate::shader_reload])
+    pub fn with_fragment_shader(fragment_shader: &str) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(Self::vertex_shader(), fragment_shader)?;
         let sal_position = program.get_attribute_location("position")?;
         let sal_uv = program.get_attribute_location("uv")?;
         let sul_matrix = program.get_uniform_location("matrix")?;
@@ -263,15 +279,10 @@ impl SpriteRect {
     pub fn vertex_shader() -> &'static str {
         "
 precision mediump float;
-uniform mat4 matrix;
+#include \"uv_vertex\"
 uniform vec2 scale;
 uniform vec2 offset;
 
-attribute vec3 position;
-attribute vec2 uv;
-
-varying vec2 tex_coord;
-
 void main() {
     gl_Position = matrix * vec4(position, 1.0) ;
     tex_coord = uv*scale + offset;
@@ -284,7 +295,7 @@ void main() {
 precision mediump float;
 
 uniform sampler2D tex;
-varying vec2 tex_coord;
+#include \"uv_fragment\"
 uniform vec4 fg;
 uniform vec4 bg;
 
@@ -296,6 +307,49 @@ void main() {
 "
     }
 
+    /// Draws a texture with its own colors and alpha (like a [crate::sprite_atlas::SpriteAtlas])
+    /// over `bg`, tinted by `fg`.
+    pub fn rgba_fragment_shader() -> &'static str {
+        "
+precision mediump float;
+
+uniform sampler2D tex;
+#include \"uv_fragment\"
+uniform vec4 fg;
+uniform vec4 bg;
+
+void main() {
+    vec4 rgba = texture2D(tex, tex_coord);
+    // the texture over the background, both with straight (not premultiplied) alpha
+    float alpha = rgba.a + bg.a * (1.0 - rgba.a);
+    vec3 rgb = rgba.rgb * rgba.a + bg.rgb * bg.a * (1.0 - rgba.a);
+    gl_FragColor = fg * vec4(alpha > 0.0 ? rgb / alpha : rgb, alpha);
+}
+"
+    }
+
+    /// Draws text from a [crate::glyph_atlas::GlyphAtlas], whose texture holds signed distance fields.
+    /// The edge is blurred over about one screen pixel however big the text is, so it is never soft or jagged.
+    pub fn sdf_text_fragment_shader() -> &'static str {
+        "
+#extension GL_OES_standard_derivatives : enable
+precision mediump float;
+
+uniform sampler2D tex;
+#include \"uv_fragment\"
+uniform vec4 fg;
+uniform vec4 bg;
+
+void main() {
+    float distance = texture2D(tex, tex_coord).r;
+    // how much the distance changes across this pixel on the screen
+    float edge = max(fwidth(distance), 0.001) * 0.7;
+    float alpha = smoothstep(0.5 - edge, 0.5 + edge, distance);
+    gl_FragColor = mix(bg, fg, alpha);
+}
+"
+    }
+
     /// use this with [VertexBufferBundle::new]
     pub fn attributes_tuples(&self, position_len: i32) -> [(GLuint, i32, i32); 2] {
         [
@@ -375,19 +429,30 @@ void main() {
 pub struct BoxOutline {
     program: Program,
     pub sul_matrix: GLuint,
+    pub sul_color: GLuint,
+    pub sul_thickness: GLuint,
     pub sal_position: GLuint,
     pub sal_uv: GLuint,
 }
 
 impl BoxOutline {
     pub fn new() -> Result<Self, GLErrorWrapper> {
-        let program = Program::compile(vertex_shader(), Self::fragment_shader())?;
+        Self::with_fragment_shader(Self::fragment_shader())
+    }
+
+    /// like [Self::new], but with replacement GLSL (see [crate::shader_reload])
+    pub fn with_fragment_shader(fragment_shader: &str) -> Result<Self, GLErrorWrapper> {
+        let program = compile_program(vertex_shader(), fragment_shader)?;
         let sul_matrix = program.get_uniform_location("matrix")?;
+        let sul_color = program.get_uniform_location("color")?;
+        let sul_thickness = program.get_uniform_location("thickness")?;
         let sal_position = program.get_attribute_location("position")?;
         let sal_uv = program.get_attribute_location("uv")?;
         Ok(Self {
             program,
             sul_matrix,
+            sul_color,
+            sul_thickness,
             sal_position,
             sal_uv,
         })
@@ -396,13 +461,14 @@ impl BoxOutline {
     pub fn fragment_shader() -> &'static str {
         "
 precision mediump float;
-varying vec2 tex_coord;
+#include \"uv_fragment\"
+uniform vec4 color;
+uniform float thickness;
 void main()
 {
-    float thick = 0.06;
+    float thick = thickness;
     bool b = tex_coord.x >thick && tex_coord.x + thick < 1.0 && tex_coord.y > thick && tex_coord.y+thick < 1.0;
-    float g = b ? 0.0 : 1.0;
-    gl_FragColor = vec4(g,g,g,g);
+    gl_FragColor = b ? vec4(0.0) : color;
 }
 "
     }
@@ -415,18 +481,29 @@ void main()
         ]
     }
 
-    pub fn set_parameters(&self, matrix: &XrMatrix4x4f) -> Result<(), GLErrorWrapper> {
-        self.program.set_mat4u(self.sul_matrix as _, &matrix.m)
+    /// `thickness` is a fraction of the width and height of the square
+    pub fn set_parameters(
+        &self,
+        matrix: &XrMatrix4x4f,
+        color: &[f32; 4],
+        thickness: f32,
+    ) -> Result<(), GLErrorWrapper> {
+        self.program.set_mat4u(self.sul_matrix as _, &matrix.m)?;
+        self.program.set_uniform_4fv(self.sul_color as _, color)?;
+        self.program
+            .set_uniform_1f(self.sul_thickness as _, thickness)
     }
 
     pub fn draw<IT: GLBufferType>(
         &self,
         matrix: &XrMatrix4x4f,
+        color: &[f32; 4],
+        thickness: f32,
         buffers: &VertexBufferBundle<GLfloat, IT>,
         gpu_state: &mut GPUState,
     ) -> Result<(), GLErrorWrapper> {
         self.program.use_()?;
-        self.set_parameters(matrix)?;
+        self.set_parameters(matrix, color, thickness)?;
 
         let n_indices = buffers.index_count;
         let bound = buffers.bind(gpu_state)?;
@@ -0,0 +1,401 @@
+use crate::fonts::FontRegistry;
+use crate::sprites::{with_unpack_alignment, SpriteLocation};
+use crate::text_layout::TextStyle;
+use crate::text_painting::{self, TextSpan};
+use gl::types::GLint;
+use gl_thin::gl_fancy::GPUState;
+use gl_thin::gl_helper::{GLErrorWrapper, Texture};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// the narrowest atlas we bother with
+const MIN_WIDTH: u32 = 64;
+/// most GLES devices support textures at least this big
+const MAX_SIZE: u32 = 4096;
+
+/// Places rectangles left to right in rows ("shelves"), starting a new shelf below the tallest
+/// rectangle of the previous one when the next rectangle does not fit.
+/// Little space is wasted if the rectangles arrive tallest first.
+pub struct ShelfPacker {
+    width: u32,
+    x: u32,
+    y: u32,
+    shelf_height: u32,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32) -> Self {
+        Self {
+            width,
+            x: 0,
+            y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    /// the top left corner for a `w`x`h` rectangle, or None if it would go below `height`
+    pub fn place(&mut self, w: u32, h: u32, height: u32) -> Option<[u32; 2]> {
+        if w > self.width {
+            return None;
+        }
+        if self.x + w > self.width {
+            self.x = 0;
+            self.y += self.shelf_height;
+            self.shelf_height = 0;
+        }
+        if self.y + h > height {
+            return None;
+        }
+        let rval = [self.x, self.y];
+        self.x += w;
+        self.shelf_height = self.shelf_height.max(h);
+        Some(rval)
+    }
+
+    /// how far down the shelves reach so far
+    pub fn used_height(&self) -> u32 {
+        self.y + self.shelf_height
+    }
+}
+
+//
+
+/// where one named image ended up in a [SpriteAtlas], in pixels, not counting the padding
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpriteRegion {
+    pub min: [u32; 2],
+    pub size: [u32; 2],
+}
+
+/// Collects named images (rendered text, PNG icons, or anything else that is an [RgbaImage])
+/// to be packed into one texture by [Self::build].
+pub struct SpriteAtlasBuilder {
+    padding: u32,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl SpriteAtlasBuilder {
+    /// Each image gets `padding` pixels on every side, filled with copies of its edge pixels,
+    /// so that filtering and mipmapping do not blend its neighbours into it.
+    pub fn new(padding: u32) -> Self {
+        Self {
+            padding,
+            images: vec![],
+        }
+    }
+
+    /// add `image` as `name`, replacing any image already added with that name
+    pub fn add_image(&mut self, name: impl Into<String>, image: RgbaImage) {
+        let name = name.into();
+        if image.width() == 0 || image.height() == 0 {
+            log::warn!("sprite {:?} is empty; leaving it out", name);
+            return;
+        }
+        match self.images.iter_mut().find(|(old, _)| *old == name) {
+            Some(slot) => slot.1 = image,
+            None => self.images.push((name, image)),
+        }
+    }
+
+    /// add `spans` as rendered by [text_painting::render_spans_to_rgba]
+    pub fn add_text(
+        &mut self,
+        name: impl Into<String>,
+        fonts: &'static FontRegistry,
+        font_size: f32,
+        spans: &[TextSpan],
+        style: &TextStyle,
+    ) {
+        let image = text_painting::render_spans_to_rgba(fonts, font_size, spans, style);
+        self.add_image(name, image);
+    }
+
+    /// add a PNG (or any other format `image` can guess from its contents)
+    pub fn add_png(&mut self, name: impl Into<String>, bytes: &[u8]) -> image::ImageResult<()> {
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        self.add_image(name, image);
+        Ok(())
+    }
+
+    /// Add every `.png` in `dir`, named after its file without the extension.
+    /// Files which can not be read are logged and skipped.
+    pub fn add_png_directory(&mut self, dir: &Path) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("failed to list {:?}: {}", dir, e);
+                return;
+            }
+        };
+        let mut paths: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+            })
+            .collect();
+        // so that the packing does not depend on the order the filesystem lists them in
+        paths.sort();
+        for path in paths {
+            let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let loaded = std::fs::read(&path)
+                .map_err(image::ImageError::IoError)
+                .and_then(|bytes| self.add_png(name, &bytes));
+            if let Err(e) = loaded {
+                log::warn!("failed to load {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Arrange the images in the smallest power-of-two texture they fit in.
+    /// Images which do not fit even in a [MAX_SIZE] square are logged and left out.
+    fn pack(&self) -> (RgbaImage, HashMap<String, SpriteRegion>) {
+        let pad = self.padding;
+        let padded = |image: &RgbaImage| [image.width() + 2 * pad, image.height() + 2 * pad];
+
+        // tallest first, which suits the shelves
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&idx| std::cmp::Reverse(self.images[idx].1.height()));
+
+        let area: u32 = self
+            .images
+            .iter()
+            .map(|(_, image)| padded(image).iter().product::<u32>())
+            .sum();
+        let widest = self
+            .images
+            .iter()
+            .map(|(_, image)| padded(image)[0])
+            .max()
+            .unwrap_or(0);
+        let mut width = ((area as f32).sqrt().ceil() as u32)
+            .max(widest)
+            .max(MIN_WIDTH)
+            .next_power_of_two()
+            .min(MAX_SIZE);
+
+        // widen the atlas until everything fits, or it is as big as it can get
+        let (corners, height) = loop {
+            let mut packer = ShelfPacker::new(width);
+            let corners: Vec<Option<[u32; 2]>> = order
+                .iter()
+                .map(|&idx| {
+                    let [w, h] = padded(&self.images[idx].1);
+                    packer.place(w, h, MAX_SIZE)
+                })
+                .collect();
+            if width >= MAX_SIZE || corners.iter().all(Option::is_some) {
+                let height = packer.used_height().max(1).next_power_of_two();
+                break (corners, height);
+            }
+            width *= 2;
+        };
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut regions = HashMap::new();
+        for (&idx, corner) in order.iter().zip(corners) {
+            let (name, image) = &self.images[idx];
+            let corner = match corner {
+                Some(corner) => corner,
+                None => {
+                    log::warn!(
+                        "sprite atlas is full; dropping {:?} ({}x{})",
+                        name,
+                        image.width(),
+                        image.height()
+                    );
+                    continue;
+                }
+            };
+            let [w, h] = padded(image);
+            for y in 0..h {
+                for x in 0..w {
+                    // the padding repeats the nearest edge pixel
+                    let sx = x.saturating_sub(pad).min(image.width() - 1);
+                    let sy = y.saturating_sub(pad).min(image.height() - 1);
+                    atlas.put_pixel(corner[0] + x, corner[1] + y, *image.get_pixel(sx, sy));
+                }
+            }
+            regions.insert(
+                name.clone(),
+                SpriteRegion {
+                    min: [corner[0] + pad, corner[1] + pad],
+                    size: [image.width(), image.height()],
+                },
+            );
+        }
+        (atlas, regions)
+    }
+
+    /// pack the images and upload them
+    pub fn build(&self, gpu_state: &mut GPUState) -> Result<SpriteAtlas, GLErrorWrapper> {
+        let (image, regions) = self.pack();
+        let texture = Texture::new()?;
+        let (width, height) = image.dimensions();
+        let bound = texture.bound(gl::TEXTURE_2D, gpu_state)?;
+        with_unpack_alignment(4 * width as usize, || {
+            bound.write_pixels_and_generate_mipmap(
+                0,
+                gl::RGBA as GLint,
+                width as _,
+                height as _,
+                gl::RGBA,
+                image.as_raw(),
+            )
+        })?;
+        log::debug!(
+            "packed {} sprites into a {}x{} atlas",
+            regions.len(),
+            width,
+            height
+        );
+        Ok(SpriteAtlas {
+            texture,
+            image,
+            regions,
+        })
+    }
+}
+
+//
+
+/// Named images packed into one RGBA texture by a [SpriteAtlasBuilder].
+/// Draw one with [crate::control_panel::SpriteRectG::draw_sprite].
+pub struct SpriteAtlas {
+    texture: Texture,
+    /// what was uploaded, for [Self::dump_png]
+    image: RgbaImage,
+    regions: HashMap<String, SpriteRegion>,
+}
+
+impl SpriteAtlas {
+    /// the size of image `name` in pixels, for keeping its aspect ratio
+    pub fn size(&self, name: &str) -> Option<[u32; 2]> {
+        self.regions.get(name).map(|region| region.size)
+    }
+
+    /// the part of the texture that holds image `name`
+    pub fn sprite(&self, name: &str) -> Option<SpriteLocation<'_>> {
+        let region = self.regions.get(name)?;
+        let (width, height) = self.image.dimensions();
+        Some(SpriteLocation::new(
+            [
+                region.size[0] as f32 / width as f32,
+                region.size[1] as f32 / height as f32,
+            ],
+            [
+                region.min[0] as f32 / width as f32,
+                region.min[1] as f32 / height as f32,
+            ],
+            &self.texture,
+        ))
+    }
+
+    /// save the packed atlas, padding and all, to see how it was arranged
+    pub fn dump_png(&self, path: &Path) -> image::ImageResult<()> {
+        self.image.save_with_format(path, image::ImageFormat::Png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    fn solid(w: u32, h: u32, value: u8) -> RgbaImage {
+        RgbaImage::from_pixel(w, h, Rgba([value; 4]))
+    }
+
+    #[test]
+    fn shelves() {
+        let mut packer = ShelfPacker::new(10);
+        assert_eq!(Some([0, 0]), packer.place(4, 3, 10));
+        assert_eq!(Some([4, 0]), packer.place(6, 2, 10));
+        // the shelf is full, so the next one starts below its tallest rectangle
+        assert_eq!(Some([0, 3]), packer.place(5, 5, 10));
+        assert_eq!(8, packer.used_height());
+        assert_eq!(None, packer.place(11, 1, 10), "wider than the packer");
+        assert_eq!(None, packer.place(5, 8, 10), "below the height");
+        assert_eq!(Some([5, 3]), packer.place(5, 2, 10));
+    }
+
+    #[test]
+    fn padding_repeats_edges() {
+        let mut builder = SpriteAtlasBuilder::new(2);
+        let mut image = RgbaImage::new(2, 2);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            *pixel = Rgba([x as u8, y as u8, 7, 255]);
+        }
+        builder.add_image("corners", image.clone());
+        let (atlas, regions) = builder.pack();
+
+        let region = regions["corners"];
+        assert_eq!(
+            SpriteRegion {
+                min: [2, 2],
+                size: [2, 2]
+            },
+            region
+        );
+        for y in 0..6 {
+            for x in 0..6 {
+                let nearest = image.get_pixel(x.clamp(2, 3) - 2, y.clamp(2, 3) - 2);
+                assert_eq!(nearest, atlas.get_pixel(x, y), "at {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn widens_until_everything_fits() {
+        let mut builder = SpriteAtlasBuilder::new(0);
+        builder.add_image("tall", solid(64, 4000, 1));
+        builder.add_image("wide1", solid(900, 100, 2));
+        builder.add_image("wide2", solid(900, 100, 3));
+        let (atlas, regions) = builder.pack();
+
+        // the area alone suggests 1024 wide, but then the wide ones end up below the tall one
+        assert_eq!((2048, 4096), atlas.dimensions());
+        assert_eq!(3, regions.len());
+        assert_eq!([0, 0], regions["tall"].min);
+        assert_eq!(0, regions["wide1"].min[1]);
+        assert_eq!(0, regions["wide2"].min[1]);
+    }
+
+    #[test]
+    fn drops_what_does_not_fit() {
+        let mut builder = SpriteAtlasBuilder::new(1);
+        builder.add_image("huge", solid(MAX_SIZE, 1, 1));
+        builder.add_image("small", solid(3, 3, 2));
+        let (atlas, regions) = builder.pack();
+
+        assert!(!regions.contains_key("huge"));
+        assert_eq!(
+            SpriteRegion {
+                min: [1, 1],
+                size: [3, 3]
+            },
+            regions["small"]
+        );
+        assert_eq!(MAX_SIZE, atlas.width());
+        assert_eq!(&Rgba([2; 4]), atlas.get_pixel(1, 1));
+    }
+
+    #[test]
+    fn replaces_by_name() {
+        let mut builder = SpriteAtlasBuilder::new(0);
+        builder.add_image("icon", solid(2, 2, 1));
+        builder.add_image("icon", solid(3, 4, 2));
+        builder.add_image("empty", RgbaImage::new(0, 5));
+        let (atlas, regions) = builder.pack();
+
+        assert_eq!(1, regions.len());
+        assert_eq!([3, 4], regions["icon"].size);
+        assert_eq!(&Rgba([2; 4]), atlas.get_pixel(0, 0));
+    }
+}
@@ -1,9 +1,5 @@
-use crate::{control_panel, text_painting};
-use gl::types::GLsizei;
-use gl_thin::gl_fancy::GPUState;
-use gl_thin::gl_helper::{GLErrorWrapper, Texture};
-use image::RgbImage;
-use rusttype::{point, Scale};
+use gl::types::GLint;
+use gl_thin::gl_helper::{explode_if_gl_error, GLErrorWrapper, Texture};
 
 pub struct SpriteLocation<'a> {
     pub scale: [f32; 2],
@@ -29,120 +25,41 @@ impl<'a> SpriteLocation<'a> {
     }
 }
 
-//
+/// the `GL_UNPACK_ALIGNMENT` GL starts with, and which code we do not control may rely on
+const DEFAULT_UNPACK_ALIGNMENT: GLint = 4;
 
-pub struct SpriteSheet {
-    pub texture: Texture,
-    pub word_ys: Vec<f32>,
+/// The largest `GL_UNPACK_ALIGNMENT` that rows of `row_bytes` bytes satisfy.
+/// Our images are packed tightly, so one byte per pixel images rarely have rows a multiple of 4 long.
+pub fn unpack_alignment(row_bytes: usize) -> GLint {
+    [8, 4, 2]
+        .into_iter()
+        .find(|alignment| row_bytes.is_multiple_of(*alignment as usize))
+        .unwrap_or(1)
 }
 
-impl SpriteSheet {
-    pub fn new(gpu_state: &mut GPUState) -> Result<Self, GLErrorWrapper> {
-        let font = control_panel::default_font().unwrap();
-
-        let width: GLsizei = 256;
-        let height = 256;
-        let mut img = RgbImage::new(width as _, height as _);
-        let font_size = 60.0;
-
-        let m1 = font.v_metrics(Scale::uniform(font_size));
-        let ascent = m1.ascent;
-
-        for (i, msg) in ["x", "y", "z"].iter().enumerate() {
-            text_painting::paint_text_in_image(
-                font,
-                &mut img,
-                font_size,
-                point((width * i as GLsizei) as f32 / 4.0, ascent),
-                msg,
-            );
-        }
-
-        let small_font = 30.0;
-        let m2 = font.v_metrics(Scale::uniform(small_font));
-
-        let word_ys: Vec<_> = ["freq", "speed", "amplitude", "curl", "kludge"]
-            .iter()
-            .enumerate()
-            .map(|(i, msg)| {
-                let y0 = height as f32 / 4.0 + 1.0 + i as f32 * 1.5 * (m2.ascent - m2.descent);
-                let y2 = y0 + m2.ascent;
-                text_painting::paint_text_in_image(font, &mut img, small_font, point(0.0, y2), msg);
-                y0 / height as f32
-            })
-            .collect();
-
-        log::debug!("word_ys {:?}", word_ys);
-
-        // let (width, height) = target.get_dimensions()?;
-        let target = Texture::new()?;
-
-        if true {
-            log::debug!(
-                "text pixels {:?} .. {:?}",
-                img.as_raw().iter().min(),
-                img.as_raw().iter().max()
-            );
-        }
-
-        target
-            .bound(gl::TEXTURE_2D, gpu_state)?
-            .write_pixels_and_generate_mipmap(
-                0,
-                gl::RGB as _,
-                width,
-                height,
-                gl::RGB,
-                img.as_raw(),
-            )?;
-        Ok(Self {
-            texture: target,
-            word_ys,
-        })
-    }
-
-    pub fn x(&self) -> SpriteLocation<'_> {
-        SpriteLocation::new([0.25; 2], [-0.1, 0.0], &self.texture)
-    }
-
-    pub fn y(&self) -> SpriteLocation {
-        SpriteLocation::new([0.25; 2], [0.15, 0.0], &self.texture)
-    }
-
-    pub fn z(&self) -> SpriteLocation {
-        SpriteLocation::new([0.25; 2], [0.40, 0.0], &self.texture)
-    }
-
-    pub fn freq(&self) -> SpriteLocation {
-        SpriteLocation::new(
-            [0.5, self.word_ys[1] - self.word_ys[0]],
-            [0.0, self.word_ys[0]],
-            &self.texture,
-        )
-    }
+/// Run `upload` with `GL_UNPACK_ALIGNMENT` set for tightly packed rows of `row_bytes` bytes,
+/// then put back the default.
+pub fn with_unpack_alignment<T>(
+    row_bytes: usize,
+    upload: impl FnOnce() -> Result<T, GLErrorWrapper>,
+) -> Result<T, GLErrorWrapper> {
+    unsafe { gl::PixelStorei(gl::UNPACK_ALIGNMENT, unpack_alignment(row_bytes)) };
+    explode_if_gl_error()?;
+    let rval = upload();
+    unsafe { gl::PixelStorei(gl::UNPACK_ALIGNMENT, DEFAULT_UNPACK_ALIGNMENT) };
+    rval
+}
 
-    pub fn speed(&self) -> SpriteLocation {
-        const IDX: usize = 1;
-        SpriteLocation::new(
-            [0.5, self.word_ys[1 + IDX] - self.word_ys[IDX]],
-            [0.0, self.word_ys[IDX]],
-            &self.texture,
-        )
-    }
-    pub fn amplitude(&self) -> SpriteLocation {
-        const IDX: usize = 2;
-        SpriteLocation::new(
-            [0.5, self.word_ys[1 + IDX] - self.word_ys[IDX]],
-            [0.0, self.word_ys[IDX]],
-            &self.texture,
-        )
-    }
-    pub fn curl(&self) -> SpriteLocation {
-        const IDX: usize = 3;
-        SpriteLocation::new(
-            [0.5, self.word_ys[1 + IDX] - self.word_ys[IDX]],
-            [0.0, self.word_ys[IDX]],
-            &self.texture,
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_get_the_largest_alignment_they_satisfy() {
+        assert_eq!(unpack_alignment(512), 8);
+        assert_eq!(unpack_alignment(4 * 13), 4);
+        assert_eq!(unpack_alignment(3 * 2), 2);
+        assert_eq!(unpack_alignment(3 * 11), 1);
+        assert_eq!(unpack_alignment(1), 1);
     }
 }
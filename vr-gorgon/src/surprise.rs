@@ -0,0 +1,220 @@
+use crate::control_panel::{GorgonAxis, GorgonParam, GorgonShape};
+use crate::gorgon1::{GorgonSettings, MultiGorgonSettings};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// more layers than this XOR together into noise
+pub const MAX_ENABLED_LAYERS: usize = 4;
+/// with nothing enabled there is nothing to look at
+pub const MIN_ENABLED_LAYERS: usize = 1;
+
+/// how much [mutate] is allowed to change things, as a fraction of each parameter's tasteful range
+pub const MUTATION_AMOUNTS: [f32; 3] = [0.1, 0.25, 0.5];
+
+/// SplitMix64.  Not for cryptography, but tiny and the same on every device,
+/// so a seed always reproduces the same settings.
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// uniform in 0..1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// uniform in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+/// seeds are below this, so they can be typed on the [crate::keypad] to replay a surprise
+pub const SEED_LIMIT: u64 = 1_000_000_000;
+
+/// a seed nobody has seen before, from the clock
+pub fn fresh_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    SeededRng::new(nanos).next_u64() % SEED_LIMIT
+}
+
+/// the parameters [surprise_me] and [mutate] pick numbers for; enabling is handled separately
+const TUNABLE: [GorgonParam; 4] = [
+    GorgonParam::Frequency,
+    GorgonParam::Speed,
+    GorgonParam::Amplitude,
+    GorgonParam::Curl,
+];
+
+/// Replace every layer with random settings derived from `seed`, as one undo step.
+/// Between [MIN_ENABLED_LAYERS] and [MAX_ENABLED_LAYERS] layers end up enabled.
+pub fn surprise_me(settings: &mut MultiGorgonSettings, seed: u64) {
+    let mut rng = SeededRng::new(seed);
+    let mut layers = settings.layers();
+
+    let mut order: Vec<usize> = (0..layers.len()).collect();
+    rng.shuffle(&mut order);
+    let count = MIN_ENABLED_LAYERS + rng.below(MAX_ENABLED_LAYERS - MIN_ENABLED_LAYERS + 1);
+
+    for (rank, idx) in order.into_iter().enumerate() {
+        let gorgon = &mut layers[idx].2;
+        gorgon.enabled = rank < count;
+        for param in TUNABLE {
+            let (min, max) = param.meta().tasteful_range();
+            // a still, straight layer is a nice contrast to the busy ones
+            let value = match param {
+                GorgonParam::Amplitude | GorgonParam::Curl if rng.chance(0.5) => 0.0,
+                _ => rng.range(min, max),
+            };
+            gorgon.set(param, value);
+        }
+    }
+
+    settings.replace_layers(&layers);
+}
+
+/// Nudge the current settings by up to `amount` of each parameter's tasteful range, as one undo step.
+/// Layers are switched on or off with a probability of `amount`,
+/// keeping the number enabled between [MIN_ENABLED_LAYERS] and [MAX_ENABLED_LAYERS].
+pub fn mutate(settings: &mut MultiGorgonSettings, seed: u64, amount: f32) {
+    let mut rng = SeededRng::new(seed);
+    let mut layers = settings.layers();
+
+    for (_, _, gorgon) in layers.iter_mut() {
+        if rng.chance(amount) {
+            gorgon.enabled = !gorgon.enabled;
+        }
+        for param in TUNABLE {
+            let (min, max) = param.meta().tasteful_range();
+            let delta = rng.range(-1.0, 1.0) * amount * (max - min);
+            gorgon.set(param, gorgon.get(param) + delta);
+        }
+    }
+
+    enforce_enabled_limits(&mut layers, &mut rng);
+    settings.replace_layers(&layers);
+}
+
+/// switch random layers on or off until the count is within limits
+fn enforce_enabled_limits(
+    layers: &mut [(GorgonShape, GorgonAxis, GorgonSettings)],
+    rng: &mut SeededRng,
+) {
+    let mut order: Vec<usize> = (0..layers.len()).collect();
+    rng.shuffle(&mut order);
+    let mut count = layers.iter().filter(|(_, _, g)| g.enabled).count();
+    for idx in order {
+        let gorgon = &mut layers[idx].2;
+        if count > MAX_ENABLED_LAYERS && gorgon.enabled {
+            gorgon.enabled = false;
+            count -= 1;
+        } else if count < MIN_ENABLED_LAYERS && !gorgon.enabled {
+            gorgon.enabled = true;
+            count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surprise(seed: u64) -> MultiGorgonSettings {
+        let mut settings = MultiGorgonSettings::default();
+        surprise_me(&mut settings, seed);
+        settings
+    }
+
+    fn enabled(settings: &MultiGorgonSettings) -> usize {
+        settings
+            .layers()
+            .iter()
+            .filter(|(_, _, gorgon)| gorgon.enabled)
+            .count()
+    }
+
+    #[test]
+    fn a_seed_always_gives_the_same_settings() {
+        for seed in [0, 1, 42, SEED_LIMIT - 1] {
+            assert_eq!(surprise(seed).layers(), surprise(seed).layers());
+        }
+        assert_ne!(surprise(1).layers(), surprise(2).layers());
+    }
+
+    #[test]
+    fn surprises_enable_a_few_layers() {
+        for seed in 0..200 {
+            let count = enabled(&surprise(seed));
+            assert!(
+                (MIN_ENABLED_LAYERS..=MAX_ENABLED_LAYERS).contains(&count),
+                "seed {} enabled {}",
+                seed,
+                count
+            );
+        }
+    }
+
+    #[test]
+    fn surprises_stay_in_the_tasteful_ranges() {
+        for seed in 0..200 {
+            for (shape, axis, gorgon) in surprise(seed).layers() {
+                for param in TUNABLE {
+                    let (low, high) = param.meta().tasteful_range();
+                    let value = gorgon.get(param);
+                    assert!(
+                        (low..=high).contains(&value),
+                        "seed {} {:?} {:?} {:?} = {}",
+                        seed,
+                        shape,
+                        axis,
+                        param,
+                        value
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mutations_keep_a_few_layers_enabled() {
+        for seed in 0..200 {
+            let mut settings = surprise(seed);
+            mutate(&mut settings, seed, MUTATION_AMOUNTS[2]);
+            let count = enabled(&settings);
+            assert!(
+                (MIN_ENABLED_LAYERS..=MAX_ENABLED_LAYERS).contains(&count),
+                "seed {} enabled {}",
+                seed,
+                count
+            );
+        }
+    }
+}
@@ -5,6 +5,8 @@ use gl_thin::gl_fancy::{BoundBuffers, GPUState, VertexBufferBundle};
 use gl_thin::gl_helper::GLErrorWrapper;
 use gl_thin::linear::XrMatrix4x4f;
 
+/// The monkey head, lit by `bob_shaders`' [SunPhongShader].
+/// That program is compiled by `bob_shaders` as GLSL ES 1.00, without [crate::glsl_dialect]'s translation.
 pub struct Suzanne {
     phong: SunPhongShader,
     buffers: VertexBufferBundle<'static, GLfloat, GLushort>,
@@ -0,0 +1,325 @@
+use crate::fonts::FontChain;
+use rusttype::{point, Font, GlyphId, Point, Scale, VMetrics};
+use std::ops::Range;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// How [layout] arranges a block of text
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TextStyle {
+    /// lines wider than this (in pixels) are wrapped at a space, or mid-word if there is none.
+    /// `None` only breaks lines at newlines.
+    pub max_width: Option<f32>,
+    /// where each line sits within the width of the widest one
+    pub align: Align,
+    /// the distance between baselines, as a multiple of the font's own line height
+    pub line_spacing: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            align: Align::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+impl TextStyle {
+    pub const fn centered() -> Self {
+        Self {
+            max_width: None,
+            align: Align::Center,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// Stands in for an inline picture (see [crate::markup]).
+/// It is laid out like a glyph as wide as the font's ascent, and wraps like a letter.
+pub const ICON_CHAR: char = '\u{FFFC}';
+
+/// one glyph of [LaidOutText]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PlacedGlyph {
+    /// which font of the [FontChain] has this glyph (see [FontChain::font])
+    pub font: usize,
+    pub id: GlyphId,
+    pub scale: Scale,
+    /// the pen position on the baseline, in pixels from the top left corner of the block
+    pub position: Point<f32>,
+    /// the byte offset in the text of the char this glyph is for
+    pub source: usize,
+}
+
+pub struct LaidOutText {
+    pub glyphs: Vec<PlacedGlyph>,
+    /// width and height in pixels
+    pub size: [f32; 2],
+}
+
+/// the fonts and size of the char at a byte offset in the text being laid out
+pub type StyleAt<'a> = dyn Fn(usize) -> (&'static FontChain, Scale) + 'a;
+
+/// Break `text` into lines at newlines and wherever it is wider than [TextStyle::max_width],
+/// and place every glyph.  This needs no GL context, so it can also measure text before it is drawn.
+pub fn layout(
+    fonts: &'static FontChain,
+    scale: Scale,
+    text: &str,
+    style: &TextStyle,
+) -> LaidOutText {
+    layout_mixed(text, &|_| (fonts, scale), style)
+}
+
+/// Like [layout], but the fonts and size of each char can differ: `style_at` is given its byte offset in `text`.
+/// Each line is as tall as its biggest char.
+pub fn layout_mixed(text: &str, style_at: &StyleAt, style: &TextStyle) -> LaidOutText {
+    let mut lines = vec![];
+    let mut start = 0;
+    for paragraph in text.split('\n') {
+        let range = start..start + paragraph.len();
+        start = range.end + 1;
+        match style.max_width {
+            Some(max_width) => lines.extend(wrap(text, range, style_at, max_width)),
+            None => lines.push(range),
+        }
+    }
+    let widths: Vec<f32> = lines
+        .iter()
+        .map(|line| line_width(text, line.clone(), style_at))
+        .collect();
+    let width = widths.iter().copied().fold(0.0, f32::max);
+
+    let mut glyphs = vec![];
+    let mut top = 0.0;
+    let mut bottom = 0.0;
+    for (line, line_width) in lines.iter().zip(&widths) {
+        let x = match style.align {
+            Align::Left => 0.0,
+            Align::Center => (width - line_width) / 2.0,
+            Align::Right => width - line_width,
+        };
+        let metrics = line_metrics(text, line.clone(), style_at);
+        let baseline = top + metrics.ascent;
+        glyphs.extend(advance_pen(text, line.clone(), style_at).map(
+            |(source, font, id, scale, pen, _)| PlacedGlyph {
+                font,
+                id,
+                scale,
+                position: point(x + pen, baseline),
+                source,
+            },
+        ));
+        bottom = baseline - metrics.descent;
+        top += (metrics.ascent - metrics.descent + metrics.line_gap) * style.line_spacing;
+    }
+
+    LaidOutText {
+        glyphs,
+        size: [width.ceil(), bottom.ceil()],
+    }
+}
+
+/// the width and height in pixels [layout] would make `text`
+pub fn measure(fonts: &'static FontChain, scale: Scale, text: &str, style: &TextStyle) -> [f32; 2] {
+    layout(fonts, scale, text, style).size
+}
+
+/// the vertical metrics of the primary font, at its biggest, of `text[line]`
+fn line_metrics(text: &str, line: Range<usize>, style_at: &StyleAt) -> VMetrics {
+    let v_metrics = |offset: usize| {
+        let (fonts, scale) = style_at(offset);
+        fonts.primary().v_metrics(scale)
+    };
+    let start = line.start;
+    text[line]
+        .char_indices()
+        .map(|(offset, _)| v_metrics(start + offset))
+        .fold(v_metrics(start), |a, b| VMetrics {
+            ascent: a.ascent.max(b.ascent),
+            descent: a.descent.min(b.descent),
+            line_gap: a.line_gap.max(b.line_gap),
+        })
+}
+
+/// Each char of `text[line]` with its byte offset in `text`, the index of its font in its [FontChain],
+/// its glyph and scale, and the pen's x position before and after it.
+/// Kerning is applied between neighbouring glyphs of the same font and size.
+fn advance_pen<'a>(
+    text: &'a str,
+    line: Range<usize>,
+    style_at: &'a StyleAt<'a>,
+) -> impl Iterator<Item = (usize, usize, GlyphId, Scale, f32, f32)> + 'a {
+    let mut x = 0.0;
+    let mut prev: Option<(&Font, GlyphId, Scale)> = None;
+    let start = line.start;
+    text[line].char_indices().map(move |(offset, c)| {
+        let source = start + offset;
+        let (fonts, scale) = style_at(source);
+        let (font_idx, id, advance) = if c == ICON_CHAR {
+            (0, GlyphId(0), fonts.primary().v_metrics(scale).ascent)
+        } else {
+            let (font_idx, glyph) = fonts.glyph(c);
+            let glyph = glyph.scaled(scale);
+            (font_idx, glyph.id(), glyph.h_metrics().advance_width)
+        };
+        let font = fonts.font(font_idx);
+        if let Some((prev_font, prev_id, prev_scale)) = prev {
+            if std::ptr::eq(prev_font, font) && prev_scale == scale && c != ICON_CHAR {
+                x += font.pair_kerning(scale, prev_id, id);
+            }
+        }
+        let pen = x;
+        x += advance;
+        prev = Some((font, id, scale));
+        (source, font_idx, id, scale, pen, x)
+    })
+}
+
+/// from the first pen position to the end of the last glyph's advance, in pixels
+fn line_width(text: &str, line: Range<usize>, style_at: &StyleAt) -> f32 {
+    advance_pen(text, line, style_at)
+        .last()
+        .map(|(.., end)| end)
+        .unwrap_or(0.0)
+}
+
+/// Greedily fill lines no wider than `max_width`, breaking `text[paragraph]` at spaces.
+/// The lines are ranges of `text`.  Runs of spaces are kept, so indentation survives,
+/// but the spaces where a line breaks are dropped rather than becoming a blank line or indenting the next one.
+fn wrap(
+    text: &str,
+    paragraph: Range<usize>,
+    style_at: &StyleAt,
+    max_width: f32,
+) -> Vec<Range<usize>> {
+    let mut lines = vec![];
+    let mut line = paragraph.start..paragraph.start;
+    let mut word_start = paragraph.start;
+    for (i, word) in text[paragraph].split(' ').enumerate() {
+        let word_range = word_start..word_start + word.len();
+        word_start = word_range.end + 1;
+        if i > 0 {
+            if line_width(text, line.start..word_range.end, style_at) <= max_width {
+                line.end = word_range.end;
+                continue;
+            }
+            // another of a run of spaces; it hangs off the end of the line
+            if word.is_empty() {
+                continue;
+            }
+            let kept = text[line.clone()].trim_end_matches(' ').len();
+            if kept > 0 {
+                lines.push(line.start..line.start + kept);
+            }
+            line = word_range.start..word_range.start;
+        }
+        // a word too long for a line of its own is broken wherever it has to be
+        for (offset, c) in word.char_indices() {
+            let end = word_range.start + offset + c.len_utf8();
+            if !line.is_empty() && line_width(text, line.start..end, style_at) > max_width {
+                lines.push(line);
+                line = word_range.start + offset..end;
+            } else {
+                line.end = end;
+            }
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::{self, FontRole};
+
+    const SCALE: Scale = Scale { x: 20.0, y: 20.0 };
+
+    fn fonts() -> &'static FontChain {
+        fonts::registry().chain(FontRole::Regular)
+    }
+
+    fn width(text: &str) -> f32 {
+        line_width(text, 0..text.len(), &|_| (fonts(), SCALE))
+    }
+
+    fn wrapped(text: &str, max_width: f32) -> Vec<&str> {
+        wrap(text, 0..text.len(), &|_| (fonts(), SCALE), max_width)
+            .into_iter()
+            .map(|line| &text[line])
+            .collect()
+    }
+
+    /// the x of the first glyph of each line, and the line's baseline
+    fn line_starts(laid_out: &LaidOutText) -> Vec<(f32, f32)> {
+        let mut rval: Vec<(f32, f32)> = vec![];
+        for glyph in &laid_out.glyphs {
+            let Point { x, y } = glyph.position;
+            if rval.last().map(|(_, baseline)| *baseline) != Some(y) {
+                rval.push((x, y));
+            }
+        }
+        rval
+    }
+
+    #[test]
+    fn wrap_fills_lines_greedily() {
+        let max_width = width("one two") + 1.0;
+        assert_eq!(wrapped("one two three", max_width), ["one two", "three"]);
+        assert_eq!(wrapped("one two", max_width), ["one two"]);
+    }
+
+    #[test]
+    fn wrap_breaks_long_words() {
+        let max_width = width("abc") + 1.0;
+        assert_eq!(wrapped("abcdef", max_width), ["abc", "def"]);
+    }
+
+    #[test]
+    fn wrap_makes_no_blank_lines_from_spaces() {
+        let max_width = width("one two") - 1.0;
+        assert_eq!(wrapped("one  two", max_width), ["one", "two"]);
+        assert_eq!(wrapped("one    two", max_width), ["one", "two"]);
+        assert_eq!(wrapped(" one", width(" on")), ["one"]);
+        // indentation which fits is kept
+        assert_eq!(wrapped("  one", width("  one") + 1.0), ["  one"]);
+    }
+
+    #[test]
+    fn newlines_start_new_lines() {
+        let laid_out = layout(fonts(), SCALE, "a\nb\n\nc", &TextStyle::default());
+        let starts = line_starts(&laid_out);
+        assert_eq!(starts.len(), 3);
+        let spacing = starts[1].1 - starts[0].1;
+        assert!(spacing > 0.0);
+        // the empty line still takes up room
+        assert!((starts[2].1 - starts[1].1 - spacing * 2.0).abs() < 0.01);
+        assert!(laid_out.size[1] > spacing * 3.0);
+    }
+
+    #[test]
+    fn lines_are_aligned_within_the_widest() {
+        let text = "abcdef\nab";
+        let (wide, narrow) = (width("abcdef"), width("ab"));
+        let starts = |align| {
+            let style = TextStyle {
+                align,
+                ..TextStyle::default()
+            };
+            line_starts(&layout(fonts(), SCALE, text, &style))
+        };
+        assert_eq!(starts(Align::Left)[1].0, 0.0);
+        assert_eq!(starts(Align::Center)[1].0, (wide - narrow) / 2.0);
+        assert_eq!(starts(Align::Right)[1].0, wide - narrow);
+        assert_eq!(starts(Align::Right)[0].0, 0.0);
+    }
+}
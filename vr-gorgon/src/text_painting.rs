@@ -1,238 +1,174 @@
-use gl::types::GLint;
-use gl_thin::gl_fancy::GPUState;
-use gl_thin::gl_helper::{GLErrorWrapper, Texture};
-use image::{ImageBuffer, Rgb, RgbImage};
-use rusttype::{point, Font, Point, PositionedGlyph, Scale};
-
-#[allow(dead_code)]
-pub fn text_to_greyscale_texture_old(
-    width: GLint,
-    height: GLint,
-    font_size: f32,
-    message: &str,
-    font: &Font,
-    gpu_state: &mut GPUState,
-) -> Result<Texture, GLErrorWrapper> {
-    let scale = Scale {
-        x: font_size,
-        y: font_size,
-    };
-
-    let offset = point(0.0, font.v_metrics(scale).ascent);
-
-    let glyphs: Vec<_> = font.layout(message, scale, offset).collect();
-
-    if true {
-        let width = glyphs
-            .iter()
-            .rev()
-            .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width)
-            .next()
-            .unwrap_or(0.0)
-            .ceil() as usize;
-
-        println!("width: {}, height: {}", width, font_size);
-    }
-
-    // let (width, height) = target.get_dimensions()?;
-    let target = Texture::new()?;
-
-    if false {
-        // this doesn't work on the oculus
-        let mut pixel_data = vec![99u8; (width * height) as usize];
-        render_glyphs_to_grey(width, height, &glyphs, &mut pixel_data);
-        target
-            .bound(gl::TEXTURE_2D, gpu_state)?
-            .write_pixels_and_generate_mipmap(
-                0,
-                gl::RGB as GLint,
-                width,
-                height,
-                gl::RED,
-                pixel_data.as_slice(),
-            )?;
-    } else {
-        let mut img = RgbImage::new(width as _, height as _);
-        render_glyphs_to_image(&glyphs, &mut img);
+use crate::fonts::{FontRegistry, FontRole};
+use crate::text_layout::{self, TextStyle};
+use image::RgbaImage;
+use rusttype::Scale;
+
+/// a run of text in one style, for [crate::glyph_atlas::GlyphAtlas::layout_spans].  [crate::markup] makes these from a string.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextSpan {
+    pub text: String,
+    pub color: [f32; 4],
+    pub bold: bool,
+    /// relative to the font size the spans are rendered at
+    pub size: f32,
+    /// draw this instead of the text, which should be a single [text_layout::ICON_CHAR]
+    pub icon: Option<Icon>,
+}
 
-        if true {
-            log::debug!(
-                "text pixels {:?} .. {:?}",
-                img.as_raw().iter().min(),
-                img.as_raw().iter().max()
-            );
+impl TextSpan {
+    pub fn new(text: impl Into<String>, color: [f32; 4]) -> Self {
+        Self {
+            text: text.into(),
+            color,
+            bold: false,
+            size: 1.0,
+            icon: None,
         }
-
-        target
-            .bound(gl::TEXTURE_2D, gpu_state)?
-            .write_pixels_and_generate_mipmap(
-                0,
-                gl::RGB as GLint,
-                width,
-                height,
-                gl::RGB,
-                img.as_raw(),
-            )?;
     }
-    Ok(target)
 }
 
-pub fn text_to_greyscale_texture(
-    font_size: f32,
-    message: &str,
-    font: &Font,
-    gpu_state: &mut GPUState,
-) -> Result<(Texture, usize, usize), GLErrorWrapper> {
-    let scale = Scale {
-        x: font_size,
-        y: font_size,
-    };
-
-    let metrics = font.v_metrics(scale);
-    let offset = point(0.0, metrics.ascent);
-
-    let glyphs: Vec<_> = font.layout(message, scale, offset).collect();
-
-    let mut width = glyphs
-        .iter()
-        .rev()
-        .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width)
-        .next()
-        .unwrap_or(0.0)
-        .ceil()
-        .max(1.0) as usize;
+/// Simple pictures which can sit in a line of text.  The embedded font has no symbols worth using.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Icon {
+    /// a filled circle, like an indicator lamp that is on
+    Dot,
+    /// an empty circle, like an indicator lamp that is off
+    Ring,
+    /// a filled square, for showing a color
+    Swatch,
+}
 
-    if width & 3 != 0 {
-        width = (width / 4 + 1) * 4;
+impl Icon {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dot" => Some(Icon::Dot),
+            "ring" => Some(Icon::Ring),
+            "swatch" => Some(Icon::Swatch),
+            _ => None,
+        }
     }
 
-    let height = (metrics.ascent - metrics.descent).ceil().max(1.0) as usize;
-
-    log::debug!(
-        "height = {} = {}-{}",
-        height,
-        metrics.ascent,
-        metrics.descent
-    );
-
-    log::debug!("width: {}, height: {}", width, height);
-
-    // let (width, height) = target.get_dimensions()?;
-    let target = Texture::new()?;
-
-    if false {
-        // this doesn't work on the oculus
-        let mut pixel_data = vec![99u8; (width * height) as usize];
-        render_glyphs_to_grey(width as i32, height as i32, &glyphs, &mut pixel_data);
-
-        target
-            .bound(gl::TEXTURE_2D, gpu_state)?
-            .write_pixels_and_generate_mipmap(
-                0,
-                gl::RGB as GLint,
-                width as _,
-                height as _,
-                gl::RED,
-                pixel_data.as_slice(),
-            )?;
-    } else {
-        let mut img = RgbImage::new(width as _, height as _);
-        render_glyphs_to_image(&glyphs, &mut img);
-
-        if true {
-            log::debug!(
-                "text pixels {:?} .. {:?}",
-                img.as_raw().iter().min(),
-                img.as_raw().iter().max()
-            );
+    /// coverage at `(x, y)`, in a `size`x`size` square whose top left corner is the origin
+    pub fn coverage(&self, x: f32, y: f32, size: f32) -> f32 {
+        let center = size / 2.0;
+        let distance = ((x - center).powi(2) + (y - center).powi(2)).sqrt();
+        let radius = size * 0.35;
+        // 1 inside, 0 outside, with a pixel of antialiasing between
+        let within = |edge: f32| (edge + 0.5).clamp(0.0, 1.0);
+        match self {
+            Icon::Dot => within(radius - distance),
+            Icon::Ring => within(size * 0.06 - (distance - radius).abs()),
+            Icon::Swatch => {
+                let inset = size * 0.15;
+                within(
+                    (x - inset)
+                        .min(size - inset - x)
+                        .min((y - inset).min(size - inset - y)),
+                )
+            }
         }
-
-        target
-            .bound(gl::TEXTURE_2D, gpu_state)?
-            .write_pixels_and_generate_mipmap(
-                0,
-                gl::RGB as GLint,
-                width as _,
-                height as _,
-                gl::RGB,
-                img.as_raw(),
-            )?;
     }
-    Ok((target, width, height))
 }
 
-pub fn render_glyphs_to_grey<'a, 'f: 'a>(
-    width: i32,
-    height: i32,
-    glyphs: impl IntoIterator<Item = &'a PositionedGlyph<'f>>,
-    pixel_data: &mut [u8],
-) {
-    for g in glyphs {
-        if let Some(bb) = g.pixel_bounding_box() {
-            g.draw(|x0, y0, v| {
-                let x = x0 as i32 + bb.min.x;
-                let y = y0 as i32 + bb.min.y;
-                if x >= 0 && x < width && y >= 0 && y < height {
-                    let idx = x + y * width;
-                    pixel_data[idx as usize] = ((1.0 - v) * 255.9) as u8;
-                }
-            })
+/// where neighbouring glyphs overlap, the more opaque one wins
+fn paint_pixel(image: &mut RgbaImage, x: i32, y: i32, rgb: [u8; 3], alpha: u8) {
+    if x < 0 || y < 0 {
+        return;
+    }
+    if let Some(pix) = image.get_pixel_mut_checked(x as u32, y as u32) {
+        if alpha >= pix.0[3] {
+            pix.0 = [rgb[0], rgb[1], rgb[2], alpha];
         }
     }
 }
 
-#[allow(dead_code)]
-pub fn render_glyphs_to_rgb<'a, 'f: 'a>(
-    width: i32,
-    height: i32,
-    glyphs: impl IntoIterator<Item = &'a PositionedGlyph<'f>>,
-    pixel_data: &mut [u8],
-) {
-    for g in glyphs {
-        if let Some(bb) = g.pixel_bounding_box() {
-            g.draw(|x0, y0, v| {
-                let x = x0 as i32 + bb.min.x;
-                let y = y0 as i32 + bb.min.y;
-                if x >= 0 && x < width && y >= 0 && y < height {
-                    let idx = (3 * (x + y * width)) as usize;
-                    let a = (v * 255.9) as u8;
-                    pixel_data[idx] = a;
-                    pixel_data[idx + 1] = a;
-                    pixel_data[idx + 2] = a;
+/// Paint `spans` one after the other, laid out according to `style`, each in its own style.
+/// The coverage goes into the alpha channel, so the result can be drawn over anything
+/// (unlike the [crate::glyph_atlas::GlyphAtlas], whose distance fields need a shader to turn into coverage).
+/// Upload it with [crate::sprites::with_unpack_alignment], like any of our images.
+pub fn render_spans_to_rgba(
+    fonts: &'static FontRegistry,
+    font_size: f32,
+    spans: &[TextSpan],
+    style: &TextStyle,
+) -> RgbaImage {
+    let text: String = spans.iter().map(|span| span.text.as_str()).collect();
+    // the byte offset in `text` where each span ends
+    let ends: Vec<usize> = spans
+        .iter()
+        .scan(0, |end, span| {
+            *end += span.text.len();
+            Some(*end)
+        })
+        .collect();
+    let span_at = |offset: usize| {
+        ends.iter()
+            .position(|end| offset < *end)
+            .map(|idx| &spans[idx])
+    };
+    let role = |span: &TextSpan| {
+        if span.bold {
+            FontRole::Bold
+        } else {
+            FontRole::Regular
+        }
+    };
+    let style_at = |offset: usize| match span_at(offset) {
+        Some(span) => (
+            fonts.chain(role(span)),
+            Scale::uniform(font_size * span.size),
+        ),
+        None => (fonts.chain(FontRole::Regular), Scale::uniform(font_size)),
+    };
+
+    let laid_out = text_layout::layout_mixed(&text, &style_at, style);
+    let [width, height] = laid_out.size;
+    let mut image = RgbaImage::new(width.max(1.0) as u32, height.max(1.0) as u32);
+
+    for glyph in &laid_out.glyphs {
+        let span = match span_at(glyph.source) {
+            Some(span) => span,
+            None => continue,
+        };
+        let chain = fonts.chain(role(span));
+        let [r, g, b, a] = span
+            .color
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let alpha = |v: f32| (v * a as f32) as u8;
+
+        if let Some(icon) = span.icon {
+            let size = chain.primary().v_metrics(glyph.scale).ascent;
+            let left = glyph.position.x.round() as i32;
+            let top = (glyph.position.y - size).round() as i32;
+            for y in 0..size.ceil() as i32 {
+                for x in 0..size.ceil() as i32 {
+                    let v = icon.coverage(x as f32 + 0.5, y as f32 + 0.5, size);
+                    paint_pixel(&mut image, left + x, top + y, [r, g, b], alpha(v));
                 }
-            })
+            }
+            continue;
         }
-    }
-}
 
-pub fn render_glyphs_to_image<'a, 'f: 'a>(
-    glyphs: impl IntoIterator<Item = &'a PositionedGlyph<'f>>,
-    image: &mut RgbImage,
-) {
-    for g in glyphs {
-        if let Some(bb) = g.pixel_bounding_box() {
-            g.draw(|x0, y0, v| {
-                let x = x0 as i32 + bb.min.x;
-                let y = y0 as i32 + bb.min.y;
-                if x >= 0 && y >= 0 {
-                    if let Some(pix) = image.get_pixel_mut_checked(x as u32, y as u32) {
-                        let a = (v * 255.9) as u8;
-                        pix.0 = [a, a, a];
-                    }
+        // without a bold font, bold is faked by smearing each glyph sideways
+        let smear = if span.bold && !fonts.has_fonts_for(FontRole::Bold) {
+            (glyph.scale.x / 20.0).ceil() as i32
+        } else {
+            0
+        };
+        let positioned = chain
+            .font(glyph.font)
+            .glyph(glyph.id)
+            .scaled(glyph.scale)
+            .positioned(glyph.position);
+        if let Some(bb) = positioned.pixel_bounding_box() {
+            positioned.draw(|x0, y0, v| {
+                for dx in 0..=smear {
+                    let x = x0 as i32 + bb.min.x + dx;
+                    let y = y0 as i32 + bb.min.y;
+                    paint_pixel(&mut image, x, y, [r, g, b], alpha(v));
                 }
             })
         }
     }
-}
-
-pub fn paint_text_in_image(
-    font: &Font,
-    img: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
-    scale: f32,
-    offset: Point<f32>,
-    msg: &str,
-) {
-    let glyphs: Vec<_> = font.layout(msg, Scale::uniform(scale), offset).collect();
-
-    render_glyphs_to_image(&glyphs, img);
+    image
 }
@@ -0,0 +1,112 @@
+/// The colors and proportions of the control panel and error overlay.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// text
+    pub fg: [f32; 4],
+    /// text which wants attention: enabled layers, errors, rejected keypad entries
+    pub highlight: [f32; 4],
+    /// the box behind each piece of text, and the face of a button
+    pub bg: [f32; 4],
+    /// the backdrop behind the whole panel, which keeps it readable over a busy gorgon
+    pub panel: [f32; 4],
+    /// the border around the panel and buttons, and the separators between sections
+    pub frame: [f32; 4],
+    /// the seed of the last surprise, and the filled part of a slider
+    pub accent: [f32; 4],
+    /// the indicator of an enabled layer, and the mark in a checked checkbox
+    pub on: [f32; 4],
+    /// the indicator of a disabled layer, and the empty part of a slider
+    pub off: [f32; 4],
+    /// the ring around the widget under the cursor
+    pub outline: [f32; 4],
+    /// of the ring, as a fraction of its width and height
+    pub outline_thickness: f32,
+    /// How much of the box it is fitted into text fills.  Below 1 leaves a margin around it.
+    pub font_scale: f32,
+}
+
+impl Theme {
+    /// black on translucent white, which is what the panel has always looked like
+    pub const LIGHT: Theme = Theme {
+        name: "light",
+        fg: [0.0, 0.0, 0.0, 1.0],
+        highlight: [1.0, 0.0, 0.0, 1.0],
+        bg: [1.0, 1.0, 1.0, 0.5],
+        panel: [0.92, 0.92, 0.92, 0.6],
+        frame: [0.35, 0.35, 0.4, 0.9],
+        accent: [0.1, 0.3, 0.8, 1.0],
+        on: [0.1, 0.6, 0.2, 1.0],
+        off: [0.5, 0.5, 0.5, 1.0],
+        outline: [1.0; 4],
+        outline_thickness: 0.06,
+        font_scale: 1.0,
+    };
+
+    /// light on translucent charcoal, which is easier on the eyes over a dark gorgon
+    pub const DARK: Theme = Theme {
+        name: "dark",
+        fg: [0.9, 0.9, 0.9, 1.0],
+        highlight: [1.0, 0.55, 0.3, 1.0],
+        bg: [0.08, 0.08, 0.1, 0.6],
+        panel: [0.03, 0.03, 0.05, 0.75],
+        frame: [0.55, 0.55, 0.6, 1.0],
+        accent: [0.5, 0.7, 1.0, 1.0],
+        on: [0.4, 0.9, 0.5, 1.0],
+        off: [0.55, 0.55, 0.55, 1.0],
+        outline: [1.0, 0.85, 0.3, 1.0],
+        outline_thickness: 0.06,
+        font_scale: 0.9,
+    };
+
+    /// White and yellow on opaque black, with a thick outline, so nothing behind the panel shows through
+    pub const HIGH_CONTRAST: Theme = Theme {
+        name: "high-contrast",
+        fg: [1.0; 4],
+        highlight: [1.0, 1.0, 0.0, 1.0],
+        bg: [0.0, 0.0, 0.0, 1.0],
+        panel: [0.0, 0.0, 0.0, 1.0],
+        frame: [1.0, 1.0, 0.0, 1.0],
+        accent: [0.0, 1.0, 1.0, 1.0],
+        on: [0.0, 1.0, 0.0, 1.0],
+        off: [1.0; 4],
+        outline: [1.0, 1.0, 0.0, 1.0],
+        outline_thickness: 0.12,
+        font_scale: 1.0,
+    };
+
+    pub const ALL: [Theme; 3] = [Theme::LIGHT, Theme::DARK, Theme::HIGH_CONTRAST];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|theme| theme.name == name)
+    }
+
+    /// the next built-in theme, or the previous one if `step` is negative
+    pub fn cycle(&self, step: isize) -> Self {
+        let idx = Self::ALL
+            .iter()
+            .position(|theme| theme.name == self.name)
+            .unwrap_or(0);
+        Self::ALL[(idx as isize + step).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+
+    pub fn fg_for(&self, highlight: bool) -> &[f32; 4] {
+        if highlight {
+            &self.highlight
+        } else {
+            &self.fg
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::LIGHT
+    }
+}
+
+/// `color` as `#rrggbb`, for [crate::markup]
+pub fn markup_color(color: &[f32; 4]) -> String {
+    let [r, g, b, _] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
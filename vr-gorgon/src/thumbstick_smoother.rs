@@ -8,6 +8,8 @@ const REPEAT_PERIOD: f32 = 0.5;
 pub struct ThumbstickSmoother {
     last_pulse: Option<Instant>,
     last_state: Ordering,
+    /// pulse again every [REPEAT_PERIOD] while the stick stays deflected
+    autorepeat: bool,
 }
 
 impl Default for ThumbstickSmoother {
@@ -15,11 +17,20 @@ impl Default for ThumbstickSmoother {
         Self {
             last_pulse: None,
             last_state: Ordering::Equal,
+            autorepeat: true,
         }
     }
 }
 
 impl ThumbstickSmoother {
+    /// one pulse per deflection, however long the stick is held there
+    pub fn edge_triggered() -> Self {
+        Self {
+            autorepeat: false,
+            ..Self::default()
+        }
+    }
+
     pub fn smooth_input(&mut self, delta: f32) -> Ordering {
         match self.last_state {
             Ordering::Greater => {
@@ -68,6 +79,9 @@ impl ThumbstickSmoother {
     }
 
     fn maybe_autorepeat(&mut self, repeat_val: Ordering) -> Ordering {
+        if !self.autorepeat {
+            return Ordering::Equal;
+        }
         let now = Instant::now();
         match self.last_pulse {
             None => {
@@ -85,3 +99,22 @@ impl ThumbstickSmoother {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_triggered_pulses_once_per_deflection() {
+        let mut smoother = ThumbstickSmoother::edge_triggered();
+        let pulses: Vec<Ordering> = [0.0, 0.8, 0.9, 0.8, 0.3, 0.1, 0.7, -0.6, -0.9]
+            .into_iter()
+            .map(|delta| smoother.smooth_input(delta))
+            .collect();
+        use Ordering::*;
+        assert_eq!(
+            pulses,
+            [Equal, Greater, Equal, Equal, Equal, Equal, Greater, Less, Equal]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use crate::control_panel::SpriteRectG;
+use crate::glyph_atlas::GlyphAtlas;
+use crate::menu::PanelRect;
+use crate::sprite_atlas::{SpriteAtlas, SpriteAtlasBuilder};
+use crate::sprites::SpriteLocation;
+use gl_thin::gl_fancy::GPUState;
+use gl_thin::gl_helper::GLErrorWrapper;
+use gl_thin::linear::{xr_matrix4x4f_create_translation, XrMatrix4x4f};
+use image::RgbaImage;
+
+/// The corners of a nine-slice image are this fraction of its width and height.
+/// They are drawn at a fixed size, and only the parts between them stretch.
+pub const NINE_SLICE_INSET: f32 = 0.25;
+/// the size the built-in widget images are generated at
+const IMAGE_PIXELS: u32 = 64;
+/// the width of the line around `rounded-border`, in pixels
+const BORDER_PIXELS: f32 = 4.0;
+/// the rounded corners of the panel's backdrop, in panel units
+const PANEL_CORNER: f32 = 0.12;
+
+/// Add the images the widgets are drawn from, all white so they can be tinted:
+/// `rounded` (a filled rounded rectangle), `rounded-border` (its outline) and `check-mark`.
+/// The first two are nine-slice images; see [NINE_SLICE_INSET].
+pub fn add_builtin_images(builder: &mut SpriteAtlasBuilder) {
+    let size = IMAGE_PIXELS as f32;
+    let radius = size * NINE_SLICE_INSET;
+    builder.add_image(
+        "rounded",
+        white_image(IMAGE_PIXELS, |x, y| {
+            coverage(-rounded_rect_distance(x, y, size, radius))
+        }),
+    );
+    builder.add_image(
+        "rounded-border",
+        white_image(IMAGE_PIXELS, |x, y| {
+            let distance = rounded_rect_distance(x, y, size, radius);
+            coverage(BORDER_PIXELS / 2.0 - (distance + BORDER_PIXELS / 2.0).abs())
+        }),
+    );
+    let stroke = [[0.22, 0.52], [0.42, 0.74], [0.8, 0.28]].map(|[x, y]| [x * size, y * size]);
+    builder.add_image(
+        "check-mark",
+        white_image(IMAGE_PIXELS, |x, y| {
+            let distance = stroke
+                .windows(2)
+                .map(|pair| segment_distance([x, y], pair[0], pair[1]))
+                .fold(f32::MAX, f32::min);
+            coverage(size * 0.06 - distance)
+        }),
+    );
+}
+
+/// a `size`x`size` white image whose alpha is `alpha_at` the center of each pixel
+fn white_image(size: u32, alpha_at: impl Fn(f32, f32) -> f32) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| {
+        let alpha = alpha_at(x as f32 + 0.5, y as f32 + 0.5);
+        image::Rgba([255, 255, 255, (alpha * 255.0).round() as u8])
+    })
+}
+
+/// 1 inside, 0 outside, with a pixel of antialiasing between, for a signed distance to an edge (positive inside)
+fn coverage(edge: f32) -> f32 {
+    (edge + 0.5).clamp(0.0, 1.0)
+}
+
+/// how far `(x, y)` is outside a `size`x`size` square with corners of `radius` (negative inside)
+fn rounded_rect_distance(x: f32, y: f32, size: f32, radius: f32) -> f32 {
+    let half = size / 2.0;
+    let qx = (x - half).abs() - (half - radius);
+    let qy = (y - half).abs() - (half - radius);
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    outside + qx.max(qy).min(0.0) - radius
+}
+
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let t = ((ap[0] * ab[0] + ap[1] * ab[1]) / (ab[0] * ab[0] + ab[1] * ab[1])).clamp(0.0, 1.0);
+    let dx = ap[0] - ab[0] * t;
+    let dy = ap[1] - ab[1] * t;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// `matrix`, moved `dz` toward (negative) or away from the viewer, so overlapping parts of a widget do not clip each other
+fn nudge(matrix: &XrMatrix4x4f, dz: f32) -> XrMatrix4x4f {
+    matrix * xr_matrix4x4f_create_translation(0.0, 0.0, dz)
+}
+
+//
+
+/// Draws frames, buttons, sliders and checkboxes in the colors of `sprite`'s [crate::theme::Theme],
+/// from the images [add_builtin_images] put in `icons` (or PNGs that replaced them).
+pub struct Widgets<'a> {
+    pub sprite: &'a SpriteRectG,
+    pub icons: &'a SpriteAtlas,
+    pub atlas: &'a GlyphAtlas,
+}
+
+impl Widgets<'_> {
+    /// Image `name` stretched over `rect`.  Its corners are drawn `corner` panel units wide
+    /// (less if `rect` is too small for that), and only the parts between them stretch.
+    fn nine_slice(
+        &self,
+        matrix: &XrMatrix4x4f,
+        name: &str,
+        corner: f32,
+        tint: &[f32; 4],
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let sprite = match self.icons.sprite(name) {
+            Some(sprite) => sprite,
+            None => return Ok(()),
+        };
+        let corner = corner.min(rect.half_size[0]).min(rect.half_size[1]);
+        let [cx, cy] = rect.center;
+        let [hw, hh] = rect.half_size;
+        let xs = [cx - hw, cx - hw + corner, cx + hw - corner, cx + hw];
+        let ys = [cy - hh, cy - hh + corner, cy + hh - corner, cy + hh];
+        let fractions = [0.0, NINE_SLICE_INSET, 1.0 - NINE_SLICE_INSET, 1.0];
+        for row in 0..3 {
+            for column in 0..3 {
+                let half_size = [
+                    (xs[column + 1] - xs[column]) / 2.0,
+                    (ys[row + 1] - ys[row]) / 2.0,
+                ];
+                if half_size[0] <= 0.0 || half_size[1] <= 0.0 {
+                    continue;
+                }
+                let cell = PanelRect {
+                    center: [xs[column] + half_size[0], ys[row] + half_size[1]],
+                    half_size,
+                };
+                let part = SpriteLocation::new(
+                    [
+                        sprite.scale[0] * (fractions[column + 1] - fractions[column]),
+                        sprite.scale[1] * (fractions[row + 1] - fractions[row]),
+                    ],
+                    [
+                        sprite.offset[0] + sprite.scale[0] * fractions[column],
+                        sprite.offset[1] + sprite.scale[1] * fractions[row],
+                    ],
+                    sprite.texture,
+                );
+                self.sprite
+                    .draw_sprite_stretched(matrix, &part, tint, &cell, gpu_state)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// a bordered backdrop for a group of widgets, behind anything drawn at the same depth
+    pub fn panel(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let theme = self.sprite.theme();
+        let m_fill = nudge(matrix, 0.02);
+        self.nine_slice(
+            &m_fill,
+            "rounded",
+            PANEL_CORNER,
+            &theme.panel,
+            rect,
+            gpu_state,
+        )?;
+        let m_border = nudge(matrix, 0.019);
+        self.nine_slice(
+            &m_border,
+            "rounded-border",
+            PANEL_CORNER,
+            &theme.frame,
+            rect,
+            gpu_state,
+        )
+    }
+
+    /// a line across the middle of `rect`, as thick as `rect` is tall, behind anything drawn at the same depth
+    pub fn separator(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let frame = &self.sprite.theme().frame;
+        let corner = rect.half_size[1];
+        self.nine_slice(
+            &nudge(matrix, 0.01),
+            "rounded",
+            corner,
+            frame,
+            rect,
+            gpu_state,
+        )
+    }
+
+    /// the rounded face and border of a button, returning the part of `rect` inside the border
+    fn button_face(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        gpu_state: &mut GPUState,
+    ) -> Result<PanelRect, GLErrorWrapper> {
+        let theme = self.sprite.theme();
+        let corner = rect.half_size[0].min(rect.half_size[1]) * 0.4;
+        self.nine_slice(matrix, "rounded", corner, &theme.bg, rect, gpu_state)?;
+        let m_border = nudge(matrix, -0.001);
+        self.nine_slice(
+            &m_border,
+            "rounded-border",
+            corner,
+            &theme.frame,
+            rect,
+            gpu_state,
+        )?;
+        Ok(PanelRect {
+            center: rect.center,
+            half_size: rect.half_size.map(|half| half - corner * 0.5),
+        })
+    }
+
+    /// `label` on a rounded face; `highlight` colors the label like other highlighted text
+    pub fn button(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        label: &str,
+        highlight: bool,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let inset = self.button_face(matrix, rect, gpu_state)?;
+        self.sprite.draw_caption(
+            self.atlas,
+            label,
+            &nudge(matrix, -0.002),
+            &inset,
+            highlight,
+            gpu_state,
+        )
+    }
+
+    /// icon `name` on a rounded face, in the color of text
+    pub fn icon_button(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        name: &str,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let inset = self.button_face(matrix, rect, gpu_state)?;
+        self.sprite.draw_sprite(
+            &nudge(matrix, -0.002),
+            self.icons,
+            name,
+            &self.sprite.theme().fg,
+            &inset,
+            gpu_state,
+        )
+    }
+
+    /// a horizontal track across `rect`, filled from the left up to `fraction` (0..1), with a knob at the end of the fill
+    pub fn slider(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        fraction: f32,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let theme = self.sprite.theme();
+        let fraction = fraction.clamp(0.0, 1.0);
+        let track_half_height = rect.half_size[1] * 0.4;
+        let left = rect.center[0] - rect.half_size[0];
+        let track = PanelRect {
+            center: rect.center,
+            half_size: [rect.half_size[0], track_half_height],
+        };
+        self.nine_slice(
+            matrix,
+            "rounded",
+            track_half_height,
+            &theme.off,
+            &track,
+            gpu_state,
+        )?;
+
+        let knob_x = left + 2.0 * rect.half_size[0] * fraction;
+        let filled = PanelRect {
+            center: [(left + knob_x) / 2.0, rect.center[1]],
+            half_size: [(knob_x - left) / 2.0, track_half_height],
+        };
+        self.nine_slice(
+            &nudge(matrix, -0.001),
+            "rounded",
+            track_half_height,
+            &theme.accent,
+            &filled,
+            gpu_state,
+        )?;
+
+        let knob = PanelRect {
+            center: [knob_x, rect.center[1]],
+            half_size: [rect.half_size[1]; 2],
+        };
+        self.nine_slice(
+            &nudge(matrix, -0.002),
+            "rounded",
+            rect.half_size[1],
+            &theme.fg,
+            &knob,
+            gpu_state,
+        )
+    }
+
+    /// a box, with a check mark in it if `checked`
+    pub fn checkbox(
+        &self,
+        matrix: &XrMatrix4x4f,
+        rect: &PanelRect,
+        checked: bool,
+        gpu_state: &mut GPUState,
+    ) -> Result<(), GLErrorWrapper> {
+        let theme = self.sprite.theme();
+        let corner = rect.half_size[0].min(rect.half_size[1]) * 0.4;
+        self.nine_slice(matrix, "rounded", corner, &theme.bg, rect, gpu_state)?;
+        self.nine_slice(
+            &nudge(matrix, -0.001),
+            "rounded-border",
+            corner,
+            &theme.fg,
+            rect,
+            gpu_state,
+        )?;
+        if checked {
+            self.sprite.draw_sprite(
+                &nudge(matrix, -0.002),
+                self.icons,
+                "check-mark",
+                &theme.on,
+                rect,
+                gpu_state,
+            )?;
+        }
+        Ok(())
+    }
+}
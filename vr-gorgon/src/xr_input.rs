@@ -1,18 +1,43 @@
 use gl_thin::errors::{Wrappable, XrErrorWrapped};
 use gl_thin::openxr_helpers::Backend;
 use openxr::{
-    Action, ActionSet, ActionState, ActiveActionSet, Binding, Instance, Session, Space,
-    SpaceLocation,
+    Action, ActionSet, ActionState, ActiveActionSet, Binding, Instance, ReferenceSpaceType,
+    Session, Space, SpaceLocation, SpaceLocationFlags,
 };
 use openxr_sys::{Path, Posef, Time, Vector2f};
 
 pub struct XrInputs {
     pub action_set: ActionSet,
+    pub user_hand_left: Path,
     pub user_hand_right: Path,
     pub controller_1: Action<Posef>,
     pub controller_space_1: Space,
+    /// the left controller's aim pose, for the laser pointer
+    pub pointer: Action<Posef>,
+    pub pointer_space: Space,
+    pub left_trigger: Action<bool>,
+    /// for picking up the panel in [crate::panel_anchor::AnchorMode::Grabbable]
+    pub left_grip_space: Space,
+    pub left_squeeze: Action<bool>,
+    /// cycles through the [crate::panel_anchor::AnchorMode]s
+    pub left_thumbstick_click: Action<bool>,
+    /// enters and leaves [crate::evolution::Evolution] mode
+    pub left_menu_click: Action<bool>,
+    /// undo
+    pub x_click: Action<bool>,
+    /// redo
+    pub y_click: Action<bool>,
+    /// [crate::surprise::mutate]
+    pub right_thumbstick_click: Action<bool>,
+    /// chooses the mutation amount
+    pub right_trigger: Action<bool>,
     pub a_click: Action<bool>,
+    pub b_click: Action<bool>,
     pub right_joy: Action<Vector2f>,
+    /// picks the [crate::theme::Theme] and resizes the panel
+    pub left_joy: Action<Vector2f>,
+    /// the VIEW reference space, which is the head: between the eyes, looking down -Z
+    pub view_space: Space,
 }
 
 impl XrInputs {
@@ -35,20 +60,94 @@ impl XrInputs {
         let left_grip_pose = Self::path_for(instance, "/user/hand/left/input/grip/pose")?;
         let right_grip_pose = Self::path_for(instance, "/user/hand/right/input/grip/pose")?;
 
+        let left_aim_pose = Self::path_for(instance, "/user/hand/left/input/aim/pose")?;
+        let pointer_action = action_set
+            .create_action::<Posef>("pointer_pose", "laser pointer", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action laser pointer")?;
+
+        let left_select_click = Self::path_for(instance, "/user/hand/left/input/select/click")?;
+        let left_trigger_value = Self::path_for(instance, "/user/hand/left/input/trigger/value")?;
+        let left_trigger_action = action_set
+            .create_action("left_trigger", "left trigger", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action left trigger")?;
+
+        let left_squeeze_value = Self::path_for(instance, "/user/hand/left/input/squeeze/value")?;
+        let left_squeeze_action = action_set
+            .create_action("left_squeeze", "left squeeze", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action left squeeze")?;
+
+        let left_thumbstick_click =
+            Self::path_for(instance, "/user/hand/left/input/thumbstick/click")?;
+        let left_thumbstick_click_action = action_set
+            .create_action(
+                "left_thumbstick_click",
+                "left thumbstick click",
+                &[user_hand_left],
+            )
+            .annotate_if_err(
+                Some(instance),
+                "failed to create action left thumbstick click",
+            )?;
+
+        let left_menu_click = Self::path_for(instance, "/user/hand/left/input/menu/click")?;
+        let left_menu_click_action = action_set
+            .create_action("left_menu_click", "left menu click", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action left menu click")?;
+
+        let left_x_click = Self::path_for(instance, "/user/hand/left/input/x/click")?;
+        let x_click_action = action_set
+            .create_action("x_click", "X click", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action X click")?;
+
+        let left_y_click = Self::path_for(instance, "/user/hand/left/input/y/click")?;
+        let y_click_action = action_set
+            .create_action("y_click", "Y click", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create action Y click")?;
+
+        let right_thumbstick_click =
+            Self::path_for(instance, "/user/hand/right/input/thumbstick/click")?;
+        let right_thumbstick_click_action = action_set
+            .create_action(
+                "right_thumbstick_click",
+                "right thumbstick click",
+                &[user_hand_right],
+            )
+            .annotate_if_err(
+                Some(instance),
+                "failed to create action right thumbstick click",
+            )?;
+
+        let right_trigger_value = Self::path_for(instance, "/user/hand/right/input/trigger/value")?;
+        let right_trigger_action = action_set
+            .create_action("right_trigger", "right trigger", &[user_hand_right])
+            .annotate_if_err(Some(instance), "failed to create action right trigger")?;
+
         let right_a_click = Self::path_for(instance, "/user/hand/right/input/a/click")?;
         let a_click_action = action_set
             .create_action("a_click", "A click", &[user_hand_right])
             .annotate_if_err(Some(instance), "failed to create action A click")?;
 
+        let right_b_click = Self::path_for(instance, "/user/hand/right/input/b/click")?;
+        let b_click_action = action_set
+            .create_action("b_click", "B click", &[user_hand_right])
+            .annotate_if_err(Some(instance), "failed to create action B click")?;
+
         let right_thumbstick = Self::path_for(instance, "/user/hand/right/input/thumbstick")?;
         let right_thumbstick_action = action_set
             .create_action("right_thumbstick", "right thumbstick", &[user_hand_right])
             .annotate_if_err(Some(instance), "failed to create right_thumbstick")?;
 
+        let left_thumbstick = Self::path_for(instance, "/user/hand/left/input/thumbstick")?;
+        let left_thumbstick_action = action_set
+            .create_action("left_thumbstick", "left thumbstick", &[user_hand_left])
+            .annotate_if_err(Some(instance), "failed to create left_thumbstick")?;
+
         {
             let bindings = [
                 Binding::new(&pose_action, left_grip_pose),
                 Binding::new(&pose_action, right_grip_pose),
+                Binding::new(&pointer_action, left_aim_pose),
+                Binding::new(&left_trigger_action, left_select_click),
                 // Binding::new(&a_click_action, right_a_click),
             ];
             let interaction_profile =
@@ -64,7 +163,19 @@ impl XrInputs {
                 Binding::new(&pose_action, left_grip_pose),
                 Binding::new(&pose_action, right_grip_pose),
                 Binding::new(&a_click_action, right_a_click),
+                Binding::new(&b_click_action, right_b_click),
                 Binding::new(&right_thumbstick_action, right_thumbstick),
+                Binding::new(&left_thumbstick_action, left_thumbstick),
+                Binding::new(&pointer_action, left_aim_pose),
+                // the runtime applies its own threshold to turn the analog trigger into a bool
+                Binding::new(&left_trigger_action, left_trigger_value),
+                Binding::new(&left_squeeze_action, left_squeeze_value),
+                Binding::new(&left_thumbstick_click_action, left_thumbstick_click),
+                Binding::new(&left_menu_click_action, left_menu_click),
+                Binding::new(&x_click_action, left_x_click),
+                Binding::new(&y_click_action, left_y_click),
+                Binding::new(&right_thumbstick_click_action, right_thumbstick_click),
+                Binding::new(&right_trigger_action, right_trigger_value),
             ];
             let interaction_profile =
                 Self::path_for(instance, "/interaction_profiles/oculus/touch_controller")?;
@@ -78,6 +189,15 @@ impl XrInputs {
         let controller_space_1 = pose_action
             .create_space(xr_session.clone(), user_hand_right, posef)
             .annotate_if_err(Some(instance), "failed to ")?;
+        let pointer_space = pointer_action
+            .create_space(xr_session.clone(), user_hand_left, posef)
+            .annotate_if_err(Some(instance), "failed to create laser pointer space")?;
+        let left_grip_space = pose_action
+            .create_space(xr_session.clone(), user_hand_left, posef)
+            .annotate_if_err(Some(instance), "failed to create left grip space")?;
+        let view_space = xr_session
+            .create_reference_space(ReferenceSpaceType::VIEW, posef)
+            .annotate_if_err(Some(instance), "failed to create view space")?;
 
         //
 
@@ -87,11 +207,26 @@ impl XrInputs {
 
         Ok(Self {
             action_set,
+            user_hand_left,
             user_hand_right,
             controller_1: pose_action,
             controller_space_1,
+            pointer: pointer_action,
+            pointer_space,
+            left_trigger: left_trigger_action,
+            left_grip_space,
+            left_squeeze: left_squeeze_action,
+            left_thumbstick_click: left_thumbstick_click_action,
+            left_menu_click: left_menu_click_action,
+            x_click: x_click_action,
+            y_click: y_click_action,
+            right_thumbstick_click: right_thumbstick_click_action,
+            right_trigger: right_trigger_action,
             a_click: a_click_action,
+            b_click: b_click_action,
             right_joy: right_thumbstick_action,
+            left_joy: left_thumbstick_action,
+            view_space,
         })
     }
 
@@ -131,11 +266,106 @@ impl XrInputs {
         }
     }
 
+    pub fn pointer_locate_if_active<G>(
+        &self,
+        xr_session: &Session<G>,
+        base: &Space,
+        predicted_display_time: Time,
+    ) -> Option<SpaceLocation> {
+        if self
+            .pointer
+            .is_active(xr_session, self.user_hand_left)
+            .unwrap_or(false)
+        {
+            self.pointer_space.locate(base, predicted_display_time).ok()
+        } else {
+            None
+        }
+    }
+
+    /// the head's pose, if it is tracked
+    pub fn head_locate(&self, base: &Space, predicted_display_time: Time) -> Option<Posef> {
+        let location = self.view_space.locate(base, predicted_display_time).ok()?;
+        let valid = SpaceLocationFlags::POSITION_VALID | SpaceLocationFlags::ORIENTATION_VALID;
+        location
+            .location_flags
+            .contains(valid)
+            .then_some(location.pose)
+    }
+
+    /// the left grip pose, but only while the grip button is squeezed
+    pub fn left_grip_locate_if_squeezed<G>(
+        &self,
+        xr_session: &Session<G>,
+        base: &Space,
+        predicted_display_time: Time,
+    ) -> Option<SpaceLocation> {
+        let squeezed = self
+            .left_squeeze
+            .state(xr_session, self.user_hand_left)
+            .map(|state| state.current_state)
+            .unwrap_or(false);
+        if squeezed {
+            self.left_grip_space
+                .locate(base, predicted_display_time)
+                .ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn left_thumbstick_clicked<G>(
+        &self,
+        xr_session: &Session<G>,
+    ) -> openxr::Result<ActionState<bool>> {
+        self.left_thumbstick_click
+            .state(xr_session, self.user_hand_left)
+    }
+
+    pub fn left_trigger<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
+        self.left_trigger.state(xr_session, self.user_hand_left)
+    }
+
+    pub fn left_menu_clicked<G>(
+        &self,
+        xr_session: &Session<G>,
+    ) -> openxr::Result<ActionState<bool>> {
+        self.left_menu_click.state(xr_session, self.user_hand_left)
+    }
+
+    pub fn x_clicked<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
+        self.x_click.state(xr_session, self.user_hand_left)
+    }
+
+    pub fn y_clicked<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
+        self.y_click.state(xr_session, self.user_hand_left)
+    }
+
+    pub fn right_thumbstick_clicked<G>(
+        &self,
+        xr_session: &Session<G>,
+    ) -> openxr::Result<ActionState<bool>> {
+        self.right_thumbstick_click
+            .state(xr_session, self.user_hand_right)
+    }
+
+    pub fn right_trigger<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
+        self.right_trigger.state(xr_session, self.user_hand_right)
+    }
+
     pub fn a_clicked<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
         self.a_click.state(xr_session, self.user_hand_right)
     }
 
+    pub fn b_clicked<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<bool>> {
+        self.b_click.state(xr_session, self.user_hand_right)
+    }
+
     pub fn right_joy<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<Vector2f>> {
         self.right_joy.state(xr_session, self.user_hand_right)
     }
+
+    pub fn left_joy<G>(&self, xr_session: &Session<G>) -> openxr::Result<ActionState<Vector2f>> {
+        self.left_joy.state(xr_session, self.user_hand_left)
+    }
 }